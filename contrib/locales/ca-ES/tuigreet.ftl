@@ -1,3 +1,5 @@
+language_name = Català
+
 title_authenticate = Autenticació a {$hostname}
 title_command = Canvi d'ordre de sessió
 title_power = Opcions d'engegada
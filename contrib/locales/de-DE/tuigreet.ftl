@@ -1,3 +1,5 @@
+language_name = Deutsch
+
 title_authenticate = Bei {$hostname} authentifizieren
 title_command = Sitzungsbefehl ändern
 title_power = Energieeinstellungen
@@ -1,28 +1,116 @@
+language_name = English
+
 title_authenticate = Authenticate into {$hostname}
 title_command = Change session command
+title_environment = Change session environment
 title_power = Power options
 title_session = Change session
 title_users = Select a user
+title_layout = Keyboard layout
+title_locale = Language
 
 action_reset = Reset
 action_command = Change command
 action_session = Choose session
 action_power = Power
+action_layout = Layout
+action_locale = Language
+action_dismiss = Dismiss
 
 date = %a, %d %h %Y - %H:%M
 
 select_user = Press Enter to select a user or start typing...
 username = Username:
 wait = Please wait...
+canceling = Canceling...
+autologin = Logging in as {$username} in {$seconds}s...
+autologin_cancel = Press any key to cancel
 failed = Authentication failed, please try again.
+paste_denied = Secrets cannot be pasted, please type them.
+password_too_short = too short
+password_too_long = too long
+password_too_simple = needs {$classes} kinds of characters
+failed_remaining = Authentication failed, {$remaining ->
+    [one] one attempt is left
+   *[other] {$remaining} attempts are left
+  } before input is locked out.
+lockout = {$attempts ->
+    [one] Authentication failed
+   *[other] {$attempts} failed attempts
+  }, try again in {$seconds}s.
+last_login = Last login: {$time} on {$line}
+last_login_from = Last login: {$time} on {$line} from {$host}
+failed_logins = {$count ->
+    [one] One failed attempt
+   *[other] {$count} failed attempts
+  } since the last login.
+uptime = {$days ->
+    [0] {$time}
+    [one] 1 day, {$time}
+   *[other] {$days} days, {$time}
+  }
+welcome = Welcome
+welcome_back = Welcome back, {$user}
+user_count = {$count ->
+    [0] No users
+    [one] One user
+   *[other] {$count} users
+  }
+terminal_too_small = The terminal is too small ({$width}x{$height}), it must be at least {$min_width}x{$min_height}.
+
+plain_sessions_hint = Leave the username empty to choose a session.
+plain_session = Session number:
+plain_autologin_cancel = Press Enter to cancel.
 
 new_command = New command:
+new_environment = Variables:
+invalid_environment = Invalid environment variable assignment: {$assignment}
+
+osk_shift = Shift
+osk_space = Space
+osk_backspace = Backspace
+osk_enter = Enter
 
 shutdown = Shut down
 reboot = Reboot
+suspend = Suspend
+hibernate = Hibernate
+hybrid_sleep = Hybrid sleep
+power_confirm = {$action} in {$seconds}s...
+power_confirm_hint = Press Enter to confirm or Escape to cancel
+device_cancel_hint = Press Escape to cancel
 
 command_exited = Command exited with
 command_failed = Command failed
+command_timed_out = Command timed out
+reload_failed = Could not reload the configuration
+ipc_socket_not_found = greetd socket not found at {$socket}, is greetd running?
+ipc_socket_failed = Could not communicate with greetd at {$socket}: {$error}
+ipc_closed = greetd closed the connection
+ipc_session_failed = Session start failed: {$error}
+ipc_greetd_error = greetd error: {$error}
+session_exited = The session {$session} exited right after starting.
+session_retry = Press Enter to try again, or {$key} to choose another session.
+command_not_found = Command not found: {$command}
+command_not_found_hint = Press Enter to start it anyway, or {$command} or {$sessions} to change it.
+
+network_down = Offline
+network_wired = Wired
+network_wireless = Wi-Fi
+network_wireless_ssid = Wi-Fi ({$ssid})
+
+battery = {$capacity}%
+battery_charging = {$capacity}%, charging
 
 status_command = CMD
+status_layout = KBD
+status_network = NET
+status_battery = BAT
+status_num = NUM LOCK
 status_caps = CAPS LOCK
+health_disk = Only {$free}% of disk space is left on the root filesystem.
+health_clock = The hardware clock is {$seconds}s away from the system time.
+health_units = {$count ->
+    [one] One system service failed.
+   *[other] {$count} system services failed.
+  }
@@ -1,28 +1,116 @@
+language_name = Français
+
 title_authenticate = Se connecter à {$hostname}
 title_command = Changer la commande de session
+title_environment = Changer l'environnement de session
 title_power = Options d'alimentation
 title_session = Changer la session
 title_users = Choisissez un utilisateur
+title_layout = Disposition du clavier
+title_locale = Langue
 
 action_reset = Réinitialiser
 action_command = Changer la commande
 action_session = Choisir la session
 action_power = Alimentation
+action_layout = Clavier
+action_locale = Langue
+action_dismiss = Ignorer
 
 date = %a %d %h %Y - %H:%M
 
 select_user = Appuyez sur Entrée pour choisir un utilisateur ou tapez son nom...
 username = Nom d'utilisateur :
 wait = Veuillez patienter...
+canceling = Annulation...
+autologin = Connexion en tant que {$username} dans {$seconds} s...
+autologin_cancel = Appuyez sur une touche pour annuler
 failed = Erreur d'authentification, veuillez réessayer.
+paste_denied = Les secrets ne peuvent pas être collés, veuillez les saisir.
+password_too_short = trop court
+password_too_long = trop long
+password_too_simple = {$classes} types de caractères requis
+failed_remaining = Erreur d'authentification, {$remaining ->
+    [one] il reste une tentative
+   *[other] il reste {$remaining} tentatives
+  } avant le blocage de la saisie.
+lockout = {$attempts ->
+    [one] Erreur d'authentification
+   *[other] {$attempts} échecs
+  }, réessayez dans {$seconds}s.
+last_login = Dernière connexion : {$time} sur {$line}
+last_login_from = Dernière connexion : {$time} sur {$line} depuis {$host}
+failed_logins = {$count ->
+    [one] Une tentative échouée
+   *[other] {$count} tentatives échouées
+  } depuis la dernière connexion.
+uptime = {$days ->
+    [0] {$time}
+    [one] 1 jour, {$time}
+   *[other] {$days} jours, {$time}
+  }
+welcome = Bienvenue
+welcome_back = Bon retour, {$user}
+user_count = {$count ->
+    [0] Aucun utilisateur
+    [one] Un utilisateur
+   *[other] {$count} utilisateurs
+  }
+terminal_too_small = Le terminal est trop petit ({$width}x{$height}), il doit faire au moins {$min_width}x{$min_height}.
+
+plain_sessions_hint = Laissez le nom d'utilisateur vide pour choisir une session.
+plain_session = Numéro de session :
+plain_autologin_cancel = Appuyez sur Entrée pour annuler.
 
 command = Nouvelle commande :
+new_environment = Variables :
+invalid_environment = Affectation de variable d'environnement invalide : {$assignment}
+
+osk_shift = Maj
+osk_space = Espace
+osk_backspace = Effacer
+osk_enter = Entrée
 
 shutdown = Éteindre
 reboot = Redémarrer
+suspend = Mettre en veille
+hibernate = Hiberner
+hybrid_sleep = Veille hybride
+power_confirm = {$action} dans {$seconds}s...
+power_confirm_hint = Appuyez sur Entrée pour confirmer ou Échap pour annuler
+device_cancel_hint = Appuyez sur Échap pour annuler
 
 command_exited = La commande a retourné
 command_failed = Échec de la commande
+command_timed_out = La commande a expiré
+reload_failed = Impossible de recharger la configuration
+ipc_socket_not_found = Socket de greetd introuvable à {$socket}, greetd est-il lancé ?
+ipc_socket_failed = Impossible de communiquer avec greetd à {$socket} : {$error}
+ipc_closed = greetd a fermé la connexion
+ipc_session_failed = Échec du démarrage de la session : {$error}
+ipc_greetd_error = Erreur de greetd : {$error}
+session_exited = La session {$session} s'est arrêtée juste après son démarrage.
+session_retry = Appuyez sur Entrée pour réessayer, ou sur {$key} pour choisir une autre session.
+command_not_found = Commande introuvable : {$command}
+command_not_found_hint = Appuyez sur Entrée pour la lancer quand même, ou sur {$command} ou {$sessions} pour la changer.
+
+network_down = Hors ligne
+network_wired = Filaire
+network_wireless = Wi-Fi
+network_wireless_ssid = Wi-Fi ({$ssid})
+
+battery = {$capacity} %
+battery_charging = {$capacity} %, en charge
 
 status_command = CMD
+status_layout = CLAV.
+status_network = RÉS.
+status_battery = BAT
+status_num = VERR. NUM.
 status_caps = VERR. MAJ.
+health_disk = Il ne reste que {$free} % d'espace libre sur le système de fichiers racine.
+health_clock = L'horloge matérielle est décalée de {$seconds} s par rapport à l'heure système.
+health_units = {$count ->
+    [one] Un service système a échoué.
+   *[other] {$count} services système ont échoué.
+  }
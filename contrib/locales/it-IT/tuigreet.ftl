@@ -1,3 +1,5 @@
+language_name = Italiano
+
 title_authenticate = Accedi a {$hostname}
 title_command = Cambia comando sessione
 title_power = Opzioni alimentazione
@@ -1,3 +1,5 @@
+language_name = Polski
+
 title_authenticate = Uwierzytelnianie dla urządzenia {$hostname}
 title_command = Zmiana polecenia dla sesji
 title_power = Opcje zasilania
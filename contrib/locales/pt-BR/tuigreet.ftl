@@ -1,3 +1,5 @@
+language_name = Português (Brasil)
+
 title_authenticate = Autenticar em {$hostname}
 title_command = Mudar comando da sessão
 title_power = Opções de energia
@@ -1,3 +1,5 @@
+language_name = Русский
+
 title_authenticate = Авторизация в {$hostname}
 title_command = Изменить команду сеанса
 title_power = Опции питания
@@ -0,0 +1,192 @@
+use std::{
+  env, fs,
+  os::unix::fs::PermissionsExt,
+  path::{Path, PathBuf},
+};
+
+use tokio::task;
+
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+// Completes a value with Tab against the candidates starting with it, going
+// through them in order on repeated presses, back to the first one after the
+// last.
+#[derive(Debug, Clone)]
+pub struct Completion {
+  candidates: Vec<String>,
+  index: usize,
+}
+
+impl Completion {
+  // Nothing is completed when no candidate starts with the prefix.
+  pub fn new<I>(prefix: &str, candidates: I) -> Option<Completion>
+  where
+    I: IntoIterator<Item = String>,
+  {
+    let mut candidates = candidates.into_iter().filter(|candidate| candidate.starts_with(prefix)).collect::<Vec<_>>();
+
+    candidates.sort();
+    candidates.dedup();
+
+    match candidates.is_empty() {
+      true => None,
+      false => Some(Completion { candidates, index: 0 }),
+    }
+  }
+
+  pub fn current(&self) -> &str {
+    &self.candidates[self.index]
+  }
+
+  pub fn next(&mut self) -> &str {
+    self.index = (self.index + 1) % self.candidates.len();
+
+    self.current()
+  }
+}
+
+// Directories searched for commands, either given or from the environment of
+// the greeter.
+pub fn search_path(path: Option<&str>) -> Vec<PathBuf> {
+  let path = path.map(str::to_string).or_else(|| env::var("PATH").ok()).unwrap_or_else(|| DEFAULT_PATH.to_string());
+
+  env::split_paths(&path).filter(|dir| dir.is_absolute()).collect()
+}
+
+// Lists the executables found in the given directories, which may take a
+// while on slow or network file systems, so that it is done away from the
+// event loop and only once.
+pub async fn executables(dirs: Vec<PathBuf>) -> Vec<String> {
+  task::spawn_blocking(move || {
+    let mut executables = dirs
+      .iter()
+      .filter_map(|dir| fs::read_dir(dir).ok())
+      .flatten()
+      .flatten()
+      .filter(|entry| is_executable(&entry.path()))
+      .filter_map(|entry| entry.file_name().into_string().ok())
+      .collect::<Vec<_>>();
+
+    executables.sort();
+    executables.dedup();
+    executables
+  })
+  .await
+  .unwrap_or_default()
+}
+
+// Returns the program a command runs when it cannot be found, which greetd
+// would only tell once the authentication was used. Commands using the syntax of
+// the shell beyond variable assignments are not looked into.
+pub fn missing_program(command: &str, dirs: &[PathBuf]) -> Option<String> {
+  let program = command.split_whitespace().find(|word| !is_assignment(word) && *word != "exec")?;
+
+  if program.contains(|c: char| "$`'\"\\()<>|&;*?[]{}~".contains(c)) {
+    return None;
+  }
+
+  let found = match program.contains('/') {
+    true => is_executable(Path::new(program)),
+    false => dirs.iter().any(|dir| is_executable(&dir.join(program))),
+  };
+
+  (!found).then(|| program.to_string())
+}
+
+fn is_assignment(word: &str) -> bool {
+  match word.split_once('=') {
+    Some((name, _)) => !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+    None => false,
+  }
+}
+
+fn is_executable(path: &Path) -> bool {
+  fs::metadata(path).map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+// Lists the entries of the directory a path points into which start with its
+// last component, directories ending with a slash to be completed further.
+// Hidden entries are only listed when asked for.
+pub fn paths(prefix: &str) -> Vec<String> {
+  let (dir, file) = match prefix.rfind('/') {
+    Some(index) => prefix.split_at(index + 1),
+    None => return Vec::new(),
+  };
+
+  let entries = match fs::read_dir(dir) {
+    Ok(entries) => entries,
+    Err(_) => return Vec::new(),
+  };
+
+  entries
+    .flatten()
+    .filter_map(|entry| {
+      let name = entry.file_name().into_string().ok()?;
+
+      if !name.starts_with(file) || (name.starts_with('.') && !file.starts_with('.')) {
+        return None;
+      }
+
+      match entry.path().is_dir() {
+        true => Some(format!("{dir}{name}/")),
+        false => Some(format!("{dir}{name}")),
+      }
+    })
+    .collect()
+}
+
+// Commands are completed from the executables found on the search path, and
+// their arguments as paths, only the last word being completed. Words which
+// contain a slash, including commands, are always completed as paths.
+pub fn complete_command(command: &str, executables: &[String]) -> Option<Completion> {
+  let start = command.rfind(char::is_whitespace).map(|index| index + 1).unwrap_or(0);
+  let (head, word) = command.split_at(start);
+
+  let candidates = match (word.contains('/'), head.trim().is_empty()) {
+    (true, _) => paths(word),
+    (false, true) if !word.is_empty() => executables.to_vec(),
+    (false, _) => return None,
+  };
+
+  Completion::new(command, candidates.into_iter().map(|candidate| format!("{head}{candidate}")))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::{complete_command, missing_program, Completion};
+
+  #[test]
+  fn completions_are_cycled_through() {
+    let candidates = ["bob", "alice", "alan", "alice"].map(str::to_string);
+    let mut completion = Completion::new("al", candidates.clone()).unwrap();
+
+    assert_eq!(completion.current(), "alan");
+    assert_eq!(completion.next(), "alice");
+    assert_eq!(completion.next(), "alan");
+
+    assert!(Completion::new("carol", candidates).is_none());
+  }
+
+  #[test]
+  fn only_the_last_word_of_commands_is_completed() {
+    let executables = ["startx", "startxfce4", "sway"].map(str::to_string);
+
+    assert_eq!(complete_command("star", &executables).unwrap().current(), "startx");
+    assert_eq!(complete_command("/bi", &executables).unwrap().current(), "/bin/");
+    assert_eq!(complete_command("startx /bi", &executables).unwrap().current(), "startx /bin/");
+    assert!(complete_command("startx sw", &executables).is_none());
+    assert!(complete_command("", &executables).is_none());
+  }
+
+  #[test]
+  fn programs_are_looked_up_on_the_search_path() {
+    let dirs = [PathBuf::from("/bin"), PathBuf::from("/usr/bin")];
+
+    assert_eq!(missing_program("swa --unsupported-gpu", &dirs), Some("swa".to_string()));
+    assert_eq!(missing_program("WLR_RENDERER=vulkan exec /nonexistent/sway", &dirs), Some("/nonexistent/sway".to_string()));
+    assert_eq!(missing_program("sh -c 'exec sway'", &dirs), None);
+    assert_eq!(missing_program("$HOME/bin/sway", &dirs), None);
+  }
+}
@@ -0,0 +1,132 @@
+// The accents letters can be composed with: their combining mark, the spacing
+// character typed by the dead key on its own, and the letters they compose
+// with along with the result, in the same order.
+const ACCENTS: [(char, char, &str, &str); 8] = [
+  ('\u{300}', '`', "AEIOUaeiou", "ÀÈÌÒÙàèìòù"),
+  ('\u{301}', '´', "AEIOUYaeiouyCcLlNnRrSsZz", "ÁÉÍÓÚÝáéíóúýĆćĹĺŃńŔŕŚśŹź"),
+  ('\u{302}', '^', "AEIOUaeiouCcGgHhJjSsWwYy", "ÂÊÎÔÛâêîôûĈĉĜĝĤĥĴĵŜŝŴŵŶŷ"),
+  ('\u{303}', '~', "ANOanoIiUu", "ÃÑÕãñõĨĩŨũ"),
+  ('\u{308}', '¨', "AEIOUaeiouyY", "ÄËÏÖÜäëïöüÿŸ"),
+  ('\u{30a}', '°', "AaUu", "ÅåŮů"),
+  ('\u{327}', '¸', "CcGgKkLlNnRrSsTt", "ÇçĢģĶķĻļŅņŖŗŞşŢţ"),
+  ('\u{30c}', 'ˇ', "CcDdEeLlNnRrSsTtZz", "ČčĎďĚěĽľŇňŘřŠšŤťŽž"),
+];
+
+// What typing a character amounts to once composed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Composed {
+  // The character before the cursor is replaced with the given one.
+  Replace(char),
+  Insert(String),
+  // The character is held until the next one, to be composed with it.
+  Pending,
+}
+
+// Terminals either send accented characters as they are, or followed by a
+// combining mark, which is composed with the character before it. Dead keys,
+// when the terminal leaves them to applications, type a spacing accent,
+// composed with the next character if enabled.
+#[derive(Debug, Default, Clone)]
+pub struct Composer {
+  pub dead_keys: bool,
+  pending: Option<char>,
+}
+
+impl Composer {
+  pub fn feed(&mut self, previous: Option<char>, c: char) -> Composed {
+    if let Some(accent) = self.pending.take() {
+      return match accent_mark(accent).and_then(|mark| compose(c, mark)) {
+        Some(composed) => Composed::Insert(composed.to_string()),
+
+        // The accent is typed on its own when followed by a space or by
+        // itself, as with the compose tables of X11.
+        None if c == ' ' || c == accent => Composed::Insert(accent.to_string()),
+        None => Composed::Insert([accent, c].iter().collect()),
+      };
+    }
+
+    if let Some(composed) = previous.and_then(|previous| compose(previous, c)) {
+      return Composed::Replace(composed);
+    }
+
+    if self.dead_keys && accent_mark(c).is_some() {
+      self.pending = Some(c);
+
+      return Composed::Pending;
+    }
+
+    Composed::Insert(c.to_string())
+  }
+
+  // Returns the accent held when something else than a character is typed,
+  // which is then inserted as is.
+  pub fn flush(&mut self) -> Option<char> {
+    self.pending.take()
+  }
+}
+
+fn accent_mark(accent: char) -> Option<char> {
+  ACCENTS.iter().find(|(_, spacing, _, _)| *spacing == accent).map(|(mark, _, _, _)| *mark)
+}
+
+fn compose(base: char, mark: char) -> Option<char> {
+  let (_, _, bases, composed) = ACCENTS.iter().find(|(combining, _, _, _)| *combining == mark)?;
+
+  bases.chars().position(|c| c == base).and_then(|index| composed.chars().nth(index))
+}
+
+// Composes the combining marks of a whole text, such as a greeting read from a
+// file, leaving those which cannot be alone.
+pub fn compose_str(text: &str) -> String {
+  let mut composed = String::with_capacity(text.len());
+
+  for c in text.chars() {
+    match composed.chars().last().and_then(|previous| compose(previous, c)) {
+      Some(c) => {
+        composed.pop();
+        composed.push(c);
+      }
+
+      None => composed.push(c),
+    }
+  }
+
+  composed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{compose_str, Composed, Composer};
+
+  #[test]
+  fn combining_marks_are_composed() {
+    let mut composer = Composer::default();
+
+    assert_eq!(composer.feed(Some('e'), '\u{301}'), Composed::Replace('é'));
+    assert_eq!(composer.feed(Some('n'), '\u{303}'), Composed::Replace('ñ'));
+    assert_eq!(composer.feed(Some('x'), '\u{301}'), Composed::Insert("\u{301}".to_string()));
+    assert_eq!(composer.feed(None, '^'), Composed::Insert("^".to_string()));
+
+    assert_eq!(compose_str("Bienvenue a\u{300} Montre\u{301}al"), "Bienvenue à Montréal");
+  }
+
+  #[test]
+  fn dead_keys_are_composed_with_the_next_character() {
+    let mut composer = Composer {
+      dead_keys: true,
+      ..Composer::default()
+    };
+
+    let mut feed = |c| composer.feed(None, c);
+
+    assert_eq!((feed('^'), feed('e')), (Composed::Pending, Composed::Insert("ê".to_string())));
+    assert_eq!((feed('¨'), feed('u')), (Composed::Pending, Composed::Insert("ü".to_string())));
+    assert_eq!((feed('~'), feed(' ')), (Composed::Pending, Composed::Insert("~".to_string())));
+    assert_eq!((feed('^'), feed('^')), (Composed::Pending, Composed::Insert("^".to_string())));
+    assert_eq!((feed('`'), feed('x')), (Composed::Pending, Composed::Insert("`x".to_string())));
+
+    assert_eq!(composer.feed(None, 'ˇ'), Composed::Pending);
+    assert_eq!(composer.flush(), Some('ˇ'));
+    assert_eq!(composer.flush(), None);
+  }
+}
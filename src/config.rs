@@ -0,0 +1,200 @@
+use std::{error::Error, fs, io::ErrorKind};
+
+use getopts::{Matches, Options};
+use toml::Value;
+
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/greetd/tuigreet.toml";
+
+pub type FileOptions = Vec<(String, Vec<String>)>;
+
+// Top-level keys are named after the long option they configure, so that
+// `width = 100` is equivalent to `--width 100`. Options in the table named
+// after the current seat under `seat` take precedence over top-level ones.
+pub fn load(path: Option<&str>, seat: Option<&str>) -> Result<FileOptions, Box<dyn Error>> {
+  let (path, explicit) = match path {
+    Some(path) => (path, true),
+    None => (DEFAULT_CONFIG_PATH, false),
+  };
+
+  let contents = match fs::read_to_string(path) {
+    Ok(contents) => contents,
+    Err(err) if err.kind() == ErrorKind::NotFound && !explicit => return Ok(vec![]),
+    Err(err) => return Err(format!("could not read {path}: {err}").into()),
+  };
+
+  let mut table = match contents.parse::<Value>()? {
+    Value::Table(table) => table,
+    _ => return Err(format!("{path} must contain a TOML table").into()),
+  };
+
+  let seats = match table.remove("seat") {
+    Some(Value::Table(seats)) => seats,
+    Some(_) => return Err("'seat' must be a table of seat-specific options".into()),
+    None => Default::default(),
+  };
+
+  let mut options = table.into_iter().map(|(key, value)| to_args(&key, value).map(|args| (key, args))).collect::<Result<FileOptions, _>>()?;
+
+  if let Some(seat) = seat {
+    match seats.get(seat) {
+      Some(Value::Table(overrides)) => {
+        for (key, value) in overrides {
+          let args = to_args(key, value.clone())?;
+
+          options.retain(|(name, _)| name != key);
+          options.push((key.clone(), args));
+        }
+      }
+
+      Some(_) => return Err(format!("options for {seat} must be a table").into()),
+      None => {}
+    }
+  }
+
+  Ok(options)
+}
+
+pub fn merge(opts: &Options, cli: &Matches, args: &[String], file: FileOptions) -> Result<Matches, Box<dyn Error>> {
+  opts.parse(file.iter().flat_map(|(_, args)| args)).map_err(|err| format!("invalid configuration file: {err}"))?;
+
+  let file = file.into_iter().filter(|(key, _)| !cli.opt_present(key)).flat_map(|(_, args)| args);
+  let merged = args.iter().take(1).cloned().chain(file).chain(args.iter().skip(1).cloned()).collect::<Vec<_>>();
+
+  Ok(opts.parse(merged)?)
+}
+
+fn to_args(key: &str, value: Value) -> Result<Vec<String>, Box<dyn Error>> {
+  let flag = format!("--{key}");
+
+  match value {
+    Value::Boolean(true) => Ok(vec![flag]),
+    Value::Boolean(false) => Ok(vec![]),
+    // Values are attached to the flag, for options whose value is optional.
+    Value::String(value) => Ok(vec![format!("{flag}={value}")]),
+    Value::Integer(value) => Ok(vec![format!("{flag}={value}")]),
+    Value::Float(value) => Ok(vec![format!("{flag}={value}")]),
+
+    Value::Array(values) => {
+      let args = values.into_iter().map(|value| to_args(key, value)).collect::<Result<Vec<_>, _>>()?;
+
+      Ok(args.into_iter().flatten().collect())
+    }
+
+    _ => Err(format!("unsupported value for configuration key '{key}'").into()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs, process};
+
+  use getopts::Options;
+  use toml::Value;
+
+  use super::{load, merge, to_args, FileOptions};
+
+  fn options() -> Options {
+    let mut opts = Options::new();
+
+    opts.optopt("", "width", "", "WIDTH");
+    opts.optflag("", "time", "");
+    opts.optflagopt("", "debug", "", "FILE");
+    opts.optmulti("", "clock", "", "CLOCK");
+    opts
+  }
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  fn load_file(name: &str, contents: &str, seat: Option<&str>) -> Result<FileOptions, String> {
+    let path = env::temp_dir().join(format!("tuigreet-{}-{name}.toml", process::id()));
+
+    fs::write(&path, contents).unwrap();
+
+    let options = load(path.to_str(), seat).map_err(|err| err.to_string());
+
+    fs::remove_file(&path).unwrap();
+
+    options
+  }
+
+  #[test]
+  fn values_are_attached_to_flags() {
+    assert_eq!(to_args("time", Value::Boolean(true)).unwrap(), args(&["--time"]));
+    assert_eq!(to_args("width", Value::Integer(100)).unwrap(), args(&["--width=100"]));
+    assert_eq!(to_args("debug", Value::String("/tmp/log".to_string())).unwrap(), args(&["--debug=/tmp/log"]));
+  }
+
+  #[test]
+  fn false_drops_the_flag() {
+    assert!(to_args("time", Value::Boolean(false)).unwrap().is_empty());
+  }
+
+  #[test]
+  fn arrays_repeat_the_option() {
+    let clocks = Value::Array(vec![Value::String("TZ=UTC".to_string()), Value::String("TZ=Asia/Tokyo".to_string())]);
+
+    assert_eq!(to_args("clock", clocks).unwrap(), args(&["--clock=TZ=UTC", "--clock=TZ=Asia/Tokyo"]));
+    assert!(to_args("clock", Value::Table(Default::default())).is_err());
+  }
+
+  #[test]
+  fn command_line_takes_precedence() {
+    let opts = options();
+    let argv = args(&["tuigreet", "--width", "60", "--clock", "TZ=UTC"]);
+    let cli = opts.parse(&argv).unwrap();
+    let file = vec![
+      ("width".to_string(), args(&["--width=100"])),
+      ("time".to_string(), args(&["--time"])),
+      ("clock".to_string(), args(&["--clock=TZ=Asia/Tokyo"])),
+    ];
+
+    let matches = merge(&opts, &cli, &argv, file).unwrap();
+
+    assert_eq!(matches.opt_str("width").as_deref(), Some("60"));
+    assert_eq!(matches.opt_strs("clock"), args(&["TZ=UTC"]));
+    assert!(matches.opt_present("time"));
+  }
+
+  #[test]
+  fn file_options_follow_the_program_name() {
+    let opts = options();
+    let argv = args(&["tuigreet", "positional"]);
+    let cli = opts.parse(&argv).unwrap();
+
+    let matches = merge(&opts, &cli, &argv, vec![("debug".to_string(), args(&["--debug=/tmp/log"]))]).unwrap();
+
+    assert_eq!(matches.opt_str("debug").as_deref(), Some("/tmp/log"));
+    assert_eq!(matches.free, args(&["tuigreet", "positional"]));
+  }
+
+  #[test]
+  fn invalid_file_options_are_reported() {
+    let opts = options();
+    let argv = args(&["tuigreet"]);
+    let cli = opts.parse(&argv).unwrap();
+
+    let err = merge(&opts, &cli, &argv, vec![("unknown".to_string(), args(&["--unknown"]))]).unwrap_err();
+
+    assert!(err.to_string().starts_with("invalid configuration file"));
+  }
+
+  #[test]
+  fn seat_options_override_top_level_ones() {
+    let options = load_file("seats", "width = 100\ntime = true\n\n[seat.seat1]\nwidth = 60\n\n[seat.seat2]\ntime = false\n", Some("seat1")).unwrap();
+
+    assert_eq!(options, vec![("time".to_string(), args(&["--time"])), ("width".to_string(), args(&["--width=60"]))]);
+  }
+
+  #[test]
+  fn seats_must_be_tables() {
+    assert!(load_file("invalid-seats", "seat = 1\n", None).is_err());
+    assert!(load_file("invalid-seat", "[seat]\nseat1 = 1\n", Some("seat1")).is_err());
+  }
+
+  #[test]
+  fn missing_file_is_only_reported_when_given() {
+    assert!(load(Some("/nonexistent/tuigreet.toml"), None).is_err());
+  }
+}
@@ -0,0 +1,278 @@
+use std::{convert::TryInto, env, error::Error};
+
+use nix::unistd::getuid;
+use tokio::{
+  io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+  net::UnixStream,
+};
+
+const DEFAULT_SYSTEM_BUS_PATH: &str = "/run/dbus/system_bus_socket";
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+// The largest message the specification allows.
+const MAX_MESSAGE_LENGTH: usize = 128 * 1024 * 1024;
+const PARSE_ERROR: &str = "could not parse a message from the system bus";
+
+// A method call on the system bus, only supporting the few argument types the
+// greeter needs, so as not to depend on a full D-Bus implementation.
+pub struct MethodCall {
+  destination: String,
+  path: String,
+  interface: String,
+  member: String,
+  signature: String,
+  body: Vec<u8>,
+}
+
+impl MethodCall {
+  pub fn new(destination: &str, path: &str, interface: &str, member: &str) -> MethodCall {
+    MethodCall {
+      destination: destination.to_string(),
+      path: path.to_string(),
+      interface: interface.to_string(),
+      member: member.to_string(),
+      signature: String::new(),
+      body: Vec::new(),
+    }
+  }
+
+  pub fn boolean(mut self, value: bool) -> MethodCall {
+    pad(&mut self.body, 4);
+
+    self.signature.push('b');
+    self.body.extend_from_slice(&(value as u32).to_le_bytes());
+    self
+  }
+
+  // Calls the method and waits for its reply, a D-Bus error being returned
+  // along with its message.
+  pub async fn call(self) -> Result<(), Box<dyn Error>> {
+    let path = env::var("DBUS_SYSTEM_BUS_ADDRESS")
+      .ok()
+      .and_then(|address| {
+        address
+          .split(';')
+          .find_map(|address| address.strip_prefix("unix:path=").map(|path| path.split(',').next().unwrap_or_default().to_string()))
+      })
+      .unwrap_or_else(|| DEFAULT_SYSTEM_BUS_PATH.to_string());
+
+    let mut stream = BufReader::new(UnixStream::connect(&path).await.map_err(|err| format!("could not connect to {path}: {err}"))?);
+
+    authenticate(&mut stream).await?;
+
+    let hello = MethodCall::new("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus", "Hello");
+
+    stream.write_all(&hello.encode(1)).await?;
+    wait_reply(&mut stream, 1).await?;
+
+    stream.write_all(&self.encode(2)).await?;
+    wait_reply(&mut stream, 2).await
+  }
+
+  fn encode(&self, serial: u32) -> Vec<u8> {
+    let mut message = vec![b'l', METHOD_CALL, 0, 1];
+
+    message.extend_from_slice(&(self.body.len() as u32).to_le_bytes());
+    message.extend_from_slice(&serial.to_le_bytes());
+
+    let mut fields = vec![
+      (FIELD_PATH, 'o', self.path.as_str()),
+      (FIELD_INTERFACE, 's', self.interface.as_str()),
+      (FIELD_MEMBER, 's', self.member.as_str()),
+      (FIELD_DESTINATION, 's', self.destination.as_str()),
+    ];
+
+    if !self.signature.is_empty() {
+      fields.push((FIELD_SIGNATURE, 'g', self.signature.as_str()));
+    }
+
+    // The length of the array of header fields is only known once they are
+    // all written.
+    message.extend_from_slice(&[0; 4]);
+
+    for (code, kind, value) in fields {
+      pad(&mut message, 8);
+
+      message.extend_from_slice(&[code, 1, kind as u8, 0]);
+
+      match kind {
+        'g' => message.push(value.len() as u8),
+        _ => {
+          pad(&mut message, 4);
+          message.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        }
+      }
+
+      message.extend_from_slice(value.as_bytes());
+      message.push(0);
+    }
+
+    let length = (message.len() - 16) as u32;
+    message[12..16].copy_from_slice(&length.to_le_bytes());
+
+    pad(&mut message, 8);
+    message.extend_from_slice(&self.body);
+    message
+  }
+}
+
+// The EXTERNAL mechanism lets the bus identify us from the credentials of the
+// socket, given our UID.
+async fn authenticate(stream: &mut BufReader<UnixStream>) -> Result<(), Box<dyn Error>> {
+  let uid = getuid().to_string().bytes().map(|byte| format!("{byte:02x}")).collect::<String>();
+
+  stream.write_all(format!("\0AUTH EXTERNAL {uid}\r\n").as_bytes()).await?;
+
+  let mut line = String::new();
+  stream.read_line(&mut line).await?;
+
+  if !line.starts_with("OK ") {
+    return Err(format!("could not authenticate to the system bus: {}", line.trim()).into());
+  }
+
+  stream.write_all(b"BEGIN\r\n").await?;
+
+  Ok(())
+}
+
+// Skips over messages until the reply to the given call, such as signals
+// broadcast by the bus in the meantime.
+async fn wait_reply(stream: &mut BufReader<UnixStream>, serial: u32) -> Result<(), Box<dyn Error>> {
+  loop {
+    let mut header = [0; 16];
+    stream.read_exact(&mut header).await?;
+
+    let little = header[0] == b'l';
+    let body_length = read(&header[4..8], little).ok_or(PARSE_ERROR)? as usize;
+    let fields_length = read(&header[12..16], little).ok_or(PARSE_ERROR)? as usize;
+
+    // Lengths come from the bus, and are checked before anything is allocated
+    // for them.
+    let length = align(16 + fields_length, 8) + body_length;
+
+    if length > MAX_MESSAGE_LENGTH {
+      return Err(PARSE_ERROR.into());
+    }
+
+    let mut message = header.to_vec();
+    message.resize(length, 0);
+    stream.read_exact(&mut message[16..]).await?;
+
+    let (reply_serial, error_name) = parse_fields(&message, fields_length, little).ok_or(PARSE_ERROR)?;
+
+    if reply_serial != Some(serial) {
+      continue;
+    }
+
+    match header[1] {
+      METHOD_RETURN => return Ok(()),
+
+      // Errors usually come with a human-readable message as their first
+      // argument.
+      ERROR => {
+        let body = &message[align(16 + fields_length, 8)..];
+        let description = read(body, little)
+          .and_then(|length| body.get(4..4 + length as usize))
+          .map(|text| String::from_utf8_lossy(text).to_string());
+
+        let name = error_name.unwrap_or_default();
+
+        return Err(description.map(|description| format!("{name}: {description}")).unwrap_or(name).into());
+      }
+
+      _ => {}
+    }
+  }
+}
+
+// Returns the serial the message replies to and the name of its error, if
+// any, nothing being returned when the fields run past the message.
+fn parse_fields(message: &[u8], fields_length: usize, little: bool) -> Option<(Option<u32>, Option<String>)> {
+  let mut reply_serial = None;
+  let mut error_name = None;
+  let mut position = 16;
+
+  while position < 16 + fields_length {
+    position = align(position, 8);
+
+    let code = *message.get(position)?;
+    let signature_length = *message.get(position + 1)? as usize;
+    let signature = *message.get(position + 2)?;
+
+    position += 3 + signature_length;
+
+    match signature {
+      b'u' => {
+        position = align(position, 4);
+
+        if code == FIELD_REPLY_SERIAL {
+          reply_serial = Some(read(message.get(position..)?, little)?);
+        }
+
+        position += 4;
+      }
+
+      b's' | b'o' => {
+        position = align(position, 4);
+
+        let length = read(message.get(position..)?, little)? as usize;
+        let text = message.get(position + 4..position + 4 + length)?;
+
+        if code == FIELD_ERROR_NAME {
+          error_name = Some(String::from_utf8_lossy(text).to_string());
+        }
+
+        position += 4 + length + 1;
+      }
+
+      b'g' => position += *message.get(position)? as usize + 2,
+
+      _ => return None,
+    }
+  }
+
+  Some((reply_serial, error_name))
+}
+
+fn read(bytes: &[u8], little: bool) -> Option<u32> {
+  let bytes = bytes.get(0..4)?.try_into().ok()?;
+
+  match little {
+    true => Some(u32::from_le_bytes(bytes)),
+    false => Some(u32::from_be_bytes(bytes)),
+  }
+}
+
+fn align(position: usize, alignment: usize) -> usize {
+  position.div_ceil(alignment) * alignment
+}
+
+fn pad(buffer: &mut Vec<u8>, alignment: usize) {
+  buffer.resize(align(buffer.len(), alignment), 0);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse_fields, FIELD_REPLY_SERIAL};
+
+  #[test]
+  fn truncated_fields_are_rejected() {
+    let mut message = vec![b'l', 2, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 8, 0, 0, 0];
+    message.extend_from_slice(&[FIELD_REPLY_SERIAL, 1, b'u', 0, 7, 0, 0, 0]);
+
+    assert_eq!(parse_fields(&message, 8, true), Some((Some(7), None)));
+    assert_eq!(parse_fields(&message[..22], 8, true), None);
+    assert_eq!(parse_fields(&message, 64, true), None);
+  }
+}
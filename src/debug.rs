@@ -0,0 +1,48 @@
+use std::{error::Error, fmt, fs::OpenOptions, os::unix::fs::OpenOptionsExt, sync::Mutex};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use greetd_ipc::Request;
+use tracing::Level;
+use tracing_subscriber::fmt::{format::Writer, time::FormatTime};
+
+pub const DEFAULT_LOG_PATH: &str = "/tmp/tuigreet.log";
+
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+  fn format_time(&self, w: &mut Writer<'_>) -> fmt::Result {
+    write!(w, "{}", chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"))
+  }
+}
+
+// The log may end up attached to public bug reports, so it is only readable by
+// the greeter user, and never contains what was typed. Events are only
+// formatted once a subscriber is installed.
+pub fn init(path: &str) -> Result<(), Box<dyn Error>> {
+  let file = OpenOptions::new().create(true).append(true).mode(0o600).open(path)?;
+
+  tracing_subscriber::fmt()
+    .with_writer(Mutex::new(file))
+    .with_max_level(Level::DEBUG)
+    .with_timer(LocalTime)
+    .with_ansi(false)
+    .with_target(false)
+    .with_level(false)
+    .try_init()
+    .map_err(|err| err as Box<dyn Error>)
+}
+
+pub fn redact_request(request: &Request) -> String {
+  match request {
+    Request::PostAuthMessageResponse { response: Some(_) } => "PostAuthMessageResponse { response: <redacted> }".to_string(),
+    request => format!("{request:?}"),
+  }
+}
+
+// Typed characters are left out, since they may be part of a secret.
+pub fn redact_key(input: &KeyEvent) -> String {
+  match input.code {
+    KeyCode::Char(_) => format!("Char(<redacted>) {:?}", input.modifiers),
+    code => format!("{code:?} {:?}", input.modifiers),
+  }
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone};
+use greetd_ipc::{AuthMessageType, ErrorType, Request, Response};
+
+use crate::{
+  trace,
+  ui::{Session, SessionType},
+};
+
+// In demo mode, all data is made up and greetd is never contacted, so that
+// screenshots and recordings can be taken on any machine.
+pub const PASSWORD: &str = "demo";
+pub const HOSTNAME: &str = "tuigreet";
+
+const USERS: [(&str, Option<&str>); 3] = [("alice", Some("Alice Liddell")), ("bob", Some("Bob Marley")), ("guest", None)];
+
+const SESSIONS: [(&str, &str, &str, SessionType); 3] = [
+  ("Sway", "sway", "An i3-compatible Wayland compositor", SessionType::Wayland),
+  ("GNOME", "gnome-session", "This session logs you into GNOME", SessionType::Wayland),
+  ("Xfce Session", "startxfce4", "Use this session to run Xfce as your desktop environment", SessionType::X11),
+];
+
+// Delay before answering an authentication attempt, for the waiting screen to
+// be visible.
+const AUTH_DELAY: Duration = Duration::from_millis(800);
+
+pub fn users() -> Vec<(String, Option<String>)> {
+  USERS.iter().map(|(username, name)| (username.to_string(), name.map(str::to_string))).collect()
+}
+
+pub fn sessions() -> Vec<Session> {
+  SESSIONS
+    .iter()
+    .map(|(name, command, comment, session_type)| Session {
+      name: name.to_string(),
+      command: command.to_string(),
+      comment: Some(comment.to_string()),
+      session_type: *session_type,
+      desktop_names: None,
+      wrapper: None,
+      id: None,
+    })
+    .collect()
+}
+
+pub fn now() -> DateTime<Local> {
+  Local.ymd(2023, 6, 1).and_hms(9, 41, 0)
+}
+
+// Answers requests as greetd would for a user whose password is `PASSWORD`,
+// unless a capture is being replayed.
+pub async fn respond(request: &Request) -> Response {
+  if let Some(response) = trace::replay(request).await {
+    return response;
+  }
+
+  match request {
+    Request::CreateSession { .. } => Response::AuthMessage {
+      auth_message_type: AuthMessageType::Secret,
+      auth_message: "Password:".to_string(),
+    },
+
+    Request::PostAuthMessageResponse { response } => {
+      tokio::time::sleep(AUTH_DELAY).await;
+
+      match response.as_deref() {
+        Some(PASSWORD) => Response::Success,
+
+        _ => Response::Error {
+          error_type: ErrorType::AuthError,
+          description: "authentication failed".to_string(),
+        },
+      }
+    }
+
+    Request::StartSession { .. } | Request::CancelSession => Response::Success,
+  }
+}
@@ -0,0 +1,253 @@
+use std::mem;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use zeroize::Zeroize;
+
+// Edits an input value in place, along with the greeter's cursor offset,
+// which is counted backwards from the end of the value.
+pub struct LineEditor<'e> {
+  value: &'e mut String,
+  offset: &'e mut i16,
+}
+
+impl<'e> LineEditor<'e> {
+  pub fn new(value: &'e mut String, offset: &'e mut i16) -> LineEditor<'e> {
+    LineEditor { value, offset }
+  }
+
+  // Returns whether the key press was handled as an editing action.
+  pub fn handle(&mut self, input: KeyEvent) -> bool {
+    let control = input.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = input.modifiers.contains(KeyModifiers::ALT);
+
+    match input.code {
+      KeyCode::Char('a') if control => self.set_cursor(0),
+      KeyCode::Char('e') if control => self.set_cursor(self.len()),
+      KeyCode::Char('b') if control => self.set_cursor(self.cursor().saturating_sub(1)),
+      KeyCode::Char('f') if control => self.set_cursor(self.cursor() + 1),
+      KeyCode::Char('w') if control => self.delete_range(self.previous_word(), self.cursor()),
+      KeyCode::Char('u') if control => self.delete_range(0, self.cursor()),
+      KeyCode::Char('k') if control => self.delete_range(self.cursor(), self.len()),
+
+      KeyCode::Char('b') if alt => self.set_cursor(self.previous_word()),
+      KeyCode::Char('f') if alt => self.set_cursor(self.next_word()),
+      KeyCode::Char('d') if alt => self.delete_range(self.cursor(), self.next_word()),
+      KeyCode::Backspace if alt || control => self.delete_range(self.previous_word(), self.cursor()),
+
+      KeyCode::Left if alt || control => self.set_cursor(self.previous_word()),
+      KeyCode::Right if alt || control => self.set_cursor(self.next_word()),
+      KeyCode::Left => self.set_cursor(self.cursor().saturating_sub(1)),
+      KeyCode::Right => self.set_cursor(self.cursor() + 1),
+      KeyCode::Home => self.set_cursor(0),
+      KeyCode::End => self.set_cursor(self.len()),
+
+      KeyCode::Backspace => self.delete_range(self.cursor().saturating_sub(1), self.cursor()),
+      KeyCode::Delete => self.delete_range(self.cursor(), self.cursor() + 1),
+
+      KeyCode::Char(c) if !control && !alt => self.insert(c),
+
+      _ => return false,
+    }
+
+    true
+  }
+
+  pub fn cursor(&self) -> usize {
+    (self.len() as i16 + *self.offset).clamp(0, self.len() as i16) as usize
+  }
+
+  pub fn previous(&self) -> Option<char> {
+    self.value.chars().nth(self.cursor().checked_sub(1)?)
+  }
+
+  // Replaces the character before the cursor, which an accent was composed
+  // with.
+  pub fn replace_previous(&mut self, c: char) {
+    let cursor = self.cursor();
+
+    if cursor > 0 {
+      self.delete_range(cursor - 1, cursor);
+      self.insert(c);
+    }
+  }
+
+  fn len(&self) -> usize {
+    self.value.chars().count()
+  }
+
+  fn set_cursor(&mut self, cursor: usize) {
+    *self.offset = cursor.min(self.len()) as i16 - self.len() as i16;
+  }
+
+  // Pasted text is inserted at once, the cursor being left after it.
+  pub fn insert_str(&mut self, text: &str) {
+    let cursor = self.cursor();
+    let value = self.value.chars().take(cursor).chain(text.chars()).chain(self.value.chars().skip(cursor)).collect();
+
+    self.replace(value);
+    self.set_cursor(cursor + text.chars().count());
+  }
+
+  fn insert(&mut self, c: char) {
+    let cursor = self.cursor();
+    let value = self.value.chars().take(cursor).chain(Some(c)).chain(self.value.chars().skip(cursor)).collect();
+
+    self.replace(value);
+    self.set_cursor(cursor + 1);
+  }
+
+  fn delete_range(&mut self, start: usize, end: usize) {
+    let end = end.min(self.len());
+
+    if start >= end {
+      return;
+    }
+
+    let value = self.value.chars().take(start).chain(self.value.chars().skip(end)).collect();
+
+    self.replace(value);
+    self.set_cursor(start);
+  }
+
+  // Values are rebuilt on every edit, the previous one being wiped since it may
+  // hold a secret. It is handed back empty, along with its memory.
+  fn replace(&mut self, value: String) -> String {
+    let mut previous = mem::replace(self.value, value);
+
+    previous.zeroize();
+    previous
+  }
+
+  // Words are runs of alphanumeric characters, as with readline.
+  fn previous_word(&self) -> usize {
+    let chars = self.value.chars().take(self.cursor()).collect::<Vec<_>>();
+    let end = chars.iter().rposition(|c| c.is_alphanumeric()).map(|index| index + 1).unwrap_or(0);
+
+    chars[..end].iter().rposition(|c| !c.is_alphanumeric()).map(|index| index + 1).unwrap_or(0)
+  }
+
+  fn next_word(&self) -> usize {
+    let cursor = self.cursor();
+    let chars = self.value.chars().skip(cursor).collect::<Vec<_>>();
+    let start = chars.iter().position(|c| c.is_alphanumeric()).unwrap_or(chars.len());
+
+    cursor + chars[start..].iter().position(|c| !c.is_alphanumeric()).map(|index| start + index).unwrap_or(chars.len())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+  use super::LineEditor;
+
+  // Runs the given keys against the value, the cursor starting at its end,
+  // and returns the edited value with the cursor position.
+  fn edit(value: &str, keys: &[(KeyCode, KeyModifiers)]) -> (String, usize) {
+    let mut value = value.to_string();
+    let mut offset = 0;
+    let mut editor = LineEditor::new(&mut value, &mut offset);
+
+    for &(code, modifiers) in keys {
+      editor.handle(KeyEvent::new(code, modifiers));
+    }
+
+    let cursor = editor.cursor();
+
+    (value, cursor)
+  }
+
+  fn key(code: KeyCode) -> (KeyCode, KeyModifiers) {
+    (code, KeyModifiers::NONE)
+  }
+
+  fn control(c: char) -> (KeyCode, KeyModifiers) {
+    (KeyCode::Char(c), KeyModifiers::CONTROL)
+  }
+
+  fn alt(c: char) -> (KeyCode, KeyModifiers) {
+    (KeyCode::Char(c), KeyModifiers::ALT)
+  }
+
+  #[test]
+  fn characters_are_inserted_at_the_cursor() {
+    assert_eq!(edit("ac", &[key(KeyCode::Left), key(KeyCode::Char('b'))]), ("abc".to_string(), 2));
+    assert_eq!(edit("bc", &[control('a'), key(KeyCode::Char('a')), control('e'), key(KeyCode::Char('d'))]), ("abcd".to_string(), 4));
+  }
+
+  #[test]
+  fn cursor_stays_within_the_value() {
+    assert_eq!(edit("ab", &[key(KeyCode::Right)]), ("ab".to_string(), 2));
+    assert_eq!(edit("ab", &[key(KeyCode::Home), key(KeyCode::Left), control('b')]), ("ab".to_string(), 0));
+    assert_eq!(edit("ab", &[key(KeyCode::Home), key(KeyCode::Delete), key(KeyCode::End), key(KeyCode::Backspace)]), ("".to_string(), 0));
+  }
+
+  #[test]
+  fn words_are_moved_over() {
+    assert_eq!(edit("sway --unsupported-gpu", &[alt('b')]).1, 19);
+    assert_eq!(edit("sway --unsupported-gpu", &[alt('b'), alt('b'), alt('b')]).1, 0);
+    assert_eq!(edit("sway --unsupported-gpu", &[key(KeyCode::Home), alt('f'), alt('f')]).1, 18);
+    assert_eq!(edit("sway --unsupported-gpu", &[(KeyCode::Left, KeyModifiers::CONTROL)]).1, 19);
+  }
+
+  #[test]
+  fn words_are_deleted() {
+    assert_eq!(edit("sway --unsupported-gpu", &[control('w')]), ("sway --unsupported-".to_string(), 19));
+    assert_eq!(edit("sway --unsupported-gpu", &[control('w'), control('w')]), ("sway --".to_string(), 7));
+    assert_eq!(edit("sway --unsupported-gpu", &[key(KeyCode::Home), alt('d')]), (" --unsupported-gpu".to_string(), 0));
+    assert_eq!(edit("sway gpu", &[(KeyCode::Backspace, KeyModifiers::ALT)]), ("sway ".to_string(), 5));
+  }
+
+  #[test]
+  fn lines_are_deleted_around_the_cursor() {
+    assert_eq!(edit("startx", &[key(KeyCode::Left), control('u')]), ("x".to_string(), 0));
+    assert_eq!(edit("startx", &[key(KeyCode::Left), control('k')]), ("start".to_string(), 5));
+  }
+
+  #[test]
+  fn multibyte_characters_are_counted_once() {
+    assert_eq!(edit("héllo", &[key(KeyCode::Home), key(KeyCode::Right), key(KeyCode::Delete)]), ("hllo".to_string(), 1));
+    assert_eq!(edit("日本語", &[key(KeyCode::Left), key(KeyCode::Backspace)]), ("日語".to_string(), 1));
+  }
+
+  #[test]
+  fn modified_characters_are_not_inserted() {
+    let mut value = String::new();
+    let mut offset = 0;
+
+    assert!(!LineEditor::new(&mut value, &mut offset).handle(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL)));
+    assert!(value.is_empty());
+  }
+
+  #[test]
+  fn accents_replace_the_previous_character() {
+    let mut value = "cafe".to_string();
+    let mut offset = 0;
+    let mut editor = LineEditor::new(&mut value, &mut offset);
+
+    assert_eq!(editor.previous(), Some('e'));
+
+    editor.replace_previous('é');
+
+    assert_eq!(value, "café");
+  }
+
+  #[test]
+  fn previous_values_are_wiped() {
+    let mut value = String::with_capacity(32);
+    let mut offset = 0;
+
+    value.push_str("hunter2");
+
+    let previous = LineEditor::new(&mut value, &mut offset).replace("new".to_string());
+
+    // The wiped value is still allocated, its whole capacity having been
+    // written with zeros.
+    let memory = unsafe { std::slice::from_raw_parts(previous.as_ptr(), previous.capacity()) };
+
+    assert!(previous.is_empty());
+    assert!(previous.capacity() >= 7);
+    assert!(memory.iter().all(|byte| *byte == 0));
+    assert_eq!(value, "new");
+  }
+}
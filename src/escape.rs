@@ -0,0 +1,119 @@
+use std::{env, error::Error, io, sync::Arc};
+
+use crossterm::{
+  event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+  execute,
+  terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::Backend, Terminal};
+use tokio::{process::Command, sync::RwLock, task};
+
+use crate::{event::Events, ipc::Ipc, Greeter, MessageLevel};
+
+const DEFAULT_SHELL: &str = "/bin/sh";
+
+// Leaves the interface for developers debugging the integration with greetd,
+// printing the state of the greeter and opening a shell if allowed to. The
+// interface is brought back once the shell exits or Enter is pressed, even if
+// leaving it failed halfway, the failure being then displayed.
+pub async fn run<B: Backend>(greeter: &Arc<RwLock<Greeter>>, terminal: &mut Terminal<B>, events: &mut Events, ipc: &Ipc) -> Result<(), Box<dyn Error>> {
+  let (serial, mouse, shell) = {
+    let greeter = greeter.read().await;
+
+    (greeter.serial, greeter.mouse, greeter.debug_shell)
+  };
+
+  // Input read from the terminal in the meantime would be lost to whatever
+  // runs in it.
+  events.pause().await;
+
+  let result = interrupt(greeter, terminal, ipc, serial, mouse, shell).await;
+  let restored = restore(terminal, serial, mouse);
+
+  events.resume();
+
+  let mut greeter = greeter.write().await;
+
+  if let Err(err) = result {
+    greeter.push_message(MessageLevel::Error, err.to_string());
+  }
+
+  greeter.dirty = true;
+
+  restored
+}
+
+async fn interrupt<B: Backend>(greeter: &Arc<RwLock<Greeter>>, terminal: &mut Terminal<B>, ipc: &Ipc, serial: bool, mouse: bool, shell: bool) -> Result<(), Box<dyn Error>> {
+  if mouse {
+    execute!(io::stdout(), DisableMouseCapture)?;
+  }
+
+  execute!(io::stdout(), DisableBracketedPaste)?;
+
+  if !serial {
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+  }
+
+  disable_raw_mode()?;
+  terminal.show_cursor()?;
+
+  for line in diagnostics(&*greeter.read().await, ipc).await {
+    println!("{line}");
+  }
+
+  match shell {
+    true => {
+      let shell = env::var("SHELL").unwrap_or_else(|_| DEFAULT_SHELL.to_string());
+
+      println!("\nStarting {shell}, exit it to go back to the greeter.");
+
+      if let Err(err) = Command::new(&shell).status().await {
+        println!("Could not start {shell}: {err}");
+      }
+    }
+
+    false => {
+      println!("\nPress Enter to go back to the greeter.");
+
+      task::spawn_blocking(|| io::stdin().read_line(&mut String::new())).await??;
+    }
+  }
+
+  Ok(())
+}
+
+fn restore<B: Backend>(terminal: &mut Terminal<B>, serial: bool, mouse: bool) -> Result<(), Box<dyn Error>> {
+  enable_raw_mode()?;
+
+  if !serial {
+    execute!(io::stdout(), EnterAlternateScreen)?;
+  }
+
+  if mouse {
+    execute!(io::stdout(), EnableMouseCapture)?;
+  }
+
+  execute!(io::stdout(), EnableBracketedPaste)?;
+
+  terminal.clear()?;
+
+  Ok(())
+}
+
+// The state is printed as is, without translation, like the debug log.
+async fn diagnostics(greeter: &Greeter, ipc: &Ipc) -> Vec<String> {
+  let mut lines = vec![
+    format!("tuigreet {}", env!("VERSION")),
+    format!("mode: {:?} (previous: {:?})", greeter.mode, greeter.previous_mode),
+    format!("username: {:?}", greeter.username),
+    format!("command: {:?}", greeter.command),
+    format!("working: {}, done: {}", greeter.working, greeter.done),
+    format!("socket: {:?}", greeter.socket),
+    format!("last request: {}", ipc.last_request().await.as_deref().unwrap_or("none")),
+    format!("last response: {}", greeter.last_response.as_deref().unwrap_or("none")),
+  ];
+
+  lines.extend(greeter.messages.iter().map(|message| format!("message: {:?} {:?}", message.level, message.text)));
+
+  lines
+}
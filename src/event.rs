@@ -1,42 +1,138 @@
 use std::time::Duration;
 
-use crossterm::event::{Event as TermEvent, EventStream, KeyEvent};
-use futures::{future::FutureExt, StreamExt};
-use tokio::{sync::mpsc, time};
+use crossterm::event::{Event as TermEvent, EventStream, KeyEvent, MouseEvent, MouseEventKind};
+use futures::StreamExt;
+use greetd_ipc::Response;
+use tokio::{
+  sync::mpsc,
+  task::JoinHandle,
+  time::{self, MissedTickBehavior},
+};
 
-const TICK_RATE: u64 = 250;
+use crate::{
+  hooks::Hook,
+  info::{BatteryStatus, LastLogin, NetworkStatus, UserCommand},
+  ipc::IpcError,
+  MessageLevel,
+};
 
+// Everything the greeter reacts to goes through a single channel, consumed by
+// the event loop which alone changes the state of the greeter in response:
+// input, ticks, responses from greetd, signals and the results of background
+// tasks.
 pub enum Event {
   Key(KeyEvent),
+  Mouse(MouseEvent),
+  Paste(String),
   Tick,
+  Resize,
+  Ipc(Response),
+  // The connection to greetd was lost during an exchange.
+  IpcFailed(IpcError),
+  // greetd acknowledged that the authentication in progress was canceled.
+  Canceled,
+  Signal(Signal),
+  Notifications(Vec<String>),
+  Widget(usize, Option<String>),
+  // A hook is done, along with the message it left, if any.
+  Hook(u64, Hook, Option<(MessageLevel, String)>),
+  UserCommand(String, Option<UserCommand>),
+  // The last login of a user and their number of failed attempts, if asked for.
+  LoginHistory(String, Option<LastLogin>, Option<u32>),
+  Battery(Option<BatteryStatus>),
+  Network(NetworkStatus),
+  Executables(Vec<String>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Signal {
+  Hangup,
 }
 
 pub struct Events {
+  tx: mpsc::Sender<Event>,
   rx: mpsc::Receiver<Event>,
+  reader: Option<JoinHandle<()>>,
 }
 
 impl Events {
-  pub async fn new() -> Events {
-    let mut stream = EventStream::new();
+  // Until the terminal is listened to, only the events sent by other tasks
+  // are received, which is all plain mode needs.
+  pub fn new() -> Events {
     let (tx, rx) = mpsc::channel(10);
 
-    tokio::task::spawn(async move {
-      loop {
-        let timeout = time::sleep(Duration::from_millis(TICK_RATE));
+    Events { tx, rx, reader: None }
+  }
 
-        tokio::select! {
-          event = stream.next().fuse() => {
-            if let Some(Ok(TermEvent::Key(event))) = event {
-              let _ = tx.send(Event::Key(event)).await;
-            }
-          }
+  pub fn sender(&self) -> mpsc::Sender<Event> {
+    self.tx.clone()
+  }
+
+  // Forwards key presses and clicks read from the terminal, along with ticks
+  // at the given rate.
+  pub fn listen(&mut self, tick_rate: u64) {
+    self.resume();
 
-          _ = timeout => { let _ = tx.send(Event::Tick).await; },
+    tokio::task::spawn({
+      let tx = self.sender();
+
+      async move {
+        let mut interval = time::interval(Duration::from_millis(tick_rate));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+          interval.tick().await;
+
+          if tx.send(Event::Tick).await.is_err() {
+            break;
+          }
         }
       }
     });
+  }
+
+  // The terminal stops being read while it is handed over to another program,
+  // which would otherwise have its input taken away.
+  pub async fn pause(&mut self) {
+    if let Some(reader) = self.reader.take() {
+      reader.abort();
+
+      let _ = reader.await;
+    }
+  }
+
+  pub fn resume(&mut self) {
+    self.reader = Some(tokio::task::spawn({
+      let tx = self.sender();
+
+      async move {
+        let mut stream = EventStream::new();
+
+        while let Some(event) = stream.next().await {
+          match event {
+            Ok(TermEvent::Key(event)) => {
+              let _ = tx.send(Event::Key(event)).await;
+            }
+
+            // Mouse movements are not forwarded, since every event triggers a
+            // redraw.
+            Ok(TermEvent::Mouse(event)) if matches!(event.kind, MouseEventKind::Down(_) | MouseEventKind::ScrollUp | MouseEventKind::ScrollDown) => {
+              let _ = tx.send(Event::Mouse(event)).await;
+            }
 
-    Events { rx }
+            Ok(TermEvent::Paste(text)) => {
+              let _ = tx.send(Event::Paste(text)).await;
+            }
+
+            Ok(TermEvent::Resize(_, _)) => {
+              let _ = tx.send(Event::Resize).await;
+            }
+
+            _ => {}
+          }
+        }
+      }
+    }));
   }
 
   pub async fn next(&mut self) -> Option<Event> {
@@ -4,30 +4,80 @@ use std::{
   env,
   error::Error,
   fmt::{self, Display},
+  iter, mem,
+  path::PathBuf,
   process,
   sync::Arc,
+  time::{Duration, Instant},
 };
 
 use chrono::{
   format::{Item, StrftimeItems},
-  Locale,
+  Local, Locale,
 };
+use chrono_tz::Tz;
 use getopts::{Matches, Options};
 use i18n_embed::DesktopLanguageRequester;
+use ratatui::layout::{Alignment, Rect};
 use tokio::{
   net::UnixStream,
   process::Command,
   sync::{Notify, RwLock, RwLockWriteGuard},
 };
+use unic_langid::LanguageIdentifier;
 use zeroize::Zeroize;
 
 use crate::{
-  info::{get_issue, get_last_session, get_last_user_name, get_last_user_session, get_last_user_username, get_min_max_uids, get_users},
-  power::PowerOption,
+  completion::{search_path, Completion},
+  compose::{compose_str, Composer},
+  config,
+  debug::{self, DEFAULT_LOG_PATH},
+  demo,
+  hooks::{Hook, HookRun},
+  info::{
+    get_command_history, get_greeting_file, get_hostname, get_issue, get_kernel_release, get_last_session, get_last_user_name, get_last_user_session, get_last_user_username, get_load_average,
+    get_logged_in_count, get_min_max_uids, get_seat, get_uptime, get_user_command, get_users, shell_quote, system_identity, take_last_start, BatteryStatus, LastLogin, NetworkStatus, UserCommand,
+  },
+  ipc::IpcError,
+  keyboard::Binding,
+  power::{PowerAction, PowerBackend, PowerOption},
+  secret::Secret,
+  tasks::{Probe, Tasks},
+  trace,
+  ui::{
+    available_languages, expand_template, is_rtl_language, parse_status, render_font, resolve_session, select_language, session_allowed, text_width, ColorMode, Segments, Session, Spinner, Target,
+    Theme, DEFAULT_STATUS_LEFT, DEFAULT_STATUS_RIGHT, MESSAGES,
+  },
+  widget::Widget,
 };
 
 const DEFAULT_LOCALE: Locale = Locale::en_US;
 const DEFAULT_ASTERISKS_CHAR: char = '*';
+const DEFAULT_TICK_RATE: u64 = 250;
+const SERIAL_TICK_RATE: u64 = 1000;
+const DEFAULT_LOCKOUT_DURATION: u64 = 30;
+const DEFAULT_HOOK_TIMEOUT: u64 = 10;
+const DEFAULT_WIDTH: u16 = 80;
+const TRANSITION_DURATION: Duration = Duration::from_millis(200);
+const DEFAULT_XSESSION_WRAPPER: &str = "startx /usr/bin/env";
+const DEFAULT_OTP_LENGTH: usize = 6;
+const SESSION_FAILURE_WINDOW: i64 = 10;
+const OTP_PROMPT_KEYWORDS: &[&str] = &["verification code", "one-time", "otp", "totp", "authenticator", "passcode", "token code", "2fa", "two-factor"];
+const DEVICE_PROMPT_KEYWORDS: &[&str] = &[
+  "finger",
+  "swipe",
+  "security key",
+  "smart card",
+  "smartcard",
+  "touch your",
+  "touch the",
+  "tap your",
+  "tap the",
+  "insert your",
+  "yubikey",
+  "fido",
+  "u2f",
+];
 
 #[derive(Debug, Copy, Clone)]
 pub enum AuthStatus {
@@ -51,57 +101,365 @@ pub enum Mode {
   Password,
   Users,
   Command,
+  Environment,
   Sessions,
   Power,
+  PowerConfirm,
+  Layouts,
+  Locale,
   Processing,
+  Autologin,
+  Lockout,
+}
+
+impl Mode {
+  // Menus and editors opened over the prompt, which they go back to once closed.
+  pub fn is_overlay(&self) -> bool {
+    matches!(self, Mode::Users | Mode::Command | Mode::Environment | Mode::Sessions | Mode::Power | Mode::Layouts | Mode::Locale)
+  }
+}
+
+// Where the main container is placed on the screen, either along one of its
+// edges or at the given column and row.
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum WindowPosition {
+  TopLeft,
+  Top,
+  TopRight,
+  Left,
+  #[default]
+  Center,
+  Right,
+  BottomLeft,
+  Bottom,
+  BottomRight,
+  At(u16, u16),
+}
+
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum Animation {
+  #[default]
+  Off,
+  Slide,
+  Fade,
+}
+
+// Whether prompts are treated as asking for a one-time code, which only
+// accepts digits and is submitted once long enough.
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum OtpInput {
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum UsernameNormalization {
+  #[default]
+  None,
+  Trim,
+  Lower,
+}
+
+impl UsernameNormalization {
+  // Lowercasing also trims the username, since both usually go together.
+  pub fn apply(&self, username: &str) -> String {
+    match self {
+      UsernameNormalization::None => username.to_string(),
+      UsernameNormalization::Trim => username.trim().to_string(),
+      UsernameNormalization::Lower => username.trim().to_lowercase(),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum IdentityPosition {
+  Title,
+  Prompt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MessageLevel {
+  Info,
+  Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Message {
+  pub level: MessageLevel,
+  pub text: String,
+}
+
+impl Zeroize for Message {
+  fn zeroize(&mut self) {
+    self.text.zeroize();
+  }
+}
+
+// A question asked by PAM, whose answer is echoed unless it is secret.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+  pub message: String,
+  pub secret: bool,
+  pub otp: bool,
+}
+
+impl Zeroize for Prompt {
+  fn zeroize(&mut self) {
+    self.message.zeroize();
+  }
+}
+
+// A question which was already answered during the current authentication,
+// along with its answer when it was not secret.
+#[derive(Debug, Clone)]
+pub struct Step {
+  pub prompt: Prompt,
+  pub answer: Option<String>,
+}
+
+impl Zeroize for Step {
+  fn zeroize(&mut self) {
+    self.prompt.zeroize();
+    self.answer.zeroize();
+  }
 }
 
 #[derive(SmartDefault)]
 pub struct Greeter {
   #[default(DEFAULT_LOCALE)]
   pub locale: Locale,
+  // The zone of the clock when it differs from the local one, and the clocks
+  // shown next to it on the title bar, with their own format.
+  pub time_zone: Option<Tz>,
+  pub clocks: Vec<(Tz, Option<String>)>,
+  // The command line, kept for the configuration to be reloaded.
+  pub args: Vec<String>,
   pub config: Option<Matches>,
+  pub theme: Theme,
+  pub status_left: Segments,
+  pub status_right: Segments,
   pub socket: String,
+  pub demo: bool,
+  pub plain: bool,
+  pub serial: bool,
+  pub seat: Option<String>,
   pub stream: Option<Arc<RwLock<UnixStream>>>,
+  // Wakes up an exchange with greetd which is still waiting for its response
+  // when the attempt is canceled, so that it releases the connection.
+  pub interrupt: Arc<Notify>,
 
   pub mode: Mode,
   pub previous_mode: Mode,
   pub cursor_offset: i16,
 
   pub users: Vec<(String, Option<String>)>,
+  // The number of users in the given range of UIDs, counted for greetings.
+  pub user_count: Option<((u32, u32), usize)>,
   pub selected_user: usize,
   pub command: Option<String>,
   pub new_command: String,
-  pub sessions_path: Option<String>,
-  pub sessions: Vec<(String, String)>,
+  pub command_history: Vec<String>,
+  pub command_history_index: Option<usize>,
+  pub command_draft: String,
+  // The directories searched for the commands completed with Tab, and the
+  // executables found there.
+  pub command_path: Vec<PathBuf>,
+  pub executables: Vec<String>,
+  pub environment: Vec<String>,
+  pub new_environment: String,
+  pub sessions_path: Vec<String>,
+  pub xsessions_path: Vec<String>,
+  pub sessions: Vec<Session>,
+  pub sessions_allow: Vec<String>,
+  pub sessions_deny: Vec<String>,
+  pub session_args: HashMap<String, String>,
+  // Sessions started through the login shell of the user, either all of them
+  // or those listed.
+  pub login_shell: bool,
+  pub login_shell_sessions: Vec<String>,
+  #[default(Some(DEFAULT_XSESSION_WRAPPER.to_string()))]
+  pub xsession_wrapper: Option<String>,
   pub selected_session: usize,
 
   pub selected_power_option: usize,
+  pub layouts: Vec<String>,
+  pub selected_layout: usize,
+  pub current_layout: usize,
+  // The layout chosen from the menu, which is then set as the default one of
+  // XKB in the session environment.
+  pub session_layout: Option<String>,
+  pub layout_action: Option<(usize, Command)>,
+  pub layout_command_notify: Arc<Notify>,
+  pub locales: Vec<(LanguageIdentifier, String)>,
+  pub selected_locale: usize,
+  pub current_locale: usize,
+  // The locale matching the language chosen from the menu, which is then set
+  // in the session environment.
+  pub session_locale: Option<String>,
+  pub menu_filter: String,
+  pub menu_scroll: usize,
+  pub menu_page: usize,
+  // The width of the container of the current mode when it is sized to its
+  // content, measured before each frame is drawn.
+  #[default(DEFAULT_WIDTH)]
+  pub auto_width: u16,
 
   pub username: String,
   pub username_mask: Option<String>,
-  pub prompt: Option<String>,
-  pub answer: String,
-  pub secret: bool,
+  pub username_normalization: UsernameNormalization,
+  pub window_position: WindowPosition,
+  pub window_margin: u16,
+  // PAM conversations may ask any number of questions, one after the other,
+  // the previous ones being kept on display below the username.
+  pub prompt: Option<Prompt>,
+  pub steps: Vec<Step>,
+  pub otp_input: OtpInput,
+  // Number of digits after which one-time codes are submitted, if any.
+  #[default(Some(DEFAULT_OTP_LENGTH))]
+  pub otp_length: Option<usize>,
+  pub device_prompt: Option<String>,
+  pub answer: Secret,
 
   pub user_menu: bool,
+  pub user_completion: bool,
+  // The usernames cycled through with Tab, until another key is pressed.
+  pub completion: Option<Completion>,
+  pub user_avatars: bool,
+  pub hidden_users: Vec<String>,
+  pub hidden_shells: Vec<String>,
+  pub osk: bool,
+  pub osk_selection: (usize, usize),
+  pub osk_shift: bool,
+  pub mouse: bool,
+  pub hitboxes: Vec<(Rect, Target)>,
+  pub numlock: Option<bool>,
+  pub autologin_deadline: Option<Instant>,
+  // The only user who can log in, whose username is never asked for.
+  pub kiosk_user: Option<String>,
+  // Whether the greeter is locking the session of the kiosk user, exiting
+  // once they are authenticated rather than starting a session.
+  pub lock: bool,
+  // Whether the last session failed to start, in which case it is offered to
+  // be retried rather than being started again on its own.
+  pub start_failed: bool,
+  // The command whose program was not found once authenticated, the session
+  // being started when it is confirmed.
+  pub start_pending: Option<String>,
+  pub failures: u32,
+  pub lockout_attempts: Option<u32>,
+  #[default(Duration::from_secs(DEFAULT_LOCKOUT_DURATION))]
+  pub lockout_duration: Duration,
+  pub lockout_deadline: Option<Instant>,
+
+  #[default(Binding::Function(2))]
+  pub kb_command: Binding,
+  #[default(Binding::Function(3))]
+  pub kb_sessions: Binding,
+  #[default(Binding::Function(12))]
+  pub kb_power: Binding,
+  #[default(Binding::Function(4))]
+  pub kb_layouts: Binding,
+  #[default(Binding::Function(5))]
+  pub kb_environment: Binding,
+  #[default(Binding::Function(6))]
+  pub kb_locale: Binding,
+  #[default(Binding::Function(7))]
+  pub kb_dismiss: Binding,
+  // Leaves the interface to print the state of the greeter, and open a shell
+  // when allowed to, for debugging. There is no such key unless configured.
+  pub kb_debug: Option<Binding>,
+  pub debug_shell: bool,
+  pub last_response: Option<String>,
 
   pub remember: bool,
   pub remember_session: bool,
   pub remember_user_session: bool,
+  pub user_commands: bool,
+  pub user_commands_cache: HashMap<String, Option<String>>,
+  pub user_command_lookup: Option<String>,
+  pub user_command_notify: Arc<Notify>,
+  pub last_login: bool,
+  pub failed_logins: bool,
+  pub login_history_lookup: Option<String>,
+  pub login_history_notify: Arc<Notify>,
+  #[default(Duration::from_secs(DEFAULT_HOOK_TIMEOUT))]
+  pub hook_timeout: Duration,
+  pub hook_messages: bool,
+  // The hook handed to the task running them, and the one the login waits
+  // for before going on.
+  pub hook_run: Option<HookRun>,
+  pub hook_waiting: Option<(u64, Hook)>,
+  pub hooks_run: u64,
+  pub hook_notify: Arc<Notify>,
   pub asterisks: bool,
+  pub deny_secret_paste: bool,
+  pub composer: Composer,
   #[default(DEFAULT_ASTERISKS_CHAR)]
   pub asterisks_char: char,
+  pub asterisks_length: Option<usize>,
+  // Rules secrets are expected to follow, only hinted at when given.
+  pub password_min_length: Option<usize>,
+  pub password_max_length: Option<usize>,
+  pub password_classes: Option<usize>,
+  pub ascii: bool,
+  pub high_contrast: bool,
+  pub color_mode: ColorMode,
+  // Whether text flows from right to left, mirroring the prompt.
+  pub rtl: bool,
   pub greeting: Option<String>,
-  pub message: Option<String>,
-
+  pub banner: Option<String>,
+  #[default(Alignment::Center)]
+  pub banner_alignment: Alignment,
+  pub identity: Option<(IdentityPosition, String)>,
+  pub messages: Vec<Message>,
+  pub messages_scroll: usize,
+  pub health_checks: bool,
+  // Warnings about the state of the system, displayed above the status bar
+  // until they are dismissed.
+  pub notifications: Vec<String>,
+  pub widgets: Vec<Widget>,
+  // The last status of the system found by background tasks, for the status
+  // bar not to look it up while drawing.
+  pub tasks: Tasks,
+  pub battery: Option<BatteryStatus>,
+  pub network: Option<NetworkStatus>,
+
+  pub power_options: Vec<(PowerOption, String)>,
   pub power_commands: HashMap<PowerOption, String>,
-  pub power_command: Option<Command>,
+  pub power_action: Option<PowerAction>,
+  pub power_backend: PowerBackend,
   pub power_command_notify: Arc<Notify>,
   pub power_setsid: bool,
+  pub power_confirm_delay: Option<Duration>,
+  pub power_confirm_deadline: Option<Instant>,
+
+  pub idle_timeout: Option<Duration>,
+  #[default(Instant::now())]
+  pub last_input: Instant,
+  pub idle: bool,
+  pub prompt_timeout: Option<Duration>,
+  pub input_pending: bool,
+
+  pub spinner: Spinner,
+  pub frame: usize,
+  pub processing_since: Option<Instant>,
+  pub animation: Animation,
+  // When the current transition started, along with the direction containers
+  // slide from.
+  pub transition: Option<(Instant, i32)>,
+  // Whether the screen has to be drawn again, and the second it was last
+  // drawn at, since clocks and countdowns only change every second.
+  #[default(true)]
+  pub dirty: bool,
+  pub drawn_at: i64,
 
   pub working: bool,
   pub done: bool,
+  // Whether the attempt was canceled and greetd has yet to acknowledge it,
+  // the prompt being reset only then.
+  pub cancelling: bool,
   pub exit: Option<AuthStatus>,
 }
 
@@ -113,50 +471,111 @@ impl Drop for Greeter {
 
 impl Greeter {
   pub async fn new() -> Self {
+    Self::from_args(env::args().collect()).await
+  }
+
+  pub async fn from_args(args: Vec<String>) -> Self {
     let mut greeter = Self::default();
 
+    greeter.args = args;
     greeter.set_locale();
     greeter.parse_options().await;
-    greeter.sessions = crate::info::get_sessions(&greeter).unwrap_or_default();
+    greeter.sessions = greeter.load_sessions();
 
-    if let Some((_, command)) = greeter.sessions.get(0) {
-      if greeter.command.is_none() {
-        greeter.command = Some(command.clone());
-      }
+    if !greeter.demo {
+      greeter.command_history = get_command_history();
     }
 
+    // Executables are listed in the background once the greeter started, demo
+    // mode only having the commands of its sessions to complete.
+    if greeter.demo {
+      greeter.executables = demo::sessions().into_iter().map(|session| session.command).collect();
+    }
+
+    let mut remembered = None;
+
     if greeter.remember {
       if let Ok(username) = get_last_user_username() {
         greeter.username = username.clone();
         greeter.username_mask = get_last_user_name();
 
         if greeter.remember_user_session {
-          if let Ok(command) = get_last_user_session(&username) {
-            greeter.command = Some(command);
-          }
+          remembered = get_last_user_session(&username).ok();
         }
       }
     }
 
     if greeter.remember_session {
-      if let Ok(session) = get_last_session() {
-        greeter.command = Some(session.trim().to_string());
+      remembered = get_last_session().ok().map(|session| session.trim().to_string());
+    }
+
+    remembered = remembered.filter(|command| greeter.is_selectable(command));
+
+    // Nothing waits on the greeter before it starts, the command of the last
+    // user being looked up right away.
+    if remembered.is_none() && greeter.user_commands && !greeter.username.is_empty() {
+      let username = greeter.username.clone();
+
+      remembered = greeter.resolve_user_command(&username, get_user_command(&username));
+    }
+
+    let default = greeter.option("session-default");
+
+    match resolve_session(&greeter.sessions, remembered, default.as_deref(), greeter.command.as_deref()) {
+      Ok(command) => greeter.command = command,
+
+      Err(err) => {
+        eprintln!("{err}");
+        process::exit(1);
+      }
+    }
+
+    if let Some(username) = greeter.option("autologin") {
+      if let Some(name) = greeter.option("autologin-session") {
+        match greeter.sessions.iter().find(|session| session.name == name) {
+          Some(session) => greeter.command = Some(session.command.clone()),
+
+          None => {
+            eprintln!("Unknown session '{name}' provided in --autologin-session");
+            process::exit(1);
+          }
+        }
+      }
+
+      greeter.username = username;
+      greeter.username_mask = None;
+      greeter.mode = Mode::Autologin;
+    }
+
+    // A session exiting right after it was started most likely failed to,
+    // which is reported instead of letting automatic logins start it again.
+    if !greeter.demo {
+      if let Some((username, command)) = take_last_start(SESSION_FAILURE_WINDOW) {
+        let name = greeter.sessions.iter().find(|session| session.command == command).map(|session| session.name.clone());
+
+        greeter.push_message(MessageLevel::Error, fl!("session_exited", session = name.unwrap_or_else(|| command.clone())));
+        greeter.autologin_deadline = None;
+        greeter.mode = Mode::Username;
+        greeter.command = Some(command);
+        greeter.offer_retry(username);
       }
     }
 
-    greeter.selected_session = greeter.sessions.iter().position(|(_, command)| Some(command) == greeter.command.as_ref()).unwrap_or(0);
+    greeter.selected_session = greeter.sessions.iter().position(|session| Some(&session.command) == greeter.command.as_ref()).unwrap_or(0);
 
     greeter
   }
 
   fn scrub(&mut self, scrub_message: bool) {
     self.prompt.zeroize();
+    self.steps.zeroize();
+    self.device_prompt.zeroize();
     self.username.zeroize();
     self.username_mask.zeroize();
     self.answer.zeroize();
 
     if scrub_message {
-      self.message.zeroize();
+      self.clear_messages();
     }
   }
 
@@ -165,20 +584,32 @@ impl Greeter {
     self.previous_mode = Mode::Username;
     self.working = false;
     self.done = false;
+    self.cancelling = false;
+    self.start_pending = None;
+    self.hook_run = None;
+    self.hook_waiting = None;
+    self.menu_filter.clear();
+    self.input_pending = false;
 
     self.scrub(false);
-    self.connect().await;
-  }
 
-  pub async fn connect(&mut self) {
-    match UnixStream::connect(&self.socket).await {
-      Ok(stream) => self.stream = Some(Arc::new(RwLock::new(stream))),
+    // The connection is reopened for every attempt, greetd possibly having
+    // been restarted in the meantime.
+    if let Err(err) = self.connect().await {
+      self.push_message(MessageLevel::Error, err.to_string());
+    }
+  }
 
-      Err(err) => {
-        eprintln!("{err}");
-        process::exit(1);
-      }
+  pub async fn connect(&mut self) -> Result<(), IpcError> {
+    if self.demo {
+      return Ok(());
     }
+
+    let stream = UnixStream::connect(&self.socket).await.map_err(|err| IpcError::from_io(&self.socket, &err))?;
+
+    self.stream = Some(Arc::new(RwLock::new(stream)));
+
+    Ok(())
   }
 
   pub fn config(&self) -> &Matches {
@@ -193,14 +624,19 @@ impl Greeter {
     self.config().opt_str(name)
   }
 
+  // Menus and prompts may be given their own width, falling back to the one of
+  // all containers.
   pub fn width(&self) -> u16 {
-    if let Some(value) = self.option("width") {
-      if let Ok(width) = value.parse::<u16>() {
-        return width;
-      }
-    }
+    let value = match self.mode {
+      Mode::Users | Mode::Sessions | Mode::Power | Mode::Layouts | Mode::Locale => self.option("menu-width"),
+      _ => self.option("prompt-width"),
+    };
 
-    80
+    match value.or_else(|| self.option("width")) {
+      Some(value) if value == "auto" => self.auto_width,
+      Some(value) => value.parse::<u16>().unwrap_or(DEFAULT_WIDTH),
+      None => DEFAULT_WIDTH,
+    }
   }
 
   pub fn window_padding(&self) -> u16 {
@@ -233,172 +669,1760 @@ impl Greeter {
     1
   }
 
-  fn set_locale(&mut self) {
-    let locale = DesktopLanguageRequester::requested_languages()
-      .into_iter()
-      .next()
-      .and_then(|locale| locale.region.map(|region| format!("{}_{region}", locale.language)))
-      .and_then(|id| id.as_str().try_into().ok());
+  // Serial consoles are not refreshed more than once a second, since every
+  // frame has to go through the line.
+  pub fn tick_rate(&self) -> u64 {
+    let rate = match self.option("tick-rate").and_then(|value| value.parse::<u64>().ok()) {
+      Some(rate) if rate > 0 => rate,
+      _ => DEFAULT_TICK_RATE,
+    };
 
-    if let Some(locale) = locale {
-      self.locale = locale;
+    match self.serial {
+      true => rate.max(SERIAL_TICK_RATE),
+      false => rate,
     }
   }
 
-  async fn parse_options(&mut self) {
-    let mut opts = Options::new();
-
-    opts.optflag("h", "help", "show this usage information");
-    opts.optflag("v", "version", "print version information");
-    opts.optopt("c", "cmd", "command to run", "COMMAND");
-    opts.optopt("s", "sessions", "colon-separated list of session paths", "DIRS");
-    opts.optopt("w", "width", "width of the main prompt (default: 80)", "WIDTH");
-    opts.optflag("i", "issue", "show the host's issue file");
-    opts.optopt("g", "greeting", "show custom text above login prompt", "GREETING");
-    opts.optflag("t", "time", "display the current date and time");
-    opts.optopt("", "time-format", "custom strftime format for displaying date and time", "FORMAT");
-    opts.optflag("r", "remember", "remember last logged-in username");
-    opts.optflag("", "remember-session", "remember last selected session");
-    opts.optflag("", "remember-user-session", "remember last selected session for each user");
-    opts.optflag("", "user-menu", "allow graphical selection of users from a menu");
-    opts.optopt("", "user-menu-min-uid", "minimum UID to display in the user selection menu", "UID");
-    opts.optopt("", "user-menu-max-uid", "maximum UID to display in the user selection menu", "UID");
-    opts.optflag("", "asterisks", "display asterisks when a secret is typed");
-    opts.optopt("", "asterisks-char", "character to be used to redact secrets (default: *)", "CHAR");
-    opts.optopt("", "window-padding", "padding inside the terminal area (default: 0)", "PADDING");
-    opts.optopt("", "container-padding", "padding inside the main prompt container (default: 1)", "PADDING");
-    opts.optopt("", "prompt-padding", "padding between prompt rows (default: 1)", "PADDING");
-
-    opts.optopt("", "power-shutdown", "command to run to shut down the system", "'CMD [ARGS]...'");
-    opts.optopt("", "power-reboot", "command to run to reboot the system", "'CMD [ARGS]...'");
-    opts.optflag("", "power-no-setsid", "do not prefix power commands with setsid");
-
-    self.config = match opts.parse(&env::args().collect::<Vec<String>>()) {
-      Ok(matches) => Some(matches),
-
-      Err(err) => {
-        eprintln!("{err}");
-        print_usage(opts);
-        process::exit(1);
-      }
-    };
+  // Advances animations by one frame, keeping track of how long the greeter
+  // has been waiting for some processing to complete.
+  pub fn animate(&mut self) {
+    self.frame = self.frame.wrapping_add(1);
 
-    if self.config().opt_present("help") {
-      print_usage(opts);
-      process::exit(0);
+    match (self.mode, self.processing_since) {
+      (Mode::Processing, None) => self.processing_since = Some(Instant::now()),
+      (Mode::Processing, Some(_)) => {}
+      (_, _) => self.processing_since = None,
     }
-    if self.config().opt_present("version") {
-      print_version();
-      process::exit(0);
+  }
+
+  // Changes between the prompt and menus are animated, menus coming from the
+  // right and the prompt from the left.
+  pub fn start_transition(&mut self, previous: Mode) {
+    let is_menu = |mode| matches!(mode, Mode::Users | Mode::Sessions | Mode::Power | Mode::Layouts | Mode::Locale);
+    let is_animated = |mode| is_menu(mode) || matches!(mode, Mode::Username | Mode::Password);
+
+    if self.animation == Animation::Off || !is_animated(previous) || !is_animated(self.mode) || !(is_menu(previous) || is_menu(self.mode)) {
+      return;
     }
 
-    match env::var("GREETD_SOCK") {
-      Ok(socket) => self.socket = socket,
-      Err(_) => {
-        eprintln!("GREETD_SOCK must be defined");
-        process::exit(1);
+    self.transition = Some((Instant::now(), if is_menu(self.mode) { 1 } else { -1 }));
+  }
+
+  // Returns how far along the current transition is, from 0 to 1, or nothing
+  // once it is over.
+  pub fn transition_progress(&self) -> Option<f32> {
+    let (start, _) = self.transition?;
+    let progress = start.elapsed().as_secs_f32() / TRANSITION_DURATION.as_secs_f32();
+
+    (progress < 1.0).then_some(progress)
+  }
+
+  // Spinners and transitions change the screen on every frame.
+  pub fn animated(&self) -> bool {
+    self.mode == Mode::Processing || self.transition_progress().is_some()
+  }
+
+  pub fn second_elapsed(&self) -> bool {
+    Local::now().timestamp() != self.drawn_at
+  }
+
+  // Locks the greeter out for a while once too many consecutive attempts to
+  // authenticate have failed.
+  pub fn check_lockout(&mut self) {
+    if let Some(attempts) = self.lockout_attempts {
+      if self.failures >= attempts {
+        self.failures = 0;
+        self.lockout_deadline = Some(Instant::now() + self.lockout_duration);
+        self.mode = Mode::Lockout;
       }
     }
+  }
 
-    if self.config().opt_present("issue") && self.config().opt_present("greeting") {
-      eprintln!("Only one of --issue and --greeting may be used at the same time");
-      print_usage(opts);
-      process::exit(1);
+  pub fn check_idle(&mut self) {
+    if let Some(timeout) = self.idle_timeout {
+      if self.last_input.elapsed() >= timeout {
+        self.idle = true;
+      }
     }
+  }
 
-    if let Some(value) = self.config().opt_str("asterisks-char") {
-      if value.chars().count() != 1 {
-        eprintln!("--asterisks-char can only have one single character as its value");
-        print_usage(opts);
-        process::exit(1);
-      }
+  // Input left behind for a while, including open menus and messages of
+  // failed attempts, is abandoned so that nothing is left for the next person.
+  // Nothing is abandoned while greetd or a countdown is being waited for.
+  pub fn prompt_timed_out(&self) -> bool {
+    let timeout = match self.prompt_timeout {
+      Some(timeout) => timeout,
+      None => return false,
+    };
 
-      self.asterisks_char = value.chars().next().unwrap();
+    let waiting = matches!(self.mode, Mode::PowerConfirm | Mode::Processing | Mode::Autologin | Mode::Lockout);
+
+    self.input_pending && !waiting && self.last_input.elapsed() >= timeout
+  }
+
+  // Records user activity, returning whether the screen was idle.
+  pub fn wake(&mut self) -> bool {
+    self.last_input = Instant::now();
+    self.input_pending = true;
+
+    mem::replace(&mut self.idle, false)
+  }
+
+  // Backgrounds given with their own options take precedence over the theme.
+  fn load_theme(&self) -> Result<Theme, Box<dyn Error>> {
+    let mut theme = self.option("theme").map(|spec| Theme::load(&spec)).transpose()?.unwrap_or_default();
+
+    if let Some(color) = self.option("background") {
+      theme.set("background", &color).map_err(|err| format!("--background: {err}"))?;
     }
 
-    if let Some(format) = self.config().opt_str("time-format") {
-      if StrftimeItems::new(&format).any(|item| item == Item::Error) {
-        eprintln!("Invalid strftime format provided in --time-format");
-        process::exit(1);
-      }
+    if let Some(color) = self.option("container-background") {
+      theme.set("container", &color).map_err(|err| format!("--container-background: {err}"))?;
     }
 
-    if self.config().opt_present("user-menu") {
-      self.user_menu = true;
+    theme.degrade(self.color_mode);
 
-      let min_uid = self.config().opt_str("user-menu-min-uid").and_then(|uid| uid.parse::<u16>().ok());
-      let max_uid = self.config().opt_str("user-menu-max-uid").and_then(|uid| uid.parse::<u16>().ok());
-      let (min_uid, max_uid) = get_min_max_uids(min_uid, max_uid);
+    Ok(theme)
+  }
 
-      if min_uid >= max_uid {
-        eprintln!("Minimum UID ({min_uid}) must be less than maximum UID ({max_uid})");
-        process::exit(1);
+  // Re-reads the command line and configuration file, applying the changes
+  // that can be made without restarting: the theme, status bar, greeting,
+  // banner and sessions. Nothing is changed if the new configuration is invalid.
+  pub fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+    let opts = options();
+    let args = self.args.clone();
+
+    let cli = opts.parse(&args)?;
+    let file = config::load(cli.opt_str("config").as_deref(), self.seat.as_deref())?;
+    let config = config::merge(&opts, &cli, &args, file)?;
+
+    let previous = self.config.replace(config);
+
+    let loaded = self.load_theme().and_then(|theme| {
+      let status = self.load_status()?;
+      let greeting = self.load_greeting()?;
+      let banner = self.load_banner()?;
+      let session_args = self.load_session_args()?;
+      let xsession_wrapper = self.load_xsession_wrapper()?;
+
+      Ok((theme, status, greeting, banner, session_args, xsession_wrapper))
+    });
+
+    let (theme, (status_left, status_right), greeting, banner, session_args, xsession_wrapper) = match loaded {
+      Ok(loaded) => loaded,
+
+      Err(err) => {
+        self.config = previous;
+
+        return Err(err);
       }
+    };
 
-      self.users = get_users(min_uid, max_uid);
+    if !self.high_contrast {
+      self.theme = theme;
     }
 
-    if self.config().opt_present("remember-session") && self.config().opt_present("remember-user-session") {
-      eprintln!("Only one of --remember-session and --remember-user-session may be used at the same time");
-      print_usage(opts);
-      process::exit(1);
-    }
-    if self.config().opt_present("remember-user-session") && !self.config().opt_present("remember") {
-      eprintln!("--remember-session must be used with --remember");
-      print_usage(opts);
-      process::exit(1);
+    self.status_left = status_left;
+    self.status_right = status_right;
+    self.greeting = greeting;
+    self.banner = banner;
+
+    self.sessions_path = self.config().opt_strs("sessions");
+    self.xsessions_path = self.config().opt_strs("xsessions");
+    self.sessions_allow = self.session_names("sessions-allow");
+    self.sessions_deny = self.session_names("sessions-deny");
+    self.login_shell = self.config().opt_present("login-shell");
+    self.login_shell_sessions = self.session_names("login-shell-sessions");
+    self.session_args = session_args;
+    self.xsession_wrapper = xsession_wrapper;
+    self.sessions = self.load_sessions();
+    self.selected_session = self.sessions.iter().position(|session| Some(&session.command) == self.command.as_ref()).unwrap_or(0);
+    self.user_commands_cache.clear();
+
+    Ok(())
+  }
+
+  // The network status is only displayed by default when requested, since
+  // it is looked up every few seconds.
+  fn load_status(&self) -> Result<(Segments, Segments), Box<dyn Error>> {
+    let left = self.option("status-left").unwrap_or_else(|| DEFAULT_STATUS_LEFT.to_string());
+
+    let right = match self.option("status-right") {
+      Some(right) => right,
+      None if self.config().opt_present("network") => format!("network,{DEFAULT_STATUS_RIGHT}"),
+      None => DEFAULT_STATUS_RIGHT.to_string(),
+    };
+
+    // Giving the clock a format is enough for it to be displayed, after the
+    // default segments.
+    let right = match self.option("status-right").is_none() && self.config().opt_present("status-clock-format") {
+      true => format!("{right},clock"),
+      false => right,
+    };
+
+    Ok((parse_status(&left)?, parse_status(&right)?))
+  }
+
+  // The status of the system is only probed when displayed, while widgets
+  // are run whether or not the status bar shows them.
+  pub fn probes(&self) -> Vec<Probe> {
+    let mut probes = Vec::new();
+
+    for probe in self.status_left.iter().chain(&self.status_right).filter_map(|segment| segment.probe()) {
+      if !probes.contains(&probe) {
+        probes.push(probe);
+      }
     }
 
-    self.remember = self.config().opt_present("remember");
-    self.remember_session = self.config().opt_present("remember-session");
-    self.remember_user_session = self.config().opt_present("remember-user-session");
-    self.asterisks = self.config().opt_present("asterisks");
-    self.greeting = self.option("greeting");
-    self.command = self.option("cmd");
+    if self.health_checks {
+      probes.push(Probe::Health);
+    }
 
-    self.sessions_path = self.option("sessions");
+    probes.extend((0..self.widgets.len()).map(Probe::Widget));
+    probes
+  }
 
+  fn load_greeting(&mut self) -> Result<Option<String>, Box<dyn Error>> {
     if self.config().opt_present("issue") {
-      self.greeting = get_issue();
+      return Ok(get_issue());
     }
 
-    if let Some(command) = self.config().opt_str("power-shutdown") {
-      self.power_commands.insert(PowerOption::Shutdown, command);
+    let greeting = match self.option("greeting-file") {
+      Some(path) => match get_greeting_file(&path) {
+        Ok(greeting) => Some(greeting),
+        Err(err) => return Err(format!("Could not read greeting file {path}: {err}").into()),
+      },
+
+      None => self.option("greeting"),
+    };
+
+    // Files written elsewhere may hold accents as combining marks, which the
+    // Linux console does not draw over the letter before them.
+    let greeting = greeting.map(|greeting| compose_str(&greeting));
+
+    let user_count = match greeting.as_deref() {
+      Some(greeting) if greeting.contains("%user-count%") || greeting.contains("%users%") => self.user_count(),
+      _ => 0,
+    };
+
+    let greeting = greeting.as_deref().map(|greeting| {
+      expand_template(greeting, |variable| match variable {
+        "hostname" => Some(self.hostname()),
+        "kernel" => Some(get_kernel_release()),
+        "seat" => Some(self.seat.clone().unwrap_or_default()),
+        "load" => get_load_average(),
+        "logged-in" => Some(get_logged_in_count().to_string()),
+
+        "uptime" => get_uptime().map(|uptime| {
+          let minutes = uptime.as_secs() / 60;
+          let days = minutes / 1440;
+
+          fl!("uptime", days = days, time = format!("{}:{:02}", minutes / 60 % 24, minutes % 60))
+        }),
+
+        "date" => {
+          let now = if self.demo { demo::now() } else { Local::now() };
+
+          match &self.time_zone {
+            Some(zone) => Some(now.with_timezone(zone).format_localized("%x", self.locale).to_string()),
+            None => Some(now.format_localized("%x", self.locale).to_string()),
+          }
+        }
+
+        "user-count" => Some(user_count.to_string()),
+        "users" => Some(fl!("user_count", count = user_count)),
+
+        // Users coming back are greeted by the name they last logged in with.
+        "welcome" => match get_last_user_name().or_else(|| get_last_user_username().ok()).filter(|user| !user.is_empty()) {
+          Some(user) if self.remember => Some(fl!("welcome_back", user = user)),
+          _ => Some(fl!("welcome")),
+        },
+
+        _ => None,
+      })
+    });
+
+    Ok(greeting)
+  }
+
+  // Users are enumerated through the name service, which may be slow to answer,
+  // so they are only counted once, unless the menu listed them already.
+  fn user_count(&mut self) -> usize {
+    if self.user_menu || self.user_completion || self.demo {
+      return self.users.len();
     }
-    if let Some(command) = self.config().opt_str("power-reboot") {
-      self.power_commands.insert(PowerOption::Reboot, command);
+
+    let min_uid = self.config().opt_str("user-menu-min-uid").and_then(|uid| uid.parse::<u32>().ok());
+    let max_uid = self.config().opt_str("user-menu-max-uid").and_then(|uid| uid.parse::<u32>().ok());
+    let range = get_min_max_uids(min_uid, max_uid);
+
+    match self.user_count {
+      Some((counted, count)) if counted == range => count,
+
+      _ => {
+        let count = get_users(range.0, range.1, &self.hidden_users, &self.hidden_shells).len();
+
+        self.user_count = Some((range, count));
+
+        count
+      }
     }
+  }
 
-    self.power_setsid = !self.config().opt_present("power-no-setsid");
+  // Describes the machine as its hostname, followed by the name of its
+  // operating system, when known, and its kernel release.
+  fn load_identity(&self) -> String {
+    let identity = system_identity();
+
+    let hostname = match self.demo {
+      true => demo::HOSTNAME.to_string(),
+      false => identity.hostname,
+    };
 
-    self.connect().await;
+    match identity.os {
+      Some(os) => format!("{hostname} ({os}, {})", identity.kernel),
+      None => format!("{hostname} ({})", identity.kernel),
+    }
   }
 
-  pub fn set_prompt(&mut self, prompt: &str) {
-    self.prompt = if prompt.ends_with(' ') { Some(prompt.into()) } else { Some(format!("{prompt} ")) };
+  fn load_session_args(&self) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    self
+      .config()
+      .opt_strs("session-args")
+      .into_iter()
+      .map(|entry| match entry.split_once('=') {
+        Some((name, args)) if !name.trim().is_empty() => Ok((name.trim().to_string(), args.trim().to_string())),
+        _ => Err("--session-args must be of the form 'SESSION=ARGS'".into()),
+      })
+      .collect()
   }
 
-  pub fn remove_prompt(&mut self) {
-    self.prompt = None;
+  // X11 sessions are started through a command spawning an X server, unless
+  // they are known to take care of it themselves.
+  fn load_xsession_wrapper(&self) -> Result<Option<String>, Box<dyn Error>> {
+    match (self.option("xsession-wrapper"), self.config().opt_present("no-xsession-wrapper")) {
+      (Some(_), true) => Err("--xsession-wrapper and --no-xsession-wrapper cannot be used together".into()),
+      (None, true) => Ok(None),
+      (Some(wrapper), false) if wrapper.trim().is_empty() => Err("--xsession-wrapper cannot be empty".into()),
+      (Some(wrapper), false) => Ok(Some(wrapper.trim().to_string())),
+      (None, false) => Ok(Some(DEFAULT_XSESSION_WRAPPER.to_string())),
+    }
   }
 
-  pub fn prompt_width(&self) -> usize {
-    match &self.prompt {
-      None => 0,
-      Some(prompt) => prompt.chars().count(),
+  fn load_sessions(&self) -> Vec<Session> {
+    let sessions = match self.demo {
+      true => demo::sessions(),
+      false => crate::info::get_sessions(self).unwrap_or_default(),
+    };
+
+    // The command given with --cmd is kept, having been configured along
+    // with the lists.
+    let command = self.option("cmd");
+
+    sessions
+      .into_iter()
+      .filter(|session| Some(&session.command) == command.as_ref() || session_allowed(session, &self.sessions_allow, &self.sessions_deny))
+      .collect()
+  }
+
+  fn session_names(&self, name: &str) -> Vec<String> {
+    match self.option(name) {
+      Some(names) => names.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect(),
+      None => Vec::new(),
     }
   }
-}
 
-fn print_usage(opts: Options) {
-  eprint!("{}", opts.usage("Usage: tuigreet [OPTIONS]"));
-}
+  // Once the sessions are restricted, commands remembered from previous
+  // logins are only used if they still start one of those which can be
+  // selected, since they may have been removed from the lists since.
+  pub fn is_selectable(&self, command: &str) -> bool {
+    (self.sessions_allow.is_empty() && self.sessions_deny.is_empty()) || self.sessions.iter().any(|session| session.command == command)
+  }
 
-fn print_version() {
-  println!("tuigreet {} ({})", env!("VERSION"), env!("TARGET"));
-  println!("Copyright (C) 2020 Antoine POPINEAU <https://github.com/apognu/tuigreet>.");
-  println!("Licensed under GPLv3+ (GNU GPL version 3 or later).");
-  println!();
-  println!("This is free software, you are welcome to redistribute it under some conditions.");
+  // Selects the default command of the user, which is looked up once and away
+  // from the event loop since home directories may be slow to reach, the
+  // result coming back as an event.
+  pub fn lookup_user_command(&mut self, username: &str) {
+    if !self.user_commands {
+      return;
+    }
+
+    match self.user_commands_cache.get(username).cloned() {
+      Some(command) => self.select_user_command(command),
+
+      None => {
+        self.user_command_lookup = Some(username.to_string());
+        self.user_command_notify.notify_one();
+      }
+    }
+  }
+
+  // Commands set by users themselves must start one of the sessions they can
+  // select, unlike those set by administrators.
+  pub fn resolve_user_command(&mut self, username: &str, command: Option<UserCommand>) -> Option<String> {
+    let command = match command {
+      Some(UserCommand::Command(command)) => Some(command),
+      Some(UserCommand::Session(name)) => self.sessions.iter().find(|session| session.name == name).map(|session| session.command.clone()),
+      Some(UserCommand::Own(command)) => Some(command).filter(|command| self.is_selectable(command)),
+      None => None,
+    };
+
+    self.user_commands_cache.insert(username.to_string(), command.clone());
+
+    command
+  }
+
+  pub fn select_user_command(&mut self, command: Option<String>) {
+    if let Some(command) = command {
+      self.selected_session = self.sessions.iter().position(|session| session.command == command).unwrap_or(0);
+      self.command = Some(command);
+    }
+  }
+
+  pub fn hostname(&self) -> String {
+    match self.demo {
+      true => demo::HOSTNAME.to_string(),
+      false => get_hostname(),
+    }
+  }
+
+  fn load_banner(&self) -> Result<Option<String>, Box<dyn Error>> {
+    let pixel = match self.ascii {
+      true => '#',
+      false => '█',
+    };
+
+    if let Some(text) = self.option("banner") {
+      return Ok(Some(render_font(&text, pixel).join("\n")));
+    }
+
+    if self.config().opt_present("banner-hostname") {
+      return Ok(Some(render_font(&self.hostname(), pixel).join("\n")));
+    }
+
+    match self.option("banner-file") {
+      Some(path) => match get_greeting_file(&path) {
+        Ok(banner) => Ok(Some(banner.trim_end().to_string())),
+        Err(err) => Err(format!("Could not read banner file {path}: {err}").into()),
+      },
+
+      None => Ok(None),
+    }
+  }
+
+  // Switches the interface to the bundled language at the given index, which
+  // also becomes the locale of the session.
+  pub fn set_language(&mut self, index: usize) {
+    let language = match self.locales.get(index) {
+      Some((language, _)) => language.clone(),
+      None => return,
+    };
+
+    select_language(&language);
+
+    let locale = match language.region {
+      Some(region) => format!("{}_{region}", language.language),
+      None => language.language.to_string(),
+    };
+
+    if let Ok(date_locale) = locale.as_str().try_into() {
+      self.locale = date_locale;
+    }
+
+    self.current_locale = index;
+    self.session_locale = Some(format!("{locale}.UTF-8"));
+    self.rtl = self.config().opt_present("rtl") || is_rtl_language(language.language.as_str());
+
+    for (option, label) in self.power_options.iter_mut() {
+      if let Some(translated) = option.label() {
+        *label = translated;
+      }
+    }
+
+    if let Ok(greeting) = self.load_greeting() {
+      self.greeting = greeting;
+    }
+  }
+
+  fn set_locale(&mut self) {
+    let locale = DesktopLanguageRequester::requested_languages()
+      .into_iter()
+      .next()
+      .and_then(|locale| locale.region.map(|region| format!("{}_{region}", locale.language)))
+      .and_then(|id| id.as_str().try_into().ok());
+
+    if let Some(locale) = locale {
+      self.locale = locale;
+    }
+  }
+
+  async fn parse_options(&mut self) {
+    let opts = options();
+    let args = self.args.clone();
+
+    self.config = match opts.parse(&args) {
+      Ok(matches) => Some(matches),
+
+      Err(err) => {
+        eprintln!("{err}");
+        print_usage(opts);
+        process::exit(1);
+      }
+    };
+
+    if self.config().opt_present("help") {
+      print_usage(opts);
+      process::exit(0);
+    }
+    if self.config().opt_present("version") {
+      print_version();
+      process::exit(0);
+    }
+
+    self.seat = get_seat();
+
+    let file = match config::load(self.option("config").as_deref(), self.seat.as_deref()) {
+      Ok(file) => file,
+
+      Err(err) => {
+        eprintln!("{err}");
+        process::exit(1);
+      }
+    };
+
+    if !file.is_empty() {
+      self.config = match config::merge(&opts, self.config(), &args, file) {
+        Ok(matches) => Some(matches),
+
+        Err(err) => {
+          eprintln!("{err}");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    // Captures are replayed through the backend of demo mode, greetd not
+    // being contacted.
+    self.demo = self.config().opt_present("demo") || self.config().opt_present("replay-ipc");
+
+    if self.config().opt_present("debug") {
+      let path = self.config().opt_str("debug").unwrap_or_else(|| DEFAULT_LOG_PATH.to_string());
+
+      if let Err(err) = debug::init(&path) {
+        eprintln!("Could not open debug log {path}: {err}");
+        process::exit(1);
+      }
+
+      debug!("starting with {:?}", args.iter().skip(1).collect::<Vec<_>>());
+    }
+
+    if let Some(path) = self.option("capture-ipc") {
+      if let Err(err) = trace::init_capture(&path) {
+        eprintln!("Could not open IPC capture {path}: {err}");
+        process::exit(1);
+      }
+    }
+
+    if let Some(path) = self.option("replay-ipc") {
+      if let Err(err) = trace::init_replay(&path) {
+        eprintln!("Could not read IPC capture {path}: {err}");
+        process::exit(1);
+      }
+    }
+
+    self.plain = self.config().opt_present("plain");
+    self.serial = self.config().opt_present("serial");
+
+    match env::var("GREETD_SOCK") {
+      Ok(socket) => self.socket = socket,
+      Err(_) if self.demo => {}
+      Err(_) => {
+        eprintln!("GREETD_SOCK must be defined");
+        process::exit(1);
+      }
+    }
+
+    if ["issue", "greeting", "greeting-file"].iter().filter(|name| self.config().opt_present(name)).count() > 1 {
+      eprintln!("Only one of --issue, --greeting and --greeting-file may be used at the same time");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    if let Some(value) = self.config().opt_str("asterisks-char") {
+      if value.chars().count() != 1 {
+        eprintln!("--asterisks-char can only have one single character as its value");
+        print_usage(opts);
+        process::exit(1);
+      }
+
+      self.asterisks_char = value.chars().next().unwrap();
+    }
+
+    if let Some(length) = self.option("asterisks-length") {
+      match length.parse::<usize>() {
+        Ok(length) if length > 0 => self.asterisks_length = Some(length),
+
+        _ => {
+          eprintln!("--asterisks-length must be a positive number");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let Some(length) = self.option("password-min-length") {
+      match length.parse::<usize>() {
+        Ok(length) if length > 0 => self.password_min_length = Some(length),
+
+        _ => {
+          eprintln!("--password-min-length must be a positive number");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let Some(length) = self.option("password-max-length") {
+      match length.parse::<usize>() {
+        Ok(length) if length > 0 => self.password_max_length = Some(length),
+
+        _ => {
+          eprintln!("--password-max-length must be a positive number");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let (Some(min), Some(max)) = (self.password_min_length, self.password_max_length) {
+      if min > max {
+        eprintln!("--password-min-length cannot be greater than --password-max-length");
+        print_usage(opts);
+        process::exit(1);
+      }
+    }
+
+    if let Some(classes) = self.option("password-classes") {
+      match classes.parse::<usize>() {
+        Ok(classes) if (2..=4).contains(&classes) => self.password_classes = Some(classes),
+
+        _ => {
+          eprintln!("--password-classes must be a number between 2 and 4");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    self.ascii = self.config().opt_present("ascii") || self.serial;
+    self.high_contrast = self.config().opt_present("high-contrast");
+
+    self.color_mode = match self.option("color-mode").as_deref() {
+      None | Some("auto") => ColorMode::detect(),
+      Some("16") => ColorMode::Ansi,
+      Some("256") => ColorMode::Indexed,
+      Some("truecolor") => ColorMode::TrueColor,
+
+      Some(_) => {
+        eprintln!("--color-mode must be one of auto, 16, 256 or truecolor");
+        print_usage(opts);
+        process::exit(1);
+      }
+    };
+    self.rtl = self.config().opt_present("rtl") || MESSAGES.current_languages().first().map(|language| is_rtl_language(language.language.as_str())).unwrap_or(false);
+
+    self.locales = available_languages();
+    self.current_locale = MESSAGES
+      .current_languages()
+      .first()
+      .and_then(|current| self.locales.iter().position(|(language, _)| language == current))
+      .unwrap_or(0);
+
+    if ["banner", "banner-hostname", "banner-file"].iter().filter(|name| self.config().opt_present(name)).count() > 1 {
+      eprintln!("Only one of --banner, --banner-hostname and --banner-file may be used at the same time");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    match self.load_banner() {
+      Ok(banner) => self.banner = banner,
+
+      Err(err) => {
+        eprintln!("{err}");
+        process::exit(1);
+      }
+    }
+
+    if let Some(alignment) = self.option("banner-align") {
+      self.banner_alignment = match alignment.as_str() {
+        "left" => Alignment::Left,
+        "center" => Alignment::Center,
+        "right" => Alignment::Right,
+
+        _ => {
+          eprintln!("--banner-align must be one of left, center or right");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(position) = self.option("identity") {
+      let position = match position.as_str() {
+        "title" => IdentityPosition::Title,
+        "prompt" => IdentityPosition::Prompt,
+
+        _ => {
+          eprintln!("--identity must be one of title or prompt");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+
+      self.identity = Some((position, self.load_identity()));
+    }
+
+    if let Some(format) = self.config().opt_str("time-format") {
+      if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        eprintln!("Invalid strftime format provided in --time-format");
+        process::exit(1);
+      }
+    }
+
+    if let Some(format) = self.config().opt_str("status-clock-format") {
+      if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        eprintln!("Invalid strftime format provided in --status-clock-format");
+        process::exit(1);
+      }
+    }
+
+    if let Some(zone) = self.config().opt_str("time-zone") {
+      match parse_time_zone(&zone) {
+        Ok(zone) => self.time_zone = Some(zone),
+
+        Err(err) => {
+          eprintln!("--time-zone: {err}");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    for clock in self.config().opt_strs("clock") {
+      let (zone, format) = match clock.strip_prefix("TZ=") {
+        Some(clock) => clock.split_once(' ').map(|(zone, format)| (zone, Some(format.trim().to_string()))).unwrap_or((clock, None)),
+
+        None => {
+          eprintln!("--clock must start with TZ= followed by a time zone, such as 'TZ=UTC %H:%M'");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+
+      if format.as_deref().map(|format| StrftimeItems::new(format).any(|item| item == Item::Error)).unwrap_or(false) {
+        eprintln!("Invalid strftime format provided in --clock");
+        process::exit(1);
+      }
+
+      match parse_time_zone(zone) {
+        Ok(zone) => self.clocks.push((zone, format.filter(|format| !format.is_empty()))),
+
+        Err(err) => {
+          eprintln!("--clock: {err}");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if (self.config().opt_present("autologin-session") || self.config().opt_present("autologin-delay")) && !self.config().opt_present("autologin") {
+      eprintln!("--autologin-session and --autologin-delay must be used with --autologin");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    if self.config().opt_present("kiosk-user") && self.config().opt_present("autologin") {
+      eprintln!("--kiosk-user and --autologin cannot be used together");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    self.kiosk_user = self.option("kiosk-user").filter(|user| !user.is_empty());
+
+    if self.config().opt_present("lock") && (self.config().opt_present("kiosk-user") || self.config().opt_present("autologin")) {
+      eprintln!("--lock cannot be used with --kiosk-user or --autologin");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    if let Some(username) = self.option("lock").filter(|user| !user.is_empty()) {
+      self.kiosk_user = Some(username);
+      self.lock = true;
+    }
+
+    if self.config().opt_present("autologin") {
+      let delay = match self.option("autologin-delay").map(|delay| delay.parse::<u64>()) {
+        None => 0,
+        Some(Ok(delay)) => delay,
+
+        Some(Err(_)) => {
+          eprintln!("--autologin-delay must be a number of seconds");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+
+      self.autologin_deadline = Some(Instant::now() + Duration::from_secs(delay));
+    }
+
+    if let Some(attempts) = self.option("lockout-attempts") {
+      match attempts.parse::<u32>() {
+        Ok(attempts) if attempts > 0 => self.lockout_attempts = Some(attempts),
+
+        _ => {
+          eprintln!("--lockout-attempts must be a positive number");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let Some(duration) = self.option("lockout-duration") {
+      match duration.parse::<u64>() {
+        Ok(duration) => self.lockout_duration = Duration::from_secs(duration),
+
+        Err(_) => {
+          eprintln!("--lockout-duration must be a number of seconds");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let Some(input) = self.option("otp-input") {
+      self.otp_input = match input.as_str() {
+        "auto" => OtpInput::Auto,
+        "always" => OtpInput::Always,
+        "never" => OtpInput::Never,
+
+        _ => {
+          eprintln!("--otp-input must be one of auto, always or never");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(length) = self.option("otp-length") {
+      self.otp_length = match length.parse::<usize>() {
+        Ok(0) => None,
+        Ok(length) => Some(length),
+
+        Err(_) => {
+          eprintln!("--otp-length must be a number of digits");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(normalization) = self.option("username-normalize") {
+      self.username_normalization = match normalization.as_str() {
+        "none" => UsernameNormalization::None,
+        "trim" => UsernameNormalization::Trim,
+        "lower" => UsernameNormalization::Lower,
+
+        _ => {
+          eprintln!("--username-normalize must be one of lower, trim or none");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(position) = self.option("window-position") {
+      self.window_position = match position.as_str() {
+        "top-left" => WindowPosition::TopLeft,
+        "top" => WindowPosition::Top,
+        "top-right" => WindowPosition::TopRight,
+        "left" => WindowPosition::Left,
+        "center" => WindowPosition::Center,
+        "right" => WindowPosition::Right,
+        "bottom-left" => WindowPosition::BottomLeft,
+        "bottom" => WindowPosition::Bottom,
+        "bottom-right" => WindowPosition::BottomRight,
+
+        position => match position.split_once(',').map(|(x, y)| (x.trim().parse::<u16>(), y.trim().parse::<u16>())) {
+          Some((Ok(x), Ok(y))) => WindowPosition::At(x, y),
+
+          _ => {
+            eprintln!("--window-position must be one of top-left, top, top-right, left, center, right, bottom-left, bottom, bottom-right or X,Y");
+            print_usage(opts);
+            process::exit(1);
+          }
+        },
+      };
+    }
+
+    if let Some(animation) = self.option("animations") {
+      self.animation = match animation.as_str() {
+        "off" => Animation::Off,
+        "slide" => Animation::Slide,
+        "fade" => Animation::Fade,
+
+        _ => {
+          eprintln!("--animations must be one of slide, fade or off");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if self.serial {
+      self.animation = Animation::Off;
+    }
+
+    if let Some(margin) = self.option("window-margin") {
+      self.window_margin = match margin.parse::<u16>() {
+        Ok(margin) => margin,
+
+        Err(_) => {
+          eprintln!("--window-margin must be a number of cells");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(spinner) = self.option("spinner") {
+      match Spinner::parse(&spinner) {
+        Some(spinner) => self.spinner = spinner,
+
+        None => {
+          eprintln!("--spinner must be one of line, dots, arc or none");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    // Braille and arc spinners cannot be displayed on all consoles.
+    if self.ascii && self.spinner != Spinner::None {
+      self.spinner = Spinner::Line;
+    }
+
+    if let Some(numlock) = self.option("numlock") {
+      self.numlock = match numlock.as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+
+        _ => {
+          eprintln!("--numlock must be either on or off");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(timeout) = self.option("idle-timeout") {
+      match timeout.parse::<u64>() {
+        Ok(timeout) if timeout > 0 => self.idle_timeout = Some(Duration::from_secs(timeout)),
+
+        _ => {
+          eprintln!("--idle-timeout must be a positive number of seconds");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let Some(timeout) = self.option("prompt-timeout") {
+      match timeout.parse::<u64>() {
+        Ok(timeout) if timeout > 0 => self.prompt_timeout = Some(Duration::from_secs(timeout)),
+
+        _ => {
+          eprintln!("--prompt-timeout must be a positive number of seconds");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    // Themes are ignored in high contrast mode, the terminal's default colors
+    // being the most legible.
+    if !self.high_contrast {
+      match self.load_theme() {
+        Ok(theme) => self.theme = theme,
+
+        Err(err) => {
+          eprintln!("Invalid theme: {err}");
+          process::exit(1);
+        }
+      }
+    }
+
+    match self.load_status() {
+      Ok((left, right)) => {
+        self.status_left = left;
+        self.status_right = right;
+      }
+
+      Err(err) => {
+        eprintln!("{err}");
+        print_usage(opts);
+        process::exit(1);
+      }
+    }
+
+    if let Some(users) = self.config().opt_str("hidden-users") {
+      self.hidden_users = users.split(',').map(str::trim).filter(|user| !user.is_empty()).map(str::to_string).collect();
+    }
+
+    if let Some(shells) = self.config().opt_str("hide-shells") {
+      self.hidden_shells = shells.split(',').map(str::trim).filter(|shell| !shell.is_empty()).map(str::to_string).collect();
+    }
+
+    // Usernames are completed against the same users as the menu lists.
+    if self.config().opt_present("user-menu") || self.config().opt_present("user-completion") {
+      self.user_menu = self.config().opt_present("user-menu");
+      self.user_completion = self.config().opt_present("user-completion");
+
+      let min_uid = self.config().opt_str("user-menu-min-uid").and_then(|uid| uid.parse::<u32>().ok());
+      let max_uid = self.config().opt_str("user-menu-max-uid").and_then(|uid| uid.parse::<u32>().ok());
+      let (min_uid, max_uid) = get_min_max_uids(min_uid, max_uid);
+
+      if min_uid >= max_uid {
+        eprintln!("Minimum UID ({min_uid}) must be less than maximum UID ({max_uid})");
+        process::exit(1);
+      }
+
+      self.users = get_users(min_uid, max_uid, &self.hidden_users, &self.hidden_shells);
+    }
+
+    // The user menu is always available in demo mode, with made up users.
+    if self.demo {
+      self.user_menu = true;
+      self.users = demo::users();
+    }
+
+    self.user_avatars = self.user_menu && self.config().opt_present("user-menu-avatars");
+
+    if self.config().opt_present("remember-session") && self.config().opt_present("remember-user-session") {
+      eprintln!("Only one of --remember-session and --remember-user-session may be used at the same time");
+      print_usage(opts);
+      process::exit(1);
+    }
+    if self.config().opt_present("remember-user-session") && !self.config().opt_present("remember") {
+      eprintln!("--remember-session must be used with --remember");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    self.remember = self.config().opt_present("remember");
+    self.remember_session = self.config().opt_present("remember-session");
+    self.remember_user_session = self.config().opt_present("remember-user-session");
+    self.user_commands = self.config().opt_present("user-commands") && !self.demo;
+    self.last_login = self.config().opt_present("last-login");
+    self.failed_logins = self.config().opt_present("failed-logins");
+    self.hook_messages = self.config().opt_present("hook-messages");
+    self.health_checks = self.config().opt_present("health-checks") && !self.demo;
+
+    self.command_path = search_path(self.option("command-path").as_deref());
+
+    // Commands are not run in demo mode, their widgets staying empty.
+    if !self.demo {
+      match self.config().opt_strs("widget-exec").iter().map(|spec| Widget::parse(spec)).collect() {
+        Ok(widgets) => self.widgets = widgets,
+
+        Err(err) => {
+          eprintln!("--widget-exec: {err}");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    // Nothing is read from or written to the cache in demo mode.
+    if self.demo {
+      self.remember = false;
+      self.remember_session = false;
+      self.remember_user_session = false;
+    }
+    self.asterisks = self.config().opt_present("asterisks");
+    self.deny_secret_paste = self.config().opt_present("deny-secret-paste");
+    self.composer.dead_keys = self.config().opt_present("dead-keys");
+    self.osk = self.config().opt_present("on-screen-keyboard");
+    self.mouse = self.config().opt_present("mouse");
+    self.command = self.option("cmd");
+
+    if let Some(layouts) = self.option("layouts") {
+      self.layouts = layouts.split(',').map(str::trim).filter(|layout| !layout.is_empty()).map(str::to_string).collect();
+    }
+
+    self.sessions_path = self.config().opt_strs("sessions");
+    self.xsessions_path = self.config().opt_strs("xsessions");
+    self.sessions_allow = self.session_names("sessions-allow");
+    self.sessions_deny = self.session_names("sessions-deny");
+    self.login_shell = self.config().opt_present("login-shell");
+    self.login_shell_sessions = self.session_names("login-shell-sessions");
+
+    match self.load_session_args() {
+      Ok(session_args) => self.session_args = session_args,
+
+      Err(err) => {
+        eprintln!("{err}");
+        print_usage(opts);
+        process::exit(1);
+      }
+    }
+
+    match self.load_xsession_wrapper() {
+      Ok(wrapper) => self.xsession_wrapper = wrapper,
+
+      Err(err) => {
+        eprintln!("{err}");
+        print_usage(opts);
+        process::exit(1);
+      }
+    }
+
+    match self.load_greeting() {
+      Ok(greeting) => self.greeting = greeting,
+
+      Err(err) => {
+        eprintln!("{err}");
+        process::exit(1);
+      }
+    }
+
+    let bindings = [
+      ("kb-command", &mut self.kb_command),
+      ("kb-sessions", &mut self.kb_sessions),
+      ("kb-power", &mut self.kb_power),
+      ("kb-layouts", &mut self.kb_layouts),
+      ("kb-environment", &mut self.kb_environment),
+      ("kb-locale", &mut self.kb_locale),
+      ("kb-dismiss", &mut self.kb_dismiss),
+    ];
+
+    for (name, binding) in bindings {
+      if let Some(value) = self.config.as_ref().and_then(|config| config.opt_str(name)) {
+        match Binding::parse(&value) {
+          Some(value) => *binding = value,
+
+          None => {
+            eprintln!("--{name} must be a function key (F1 to F12) or a letter used with Control (ctrl-p)");
+            print_usage(opts);
+            process::exit(1);
+          }
+        }
+      }
+    }
+
+    if let Some(value) = self.option("kb-debug") {
+      match Binding::parse(&value) {
+        Some(value) => self.kb_debug = Some(value),
+
+        None => {
+          eprintln!("--kb-debug must be a function key (F1 to F12) or a letter used with Control (ctrl-p)");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    self.debug_shell = self.config().opt_present("debug-shell");
+
+    let bindings = [self.kb_command, self.kb_sessions, self.kb_power, self.kb_layouts, self.kb_environment, self.kb_locale, self.kb_dismiss];
+    let bindings = bindings.iter().copied().chain(self.kb_debug).collect::<Vec<_>>();
+
+    if bindings.iter().enumerate().any(|(index, binding)| bindings[..index].contains(binding)) {
+      eprintln!("--kb-command, --kb-sessions, --kb-power, --kb-layouts, --kb-environment, --kb-locale, --kb-dismiss and --kb-debug must be bound to different keys");
+      print_usage(opts);
+      process::exit(1);
+    }
+
+    if let Some(command) = self.config().opt_str("power-shutdown") {
+      self.power_commands.insert(PowerOption::Shutdown, command);
+    }
+    if let Some(command) = self.config().opt_str("power-reboot") {
+      self.power_commands.insert(PowerOption::Reboot, command);
+    }
+
+    self.power_options = [PowerOption::Shutdown, PowerOption::Reboot]
+      .iter()
+      .map(|option| (*option, option.label().unwrap_or_default()))
+      .collect();
+
+    if let Some(states) = self.option("power-sleep") {
+      for state in states.split(',') {
+        let option = match PowerOption::parse_sleep(state) {
+          Some(option) => option,
+
+          None => {
+            eprintln!("--power-sleep must be a comma-separated list of suspend, hibernate or hybrid-sleep");
+            print_usage(opts);
+            process::exit(1);
+          }
+        };
+
+        if !option.is_supported() || self.power_options.iter().any(|(existing, _)| *existing == option) {
+          continue;
+        }
+
+        self.power_options.push((option, option.label().unwrap_or_default()));
+      }
+    }
+
+    for (index, entry) in self.config().opt_strs("power-custom").into_iter().enumerate() {
+      match entry.split_once('=') {
+        Some((label, command)) if !label.trim().is_empty() && !command.trim().is_empty() => {
+          self.power_options.push((PowerOption::Custom(index), label.trim().to_string()));
+          self.power_commands.insert(PowerOption::Custom(index), command.trim().to_string());
+        }
+
+        _ => {
+          eprintln!("--power-custom must be of the form 'LABEL=CMD [ARGS]...'");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    self.power_setsid = !self.config().opt_present("power-no-setsid");
+
+    if let Some(backend) = self.option("power-backend") {
+      self.power_backend = match backend.as_str() {
+        "shell" => PowerBackend::Shell,
+        "logind" => PowerBackend::Logind,
+
+        _ => {
+          eprintln!("--power-backend must be one of shell or logind");
+          print_usage(opts);
+          process::exit(1);
+        }
+      };
+    }
+
+    if let Some(timeout) = self.option("hook-timeout") {
+      match timeout.parse::<u64>() {
+        Ok(timeout) if timeout > 0 => self.hook_timeout = Duration::from_secs(timeout),
+
+        _ => {
+          eprintln!("--hook-timeout must be a positive number of seconds");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    if let Some(delay) = self.option("power-confirm") {
+      match delay.parse::<u64>() {
+        Ok(delay) if delay > 0 => self.power_confirm_delay = Some(Duration::from_secs(delay)),
+
+        _ => {
+          eprintln!("--power-confirm must be a positive number of seconds");
+          print_usage(opts);
+          process::exit(1);
+        }
+      }
+    }
+
+    // Isolation marks around the path would be printed as is to the console.
+    if let Err(err) = self.connect().await {
+      crate::ui::MESSAGES.set_use_isolating(false);
+
+      eprintln!("{err}");
+      process::exit(1);
+    }
+  }
+
+  // X11 sessions cannot be started as-is by greetd, so their command is
+  // prefixed with the wrapper they were found with, spawning an X server first.
+  pub fn session_command(&self) -> Option<Vec<String>> {
+    let command = self.command.as_ref()?;
+    let session = self.sessions.iter().find(|session| &session.command == command);
+
+    let command = match session.and_then(|session| session.wrapper.as_ref()) {
+      Some(wrapper) => wrapper.split_whitespace().map(String::from).chain([command.clone()]).collect(),
+      None => vec![command.clone()],
+    };
+
+    let environment = self.session_environment(session);
+
+    // greetd does not take environment variables along with the command, so
+    // they are set through env(1).
+    let command = match environment.is_empty() {
+      true => command,
+      false => iter::once("env".to_string()).chain(environment).chain(command).collect(),
+    };
+
+    // greetd runs the command through sh with SHELL set to the shell of the
+    // user, which is started as a login shell for it to read the profile of
+    // the user. Variables are set from within it, not to be overridden.
+    let login_shell = self.login_shell
+      || session
+        .iter()
+        .any(|session| !self.login_shell_sessions.is_empty() && session_allowed(session, &self.login_shell_sessions, &[]));
+
+    match login_shell {
+      true => Some(vec![
+        "\"${SHELL:-/bin/sh}\"".to_string(),
+        "-l".to_string(),
+        "-c".to_string(),
+        shell_quote(&format!("exec {}", command.join(" "))),
+      ]),
+      false => Some(command),
+    }
+  }
+
+  // Assembles the variables the session is started with. Sessions from desktop
+  // files advertise their type and desktop, and the language and keyboard
+  // layout chosen from the greeter are carried over, all of which can still be
+  // overridden by the variables the user set.
+  pub fn session_environment(&self, session: Option<&Session>) -> Vec<String> {
+    let mut environment = Vec::new();
+
+    if let Some(session) = session {
+      if let Some(session_type) = session.session_type.as_xdg_session_type() {
+        environment.push(format!("XDG_SESSION_TYPE={session_type}"));
+      }
+
+      if let Some(desktop_names) = &session.desktop_names {
+        environment.push(shell_quote(&format!("XDG_CURRENT_DESKTOP={desktop_names}")));
+      }
+    }
+
+    if let Some(locale) = &self.session_locale {
+      environment.push(format!("LANG={locale}"));
+      environment.push(format!("LC_ALL={locale}"));
+    }
+
+    if let Some(layout) = &self.session_layout {
+      environment.push(shell_quote(&format!("XKB_DEFAULT_LAYOUT={layout}")));
+    }
+
+    environment.extend(self.environment.iter().cloned());
+    environment
+  }
+
+  // Environment variables are given as space-separated NAME=value
+  // assignments, the offending assignment being returned if one is invalid.
+  pub fn parse_environment(text: &str) -> Result<Vec<String>, String> {
+    text
+      .split_whitespace()
+      .map(|assignment| match assignment.split_once('=') {
+        Some((name, _)) if !name.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => Ok(assignment.to_string()),
+        _ => Err(assignment.to_string()),
+      })
+      .collect()
+  }
+
+  pub fn push_message<S>(&mut self, level: MessageLevel, text: S)
+  where
+    S: Into<String>,
+  {
+    let text = text.into();
+
+    // Attempts are restarted by themselves in kiosk mode, which would
+    // otherwise repeat their messages for as long as they fail.
+    if self.kiosk_user.is_some() && self.messages.iter().any(|message| message.text == text) {
+      return;
+    }
+
+    self.messages.push(Message { level, text });
+    self.messages_scroll = 0;
+  }
+
+  // Sessions which failed to start are retried by validating the username,
+  // which is kept along with the selected session.
+  pub fn offer_retry(&mut self, username: String) {
+    self.username = username;
+    self.start_failed = true;
+    self.push_message(MessageLevel::Info, fl!("session_retry", key = self.kb_sessions.to_string()));
+  }
+
+  pub fn clear_messages(&mut self) {
+    self.messages.zeroize();
+    self.messages_scroll = 0;
+  }
+
+  // As getty does, users are told when and where they last logged in, and how
+  // many attempts failed since, so they can notice logins they did not make.
+  // The history is looked up away from the event loop, since reading it
+  // involves looking the user up, its result coming back as an event.
+  pub fn lookup_login_history(&mut self) {
+    if self.demo || (!self.last_login && !self.failed_logins) {
+      return;
+    }
+
+    self.login_history_lookup = Some(self.username.clone());
+    self.login_history_notify.notify_one();
+  }
+
+  pub fn show_login_history(&mut self, login: Option<LastLogin>, failures: Option<u32>) {
+    if let Some(login) = login {
+      let time = login.time.format_localized("%c", self.locale).to_string();
+
+      let message = match login.host.is_empty() {
+        true => fl!("last_login", time = time, line = login.line),
+        false => fl!("last_login_from", time = time, line = login.line, host = login.host),
+      };
+
+      self.push_message(MessageLevel::Info, message);
+    }
+
+    if let Some(count) = failures.filter(|count| *count > 0) {
+      self.push_message(MessageLevel::Error, fl!("failed_logins", count = count));
+    }
+  }
+
+  // With a fixed length, secrets are masked with the same number of characters
+  // whatever their actual length, as long as they are not empty.
+  pub fn mask_length(&self) -> usize {
+    let length = self.answer.chars().count();
+
+    match self.asterisks_length {
+      Some(fixed) if length > 0 => fixed,
+      _ => length,
+    }
+  }
+
+  // Opens the given menu or editor, remembering the prompt it was opened from
+  // rather than the one it replaces.
+  pub fn open(&mut self, mode: Mode) {
+    if !self.mode.is_overlay() {
+      self.previous_mode = self.mode;
+    }
+
+    self.mode = mode;
+  }
+
+  // Returns the selected entry of the menu of the current mode.
+  pub fn menu_selection(&mut self) -> Option<&mut usize> {
+    match self.mode {
+      Mode::Users => Some(&mut self.selected_user),
+      Mode::Sessions => Some(&mut self.selected_session),
+      Mode::Power => Some(&mut self.selected_power_option),
+      Mode::Layouts => Some(&mut self.selected_layout),
+      Mode::Locale => Some(&mut self.selected_locale),
+      _ => None,
+    }
+  }
+
+  pub fn set_prompt(&mut self, prompt: &str, secret: bool) {
+    let message = if prompt.ends_with(' ') { prompt.into() } else { format!("{prompt} ") };
+
+    let otp = match self.otp_input {
+      OtpInput::Auto => Greeter::is_otp_prompt(prompt),
+      OtpInput::Always => true,
+      OtpInput::Never => false,
+    };
+
+    self.prompt = Some(Prompt { message, secret, otp });
+  }
+
+  // Moves the current question to the answered ones, before its answer is
+  // sent.
+  pub fn answer_prompt(&mut self) {
+    if let Some(prompt) = self.prompt.take() {
+      let answer = (!prompt.secret).then(|| self.answer.to_string());
+
+      self.steps.push(Step { prompt, answer });
+    }
+  }
+
+  pub fn is_secret(&self) -> bool {
+    self.prompt.as_ref().map(|prompt| prompt.secret).unwrap_or_default()
+  }
+
+  // Hints at how the secret being typed fares against the rules of the
+  // deployment, such as the length of the PIN of a kiosk, giving its number
+  // of characters and what is wrong with it. This is only ever displayed.
+  pub fn password_hint(&self) -> Option<String> {
+    if self.mode != Mode::Password || !self.is_secret() || self.answer.is_empty() {
+      return None;
+    }
+
+    if self.password_min_length.is_none() && self.password_max_length.is_none() && self.password_classes.is_none() {
+      return None;
+    }
+
+    let length = self.answer.chars().count();
+
+    let count = match self.password_max_length {
+      Some(max) => format!("{length}/{max}"),
+      None => length.to_string(),
+    };
+
+    let problem = match (self.password_min_length, self.password_max_length, self.password_classes) {
+      (Some(min), _, _) if length < min => Some(fl!("password_too_short")),
+      (_, Some(max), _) if length > max => Some(fl!("password_too_long")),
+      (_, _, Some(classes)) if character_classes(&self.answer) < classes => Some(fl!("password_too_simple", classes = classes)),
+      _ => None,
+    };
+
+    match problem {
+      Some(problem) => Some(format!("{problem}  {count}")),
+      None => Some(count),
+    }
+  }
+
+  pub fn is_otp(&self) -> bool {
+    self.prompt.as_ref().map(|prompt| prompt.otp).unwrap_or_default()
+  }
+
+  // Whether a one-time code was typed in full and can be submitted without
+  // waiting for Enter.
+  pub fn otp_complete(&self) -> bool {
+    match self.otp_length {
+      Some(length) => self.mode == Mode::Password && self.is_otp() && self.answer.chars().count() >= length,
+      None => false,
+    }
+  }
+
+  // Prompts asking for a code from an authenticator application or a text
+  // message, as sent by modules such as pam_google_authenticator or pam_oath.
+  // Short keywords are matched as whole words so that they are not found
+  // inside unrelated ones.
+  pub fn is_otp_prompt(message: &str) -> bool {
+    let message = message.to_lowercase();
+    let words = message.split(|c: char| !c.is_alphanumeric()).collect::<Vec<_>>();
+
+    OTP_PROMPT_KEYWORDS.iter().any(|keyword| match keyword.chars().all(char::is_alphanumeric) {
+      true => words.contains(keyword),
+      false => message.contains(keyword),
+    })
+  }
+
+  pub fn remove_prompt(&mut self) {
+    self.prompt = None;
+  }
+
+  // Informational messages asking for some action on a device, such as a
+  // fingerprint reader or a security key, rather than for typed input.
+  pub fn is_device_prompt(message: &str) -> bool {
+    let message = message.to_lowercase();
+
+    DEVICE_PROMPT_KEYWORDS.iter().any(|keyword| message.contains(keyword))
+  }
+
+  pub fn prompt_width(&self) -> usize {
+    match &self.prompt {
+      None => 0,
+      Some(prompt) => text_width(&prompt.message) as usize,
+    }
+  }
+}
+
+fn options() -> Options {
+  let mut opts = Options::new();
+
+  opts.optflag("h", "help", "show this usage information");
+  opts.optflag("v", "version", "print version information");
+  opts.optflag("", "demo", "run with made up data, without connecting to greetd");
+  opts.optflagopt("", "debug", "log greetd messages, state changes and input events to a file (default: /tmp/tuigreet.log)", "FILE");
+  opts.optopt("", "capture-ipc", "record the requests sent to greetd and its responses to a file, answers being hashed", "FILE");
+  opts.optopt("", "replay-ipc", "run in demo mode, answering requests with the responses recorded with --capture-ipc", "FILE");
+  opts.optflag("", "plain", "print prompts line by line instead of drawing the interface, for screen readers");
+  opts.optflag("", "serial", "draw the interface for serial and dumb terminals, with ASCII borders and fewer refreshes");
+  opts.optopt("", "config", &format!("path to the configuration file (default: {})", config::DEFAULT_CONFIG_PATH), "FILE");
+  opts.optopt("c", "cmd", "command to run", "COMMAND");
+  opts.optopt("", "command-path", "colon-separated list of directories searched for commands (default: $PATH)", "DIRS");
+  opts.optmulti("s", "sessions", "colon-separated list of Wayland session paths", "DIRS");
+  opts.optmulti("", "xsessions", "colon-separated list of X11 session paths", "DIRS");
+  opts.optopt("", "session-default", "name of the session selected unless another one was remembered", "SESSION");
+  opts.optopt("", "sessions-allow", "comma-separated list of the only sessions to offer, by name or desktop file", "SESSIONS");
+  opts.optopt("", "sessions-deny", "comma-separated list of sessions never to offer, by name or desktop file", "SESSIONS");
+  opts.optopt("", "xsession-wrapper", "command X11 sessions are started through (default: startx /usr/bin/env)", "'CMD [ARGS]...'");
+  opts.optflag("", "no-xsession-wrapper", "start X11 sessions as they are, without a wrapper");
+  opts.optmulti("", "session-args", "replace the arguments of the session with the given name", "'SESSION=ARGS'");
+  opts.optflag("", "login-shell", "start sessions through the login shell of the user");
+  opts.optopt(
+    "",
+    "login-shell-sessions",
+    "comma-separated list of sessions to start through the login shell of the user, by name or desktop file",
+    "SESSIONS",
+  );
+  opts.optopt("w", "width", "width of the main prompt, or auto to fit its content (default: 80)", "WIDTH");
+  opts.optopt("", "prompt-width", "width of the prompt, overriding --width", "WIDTH");
+  opts.optopt("", "menu-width", "width of the menus, overriding --width", "WIDTH");
+  opts.optflag("i", "issue", "show the host's issue file");
+  opts.optopt("g", "greeting", "show custom text above login prompt", "GREETING");
+  opts.optopt("", "greeting-file", "show the contents of a file above login prompt", "FILE");
+  opts.optopt("", "banner", "show a banner with the given text at the top of the screen", "TEXT");
+  opts.optflag("", "banner-hostname", "show a banner with the hostname at the top of the screen");
+  opts.optopt("", "banner-file", "show a banner with the contents of a file at the top of the screen", "FILE");
+  opts.optopt("", "banner-align", "alignment of the banner: left, center or right (default: center)", "ALIGN");
+  opts.optopt(
+    "",
+    "identity",
+    "show the hostname, operating system and kernel release on the title bar or above the prompt: title or prompt",
+    "POSITION",
+  );
+  opts.optflag("t", "time", "display the current date and time");
+  opts.optopt("", "time-format", "custom strftime format for displaying date and time", "FORMAT");
+  opts.optopt("", "time-zone", "time zone of the displayed date and time (default: local)", "TZ");
+  opts.optmulti("", "clock", "additional clock on the title bar, such as 'TZ=UTC %H:%M'", "'TZ=ZONE [FORMAT]'");
+  opts.optopt("", "tick-rate", "interval between screen refreshes, in milliseconds (default: 250)", "MILLISECONDS");
+  opts.optopt("", "spinner", "animation displayed while waiting: line, dots, arc or none (default: line)", "STYLE");
+  opts.optflag("", "network", "display the network connection status");
+  opts.optopt("", "status-left", "comma-separated list of segments on the left of the status bar (default: keys,command)", "SEGMENTS");
+  opts.optopt(
+    "",
+    "status-right",
+    "comma-separated list of segments on the right of the status bar (default: battery,layout,num,caps)",
+    "SEGMENTS",
+  );
+  opts.optopt("", "status-clock-format", "strftime format of the clock segment of the status bar (default: %H:%M)", "FORMAT");
+  opts.optopt("", "numlock", "turn NumLock on or off at startup", "on|off");
+  opts.optopt("", "idle-timeout", "blank the screen after this many seconds without input", "SECONDS");
+  opts.optflag("", "idle-clock", "keep displaying the date and time while the screen is blank");
+  opts.optflag("", "idle-big-clock", "display a large clock above the date and time while the screen is blank");
+  opts.optopt("", "prompt-timeout", "reset the prompt after this many seconds without input", "SECONDS");
+  opts.optflag("r", "remember", "remember last logged-in username");
+  opts.optflag("", "remember-session", "remember last selected session");
+  opts.optflag("", "remember-user-session", "remember last selected session for each user");
+  opts.optflag("", "user-commands", "read the default command of each user from /etc/tuigreet/users.d or their home");
+  opts.optflag("", "last-login", "display when users last logged in once their username is entered");
+  opts.optflag("", "health-checks", "warn about low disk space, clock skew and failed services before login");
+  opts.optmulti(
+    "",
+    "widget-exec",
+    "run a command every SECONDS (default: 60), displaying its output in the status bar",
+    "'CMD[:SECONDS]'",
+  );
+  opts.optflag("", "failed-logins", "display the number of failed attempts since the last login once a username is entered");
+  opts.optopt("", "pre-auth-hook", "command to run once a username is entered, before authenticating", "CMD");
+  opts.optopt("", "post-auth-hook", "command to run after a successful authentication, before starting the session", "CMD");
+  opts.optopt("", "hook-timeout", "seconds after which hook commands are stopped (default: 10)", "SECONDS");
+  opts.optflag("", "hook-messages", "display the output of hook commands");
+  opts.optopt("", "autologin", "automatically log in as the given user", "USER");
+  opts.optopt("", "autologin-session", "session to start when logging in automatically", "SESSION");
+  opts.optopt("", "autologin-delay", "seconds to wait before logging in automatically (default: 0)", "SECONDS");
+  opts.optopt("", "kiosk-user", "only let the given user log in, asking for nothing but their password", "USER");
+  opts.optopt("", "lock", "lock the session of the given user, exiting once they are authenticated", "USER");
+  opts.optopt("", "lockout-attempts", "number of consecutive failed attempts before locking input out", "ATTEMPTS");
+  opts.optopt("", "lockout-duration", "seconds during which input is locked out (default: 30)", "SECONDS");
+  opts.optopt("", "otp-input", "only accept digits at one-time code prompts: auto, always or never (default: auto)", "MODE");
+  opts.optopt("", "otp-length", "submit one-time codes once this many digits are typed, or 0 to wait for Enter (default: 6)", "DIGITS");
+  opts.optopt("", "username-normalize", "normalize typed usernames: lower, trim or none (default: none)", "MODE");
+  opts.optflag("", "user-menu", "allow graphical selection of users from a menu");
+  opts.optflag("", "user-completion", "complete usernames with Tab");
+  opts.optflag("", "on-screen-keyboard", "display a keyboard navigated with arrows and Enter below the prompt");
+  opts.optflag("", "mouse", "allow clicking on menus, status bar actions and on-screen keyboard keys");
+  opts.optopt("", "user-menu-min-uid", "minimum UID to display in the user selection menu", "UID");
+  opts.optopt("", "user-menu-max-uid", "maximum UID to display in the user selection menu", "UID");
+  opts.optflag("", "user-menu-avatars", "display the initials of users next to their name in the user menu");
+  opts.optopt("", "hidden-users", "comma-separated list of users to leave out of the user menu", "USERS");
+  opts.optopt("", "hide-shells", "comma-separated list of login shells whose users are left out of the user menu", "SHELLS");
+  opts.optflag("", "asterisks", "display asterisks when a secret is typed");
+  opts.optflag("", "deny-secret-paste", "ignore text pasted at prompts for secrets");
+  opts.optflag("", "dead-keys", "compose accents typed on their own with the next letter, except in answers to PAM");
+  opts.optopt("", "asterisks-char", "character to be used to redact secrets (default: *)", "CHAR");
+  opts.optopt("", "asterisks-length", "display this many characters when a secret is typed, whatever its length", "LENGTH");
+  opts.optopt("", "password-min-length", "hint when a secret being typed is shorter than this", "LENGTH");
+  opts.optopt("", "password-max-length", "hint when a secret being typed is longer than this", "LENGTH");
+  opts.optopt(
+    "",
+    "password-classes",
+    "hint when a secret being typed mixes fewer kinds of characters (lowercase, uppercase, digits, others)",
+    "COUNT",
+  );
+  opts.optopt("", "window-padding", "padding inside the terminal area (default: 0)", "PADDING");
+  opts.optopt("", "window-position", "position of the main container: center, top, bottom-right, X,Y... (default: center)", "POSITION");
+  opts.optopt(
+    "",
+    "window-margin",
+    "space between the main container and the edges of the screen when not centered (default: 0)",
+    "MARGIN",
+  );
+  opts.optopt("", "animations", "animate changes between the prompt and menus: slide, fade or off (default: off)", "STYLE");
+  opts.optopt("", "container-padding", "padding inside the main prompt container (default: 1)", "PADDING");
+  opts.optopt("", "prompt-padding", "padding between prompt rows (default: 1)", "PADDING");
+  opts.optopt("", "theme", "define the application theme colors, inline or from a file", "THEME");
+  opts.optopt("", "background", "color filling the screen behind the containers", "COLOR");
+  opts.optopt("", "container-background", "color filling the containers", "COLOR");
+  opts.optflag("", "ascii", "only use ASCII characters for borders, banners and indicators");
+  opts.optopt("", "color-mode", "colors the terminal can display: auto, 16, 256 or truecolor (default: auto)", "MODE");
+  opts.optflag("", "high-contrast", "use thick borders and the default terminal colors, ignoring the theme");
+  opts.optflag("", "rtl", "lay out the prompt from right to left, regardless of the language");
+
+  opts.optopt("", "kb-command", "key used to change the session command (default: F2)", "KEY");
+  opts.optopt("", "kb-sessions", "key used to choose the session (default: F3)", "KEY");
+  opts.optopt("", "kb-power", "key used to open the power menu (default: F12)", "KEY");
+  opts.optopt("", "kb-layouts", "key used to open the keyboard layout menu (default: F4)", "KEY");
+  opts.optopt("", "kb-environment", "key used to edit the session environment (default: F5)", "KEY");
+  opts.optopt("", "kb-locale", "key used to choose the language of the greeter and session (default: F6)", "KEY");
+  opts.optopt("", "kb-dismiss", "key used to dismiss the warnings of health checks (default: F7)", "KEY");
+  opts.optopt("", "kb-debug", "key used to leave the interface and print the state of the greeter (default: none)", "KEY");
+  opts.optflag("", "debug-shell", "open a shell after printing the state of the greeter with --kb-debug");
+  opts.optopt("", "layouts", "comma-separated list of keyboard layouts to choose from, the first one being active", "LAYOUTS");
+  opts.optopt("", "layout-command", "command used to apply a keyboard layout (default: loadkeys)", "CMD [ARGS]...");
+
+  opts.optopt("", "power-shutdown", "command to run to shut down the system", "'CMD [ARGS]...'");
+  opts.optopt("", "power-reboot", "command to run to reboot the system", "'CMD [ARGS]...'");
+  opts.optflag("", "power-no-setsid", "do not prefix power commands with setsid");
+  opts.optopt("", "power-backend", "how to shut down and reboot without a custom command: shell or logind (default: shell)", "BACKEND");
+  opts.optopt(
+    "",
+    "power-sleep",
+    "comma-separated list of sleep states to add to the power menu, if supported",
+    "suspend,hibernate,hybrid-sleep",
+  );
+  opts.optmulti("", "power-custom", "add an entry to the power menu running the given command", "'LABEL=CMD [ARGS]...'");
+  opts.optopt("", "power-confirm", "ask for confirmation before power actions, running them after this many seconds", "SECONDS");
+
+  opts
+}
+
+// Lowercase and uppercase letters, digits and anything else.
+fn character_classes(text: &str) -> usize {
+  let classes: [fn(char) -> bool; 4] = [char::is_lowercase, char::is_uppercase, char::is_numeric, |c| !c.is_lowercase() && !c.is_uppercase() && !c.is_numeric()];
+
+  classes.iter().filter(|class| text.chars().any(class)).count()
+}
+
+// Zones are looked up in the IANA database built into the greeter, Z being
+// accepted for UTC as in ISO 8601.
+fn parse_time_zone(name: &str) -> Result<Tz, String> {
+  match name {
+    "Z" => Ok(Tz::UTC),
+    name => name.parse().map_err(|_| format!("invalid time zone name {name}")),
+  }
+}
+
+fn print_usage(opts: Options) {
+  eprint!("{}", opts.usage("Usage: tuigreet [OPTIONS]"));
+}
+
+fn print_version() {
+  println!("tuigreet {} ({})", env!("VERSION"), env!("TARGET"));
+  println!("Copyright (C) 2020 Antoine POPINEAU <https://github.com/apognu/tuigreet>.");
+  println!("Licensed under GPLv3+ (GNU GPL version 3 or later).");
+  println!();
+  println!("This is free software, you are welcome to redistribute it under some conditions.");
   println!("There is NO WARRANTY, to the extent provided by law.");
 }
+
+#[cfg(test)]
+mod tests {
+  use super::UsernameNormalization;
+
+  #[test]
+  fn usernames_are_kept_as_typed_by_default() {
+    assert_eq!(UsernameNormalization::None.apply(" Alice "), " Alice ");
+  }
+
+  #[test]
+  fn usernames_are_trimmed() {
+    assert_eq!(UsernameNormalization::Trim.apply(" Alice\t"), "Alice");
+  }
+
+  #[test]
+  fn usernames_are_lowercased_and_trimmed() {
+    assert_eq!(UsernameNormalization::Lower.apply(" ALICE "), "alice");
+    assert_eq!(UsernameNormalization::Lower.apply("Élodie"), "élodie");
+  }
+}
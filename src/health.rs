@@ -0,0 +1,57 @@
+use std::{
+  fs,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use nix::sys::statvfs::statvfs;
+use tokio::{process::Command, time};
+
+const RTC_SINCE_EPOCH: &str = "/sys/class/rtc/rtc0/since_epoch";
+const MIN_FREE_SPACE: u64 = 5;
+const MAX_CLOCK_SKEW: u64 = 60;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Looks for conditions an administrator should know about before someone logs
+// in, each failing probe giving a warning. Probes which cannot run are quietly
+// ignored.
+pub async fn check() -> Vec<String> {
+  vec![check_disk(), check_clock(), check_units().await].into_iter().flatten().collect()
+}
+
+// Sessions may fail to start when the root filesystem is full, the
+// percentage of free space being the one available to unprivileged users.
+fn check_disk() -> Option<String> {
+  let stats = statvfs("/").ok()?;
+  let total = stats.blocks() as u64;
+
+  if total == 0 {
+    return None;
+  }
+
+  let free = stats.blocks_available() as u64 * 100 / total;
+
+  (free < MIN_FREE_SPACE).then(|| fl!("health_disk", free = free))
+}
+
+// The hardware clock keeps UTC, so it is compared to the system time, which
+// is the one synchronized over the network.
+fn check_clock() -> Option<String> {
+  let rtc = fs::read_to_string(RTC_SINCE_EPOCH).ok()?.trim().parse::<u64>().ok()?;
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+  let skew = now.abs_diff(rtc);
+
+  (skew > MAX_CLOCK_SKEW).then(|| fl!("health_clock", seconds = skew))
+}
+
+async fn check_units() -> Option<String> {
+  let output = Command::new("systemctl").args(["--failed", "--no-legend", "--plain", "--no-pager"]).output();
+  let output = time::timeout(PROBE_TIMEOUT, output).await.ok()?.ok()?;
+
+  if !output.status.success() {
+    return None;
+  }
+
+  let count = String::from_utf8_lossy(&output.stdout).lines().filter(|line| !line.trim().is_empty()).count();
+
+  (count > 0).then(|| fl!("health_units", count = count))
+}
@@ -0,0 +1,116 @@
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use tokio::{
+  process::Command,
+  sync::{mpsc::Sender, RwLock},
+  time,
+};
+
+use crate::{event::Event, Greeter, MessageLevel};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+  PreAuth,
+  PostAuth,
+}
+
+impl Hook {
+  fn option(&self) -> &'static str {
+    match self {
+      Hook::PreAuth => "pre-auth-hook",
+      Hook::PostAuth => "post-auth-hook",
+    }
+  }
+}
+
+// A hook waiting to be run by the task, numbered for its result to be told
+// apart from the one of an attempt which was given up.
+pub struct HookRun {
+  pub id: u64,
+  pub hook: Hook,
+  script: String,
+  username: String,
+  timeout: Duration,
+  messages: bool,
+}
+
+// Queues the command configured for the given hook, which is run away from the
+// event loop, its result coming back as an event for the login to go on.
+// Returns whether there is one to wait for.
+pub fn queue(greeter: &mut Greeter, hook: Hook) -> bool {
+  let script = match greeter.option(hook.option()) {
+    Some(script) => script,
+    None => return false,
+  };
+
+  if greeter.demo {
+    return false;
+  }
+
+  greeter.hooks_run += 1;
+
+  let run = HookRun {
+    id: greeter.hooks_run,
+    hook,
+    script,
+    username: greeter.username.clone(),
+    timeout: greeter.hook_timeout,
+    messages: greeter.hook_messages,
+  };
+
+  greeter.hook_waiting = Some((run.id, hook));
+  greeter.hook_run = Some(run);
+  greeter.hook_notify.notify_one();
+
+  true
+}
+
+// Runs the queued hooks through the shell, with the username in their
+// environment. Their output is given when asked to, while failures are always
+// reported, without preventing the login from going on.
+pub async fn watch(greeter: Arc<RwLock<Greeter>>, sender: Sender<Event>) {
+  let notify = greeter.read().await.hook_notify.clone();
+
+  loop {
+    notify.notified().await;
+
+    let run = greeter.write().await.hook_run.take();
+
+    if let Some(run) = run {
+      let message = run_hook(&run).await;
+
+      if sender.send(Event::Hook(run.id, run.hook, message)).await.is_err() {
+        return;
+      }
+    }
+  }
+}
+
+async fn run_hook(run: &HookRun) -> Option<(MessageLevel, String)> {
+  let mut command = Command::new("sh");
+  command.arg("-c").arg(&run.script);
+  command.env("TUIGREET_USERNAME", &run.username);
+  command.stdin(Stdio::null());
+  command.kill_on_drop(true);
+
+  match time::timeout(run.timeout, command.output()).await {
+    Ok(Ok(result)) if result.status.success() => {
+      let output = String::from_utf8_lossy(&result.stdout).trim_end().to_string();
+
+      match run.messages && !output.is_empty() {
+        true => Some((MessageLevel::Info, output)),
+        false => None,
+      }
+    }
+
+    Ok(Ok(result)) => {
+      let status = format!("{} {}", fl!("command_exited"), result.status);
+      let output = String::from_utf8_lossy(&result.stderr).trim_end().to_string();
+
+      Some((MessageLevel::Error, format!("{status}\n{output}")))
+    }
+
+    Ok(Err(err)) => Some((MessageLevel::Error, format!("{}: {err}", fl!("command_failed")))),
+    Err(_) => Some((MessageLevel::Error, fl!("command_timed_out"))),
+  }
+}
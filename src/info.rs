@@ -1,25 +1,74 @@
 use std::{
+  collections::HashMap,
+  convert::TryInto,
   env,
   error::Error,
+  ffi::{CStr, OsStr},
   fs::{self, File},
-  io::{self, BufRead, BufReader},
+  io::{self, BufRead, BufReader, Read, SeekFrom},
+  mem,
+  os::unix::{
+    fs::{MetadataExt, OpenOptionsExt},
+    io::AsRawFd,
+  },
   path::{Path, PathBuf},
   process::Command,
+  time::Duration,
 };
 
+use chrono::{DateTime, Local, TimeZone};
 use ini::Ini;
-use nix::sys::utsname;
+use nix::{sys::utsname, unistd::User};
+use tokio::{
+  io::{AsyncReadExt, AsyncSeekExt},
+  task,
+};
 
-use crate::Greeter;
+use crate::{
+  ui::{Session, SessionType},
+  Greeter,
+};
 
 const X_SESSIONS: &str = "/usr/share/xsessions";
 const WAYLAND_SESSIONS: &str = "/usr/share/wayland-sessions";
 const LAST_USER_USERNAME: &str = "/var/cache/tuigreet/lastuser";
 const LAST_USER_NAME: &str = "/var/cache/tuigreet/lastuser-name";
 const LAST_SESSION: &str = "/var/cache/tuigreet/lastsession";
-
-const DEFAULT_MIN_UID: u16 = 1000;
-const DEFAULT_MAX_UID: u16 = 60000;
+const LAST_START: &str = "/var/cache/tuigreet/laststart";
+const COMMAND_HISTORY: &str = "/var/cache/tuigreet/commandhistory";
+const COMMAND_HISTORY_SIZE: usize = 50;
+const USER_COMMANDS: &str = "/etc/tuigreet/users.d";
+const USER_COMMAND_FILE: &str = ".config/tuigreet/command";
+const USER_COMMAND_SIZE: u64 = 4096;
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+const NETWORK_INTERFACES: &str = "/sys/class/net";
+const OS_RELEASE: &str = "/etc/os-release";
+const OS_RELEASE_FALLBACK: &str = "/usr/lib/os-release";
+const UPTIME: &str = "/proc/uptime";
+const LOAD_AVERAGE: &str = "/proc/loadavg";
+const LASTLOG: &str = "/var/log/lastlog";
+const LASTLOG_LINE_SIZE: usize = 32;
+const LASTLOG_HOST_SIZE: usize = 256;
+const FAILLOCK_DIR: &str = "/var/run/faillock";
+const FAILLOCK_RECORD_SIZE: usize = 64;
+const FAILLOCK_STATUS_VALID: u16 = 0x1;
+const FAILLOG: &str = "/var/log/faillog";
+const FAILLOG_RECORD_SIZE: usize = 32;
+
+const KDGKBLED: u64 = 0x4B64;
+const KDSKBLED: u64 = 0x4B65;
+const K_NUMLOCK: libc::c_char = 0x02;
+const K_CAPSLOCK: libc::c_char = 0x04;
+
+const INPUT_DEVICES: &str = "/dev/input";
+const LED_NUML: usize = 0x00;
+const LED_CAPSL: usize = 0x01;
+const LED_BYTES: usize = 2;
+// _IOC(_IOC_READ, 'E', 0x19, LED_BYTES), as defined in linux/input.h.
+const EVIOCGLED: u64 = (2 << 30) | ((LED_BYTES as u64) << 16) | ((b'E' as u64) << 8) | 0x19;
+
+const DEFAULT_MIN_UID: u32 = 1000;
+const DEFAULT_MAX_UID: u32 = 60000;
 
 pub fn get_hostname() -> String {
   match utsname::uname() {
@@ -28,44 +77,291 @@ pub fn get_hostname() -> String {
   }
 }
 
+pub fn get_kernel_release() -> String {
+  match utsname::uname() {
+    Ok(uts) => uts.release().to_str().unwrap_or("").to_string(),
+    _ => String::new(),
+  }
+}
+
+pub fn get_uptime() -> Option<Duration> {
+  let uptime = fs::read_to_string(UPTIME).ok()?;
+  let seconds = uptime.split_whitespace().next()?.parse::<f64>().ok()?;
+
+  Some(Duration::from_secs_f64(seconds))
+}
+
+// The load averages over the last one, five and fifteen minutes.
+pub fn get_load_average() -> Option<String> {
+  let load = fs::read_to_string(LOAD_AVERAGE).ok()?;
+
+  Some(load.split_whitespace().take(3).collect::<Vec<_>>().join(" "))
+}
+
+// Counts the sessions recorded in utmp, as uptime and w do.
+pub fn get_logged_in_count() -> usize {
+  let mut count = 0;
+
+  unsafe {
+    libc::setutxent();
+
+    loop {
+      let entry = libc::getutxent();
+
+      if entry.is_null() {
+        break;
+      }
+
+      if (*entry).ut_type == libc::USER_PROCESS {
+        count += 1;
+      }
+    }
+
+    libc::endutxent();
+  }
+
+  count
+}
+
+// greetd runs the greeter in a logind session, whose seat is usually exported
+// in XDG_SEAT, logind being queried otherwise.
+pub fn get_seat() -> Option<String> {
+  if let Ok(seat) = env::var("XDG_SEAT") {
+    if !seat.is_empty() {
+      return Some(seat);
+    }
+  }
+
+  let session = env::var("XDG_SESSION_ID").ok()?;
+  let output = Command::new("loginctl").args(["show-session", &session, "--property=Seat", "--value"]).output().ok()?;
+  let seat = String::from_utf8(output.stdout).ok()?.trim().to_string();
+
+  (output.status.success() && !seat.is_empty()).then_some(seat)
+}
+
 pub fn get_issue() -> Option<String> {
-  let vtnr: usize = env::var("XDG_VTNR").unwrap_or_else(|_| "0".to_string()).parse().expect("unable to parse VTNR");
-  let uts = utsname::uname();
-
-  if let Ok(issue) = fs::read_to_string("/etc/issue") {
-    let issue = issue.replace("\\S", "Linux").replace("\\l", &format!("tty{vtnr}"));
-
-    return match uts {
-      Ok(uts) => Some(
-        issue
-          .replace("\\s", uts.sysname().to_str().unwrap_or(""))
-          .replace("\\r", uts.release().to_str().unwrap_or(""))
-          .replace("\\v", uts.version().to_str().unwrap_or(""))
-          .replace("\\n", uts.nodename().to_str().unwrap_or(""))
-          .replace("\\m", uts.machine().to_str().unwrap_or(""))
-          .replace("\\\\", "\\"),
-      ),
-
-      _ => Some(issue),
-    };
+  fs::read_to_string("/etc/issue").ok().map(|issue| expand_issue_escapes(&issue))
+}
+
+pub fn get_greeting_file(path: &str) -> Result<String, io::Error> {
+  fs::read_to_string(path)
+}
+
+// Expands the escape sequences supported by agetty(8) in issue files, unknown
+// sequences being kept verbatim.
+pub fn expand_issue_escapes(issue: &str) -> String {
+  let vtnr = env::var("XDG_VTNR").ok().and_then(|vtnr| vtnr.parse::<usize>().ok()).unwrap_or(0);
+  let uts = utsname::uname().ok();
+  let uts_field = |field: fn(&utsname::UtsName) -> &OsStr| uts.as_ref().map(|uts| field(uts).to_string_lossy().to_string()).unwrap_or_default();
+
+  let mut output = String::with_capacity(issue.len());
+  let mut chars = issue.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      output.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some('S') => {
+        let variable = if chars.peek() == Some(&'{') {
+          chars.next();
+          Some(chars.by_ref().take_while(|c| *c != '}').collect::<String>())
+        } else {
+          None
+        };
+
+        let os_release = get_os_release();
+
+        match variable {
+          Some(variable) => output.push_str(os_release.get(&variable).map(String::as_str).unwrap_or_default()),
+          None => output.push_str(os_release.get("PRETTY_NAME").or_else(|| os_release.get("NAME")).map(String::as_str).unwrap_or("Linux")),
+        }
+      }
+
+      Some('l') => output.push_str(&format!("tty{vtnr}")),
+      Some('s') => output.push_str(&uts_field(utsname::UtsName::sysname)),
+      Some('r') => output.push_str(&uts_field(utsname::UtsName::release)),
+      Some('v') => output.push_str(&uts_field(utsname::UtsName::version)),
+      Some('n') => output.push_str(&uts_field(utsname::UtsName::nodename)),
+      Some('m') => output.push_str(&uts_field(utsname::UtsName::machine)),
+      Some('d') => output.push_str(&Local::now().format("%a %b %e %Y").to_string()),
+      Some('t') => output.push_str(&Local::now().format("%H:%M:%S").to_string()),
+      Some('\\') => output.push('\\'),
+
+      Some(other) => {
+        output.push('\\');
+        output.push(other);
+      }
+
+      None => output.push('\\'),
+    }
+  }
+
+  output
+}
+
+pub fn get_os_release() -> HashMap<String, String> {
+  let contents = fs::read_to_string(OS_RELEASE).or_else(|_| fs::read_to_string(OS_RELEASE_FALLBACK)).unwrap_or_default();
+
+  contents
+    .lines()
+    .filter_map(|line| line.split_once('='))
+    .map(|(key, value)| (key.trim().to_string(), value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()))
+    .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemIdentity {
+  pub hostname: String,
+  pub os: Option<String>,
+  pub kernel: String,
+}
+
+pub fn system_identity() -> SystemIdentity {
+  let os_release = get_os_release();
+
+  SystemIdentity {
+    hostname: get_hostname(),
+    os: os_release.get("PRETTY_NAME").or_else(|| os_release.get("NAME")).filter(|name| !name.is_empty()).cloned(),
+    kernel: get_kernel_release(),
+  }
+}
+
+// The cache directory is created on first use when the package did not ship
+// it, provided the greeter user is allowed to.
+fn write_cache(path: &str, contents: &str) {
+  if let Some(parent) = Path::new(path).parent() {
+    let _ = fs::create_dir_all(parent);
   }
 
-  None
+  let _ = fs::write(path, contents);
 }
 
 pub fn get_last_user_username() -> Result<String, io::Error> {
-  fs::read_to_string(LAST_USER_USERNAME)
+  fs::read_to_string(LAST_USER_USERNAME).map(|username| username.trim().to_string())
 }
 
 pub fn get_last_user_name() -> Option<String> {
-  fs::read_to_string(LAST_USER_NAME).ok()
+  fs::read_to_string(LAST_USER_NAME).ok().map(|name| name.trim().to_string())
+}
+
+pub struct LastLogin {
+  pub time: DateTime<Local>,
+  pub line: String,
+  pub host: String,
+}
+
+// Looks up the last login and the number of failed attempts of the user, as
+// asked for. Users may come from a directory service, which is queried away
+// from the runtime.
+pub async fn get_login_history(username: String, last_login: bool, failed_logins: bool) -> (Option<LastLogin>, Option<u32>) {
+  let uid = task::spawn_blocking({
+    let username = username.clone();
+
+    move || User::from_name(&username).ok().flatten().map(|user| user.uid.as_raw() as u64)
+  })
+  .await
+  .ok()
+  .flatten();
+
+  let login = match (last_login, uid) {
+    (true, Some(uid)) => get_last_login(uid).await,
+    _ => None,
+  };
+
+  let failures = match failed_logins {
+    true => match get_failed_logins(&username, uid).await {
+      Ok(count) => Some(count),
+
+      Err(err) => {
+        debug!("could not read failed logins: {err}");
+
+        None
+      }
+    },
+
+    false => None,
+  };
+
+  (login, failures)
+}
+
+// Records of the lastlog database are indexed by UID, and hold the time of the
+// last login of the user along with the terminal and host it came from.
+async fn get_last_login(uid: u64) -> Option<LastLogin> {
+  let size = 4 + LASTLOG_LINE_SIZE + LASTLOG_HOST_SIZE;
+
+  let mut file = tokio::fs::File::open(LASTLOG).await.ok()?;
+  let mut record = vec![0; size];
+
+  file.seek(SeekFrom::Start(uid * size as u64)).await.ok()?;
+  file.read_exact(&mut record).await.ok()?;
+
+  let time = i32::from_ne_bytes(record[..4].try_into().ok()?);
+
+  if time == 0 {
+    return None;
+  }
+
+  let field = |bytes: &[u8]| String::from_utf8_lossy(bytes.split(|byte| *byte == 0).next().unwrap_or_default()).trim().to_string();
+
+  Some(LastLogin {
+    time: Local.timestamp_opt(time as i64, 0).single()?,
+    line: field(&record[4..4 + LASTLOG_LINE_SIZE]),
+    host: field(&record[4 + LASTLOG_LINE_SIZE..]),
+  })
+}
+
+// Failed attempts are counted from the tally of pam_faillock, which is cleared
+// on successful logins, or from the faillog database of pam_tally when there is
+// none. Both are usually only readable by root, so errors are left to the caller
+// to report.
+async fn get_failed_logins(username: &str, uid: Option<u64>) -> io::Result<u32> {
+  if username.contains('/') {
+    return Ok(0);
+  }
+
+  match tokio::fs::read(Path::new(FAILLOCK_DIR).join(username)).await {
+    Ok(tally) => Ok(
+      tally
+        .chunks_exact(FAILLOCK_RECORD_SIZE)
+        .filter(|record| u16::from_ne_bytes([record[54], record[55]]) & FAILLOCK_STATUS_VALID != 0)
+        .count() as u32,
+    ),
+
+    Err(err) if err.kind() == io::ErrorKind::NotFound => match uid {
+      Some(uid) => get_faillog_count(uid).await,
+      None => Ok(0),
+    },
+    Err(err) => Err(err),
+  }
+}
+
+async fn get_faillog_count(uid: u64) -> io::Result<u32> {
+  let mut file = match tokio::fs::File::open(FAILLOG).await {
+    Ok(file) => file,
+    Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+    Err(err) => return Err(err),
+  };
+
+  let mut count = [0; 2];
+
+  file.seek(SeekFrom::Start(uid * FAILLOG_RECORD_SIZE as u64)).await?;
+
+  match file.read_exact(&mut count).await {
+    Ok(_) => Ok(i16::from_ne_bytes(count).max(0) as u32),
+    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(0),
+    Err(err) => Err(err),
+  }
 }
 
 pub fn write_last_username(username: &str, name: Option<&str>) {
-  let _ = fs::write(LAST_USER_USERNAME, username);
+  write_cache(LAST_USER_USERNAME, username);
 
   if let Some(name) = name {
-    let _ = fs::write(LAST_USER_NAME, name);
+    write_cache(LAST_USER_NAME, name);
   } else {
     let _ = fs::remove_file(LAST_USER_NAME);
   }
@@ -76,56 +372,172 @@ pub fn get_last_session() -> Result<String, io::Error> {
 }
 
 pub fn write_last_session(session: &str) {
-  let _ = fs::write(LAST_SESSION, session);
+  write_cache(LAST_SESSION, session);
 }
 
 pub fn get_last_user_session(username: &str) -> Result<String, io::Error> {
-  fs::read_to_string(&format!("{LAST_SESSION}-{username}"))
+  fs::read_to_string(format!("{LAST_SESSION}-{username}"))
 }
 
 pub fn write_last_user_session(username: &str, session: &str) {
-  let _ = fs::write(&format!("{LAST_SESSION}-{username}"), session);
+  write_cache(&format!("{LAST_SESSION}-{username}"), session);
+}
+
+pub enum UserCommand {
+  Command(String),
+  Session(String),
+  // Set by the user themselves.
+  Own(String),
+}
+
+// Those set by administrators take precedence over those of users. Both are
+// read from files which may be slow to reach.
+pub fn get_user_command(username: &str) -> Option<UserCommand> {
+  get_managed_user_command(username).or_else(|| get_own_user_command(username).map(UserCommand::Own))
+}
+
+// Administrators can give a user a default command, or the name of a default
+// session, with "command = CMD" or "session = NAME" in
+// /etc/tuigreet/users.d/USER.conf.
+fn get_managed_user_command(username: &str) -> Option<UserCommand> {
+  if !is_plain_username(username) {
+    return None;
+  }
+
+  let conf = Ini::load_from_file(Path::new(USER_COMMANDS).join(format!("{username}.conf"))).ok()?;
+  let section = conf.general_section();
+  let value = |key| section.get(key).map(str::trim).filter(|value| !value.is_empty()).map(str::to_string);
+
+  value("command").map(UserCommand::Command).or_else(|| value("session").map(UserCommand::Session))
+}
+
+// Users can set their own default command on the first line of
+// ~/.config/tuigreet/command. The greeter may be able to read files the user
+// cannot, so the file is only trusted if it belongs to the user, is not a
+// link and cannot be written by anyone else.
+fn get_own_user_command(username: &str) -> Option<String> {
+  if !is_plain_username(username) {
+    return None;
+  }
+
+  let user = User::from_name(username).ok()??;
+  let file = fs::OpenOptions::new().read(true).custom_flags(libc::O_NOFOLLOW).open(user.dir.join(USER_COMMAND_FILE)).ok()?;
+  let metadata = file.metadata().ok()?;
+
+  if !metadata.is_file() || metadata.uid() != user.uid.as_raw() || metadata.mode() & 0o022 != 0 {
+    return None;
+  }
+
+  let mut text = String::new();
+  file.take(USER_COMMAND_SIZE).read_to_string(&mut text).ok()?;
+
+  text.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#')).map(str::to_string)
+}
+
+// Usernames are typed by anyone, and must not lead out of the directories
+// files are read from.
+fn is_plain_username(username: &str) -> bool {
+  !username.is_empty() && !username.starts_with('.') && !username.contains('/')
+}
+
+// Sessions are recorded as they are started, for the next greeter to tell
+// whether the last one exited right away.
+pub fn write_last_start(username: &str, command: &str) {
+  write_cache(LAST_START, &format!("{}\n{username}\n{command}", Local::now().timestamp()));
 }
 
-pub fn get_users(min_uid: u16, max_uid: u16) -> Vec<(String, Option<String>)> {
-  match File::open("/etc/passwd") {
+// Returns the user and command of the last session started, if it was started
+// less than the given number of seconds ago. The record is only read once.
+pub fn take_last_start(within: i64) -> Option<(String, String)> {
+  let record = fs::read_to_string(LAST_START).ok()?;
+  let _ = fs::remove_file(LAST_START);
+
+  let mut lines = record.splitn(3, '\n');
+  let at = lines.next()?.parse::<i64>().ok()?;
+  let username = lines.next()?.to_string();
+  let command = lines.next()?.to_string();
+
+  (Local::now().timestamp() - at < within).then_some((username, command))
+}
+
+pub fn get_command_history() -> Vec<String> {
+  match fs::read_to_string(COMMAND_HISTORY) {
+    Ok(history) => history.lines().filter(|command| !command.trim().is_empty()).map(str::to_string).collect(),
     Err(_) => vec![],
-    Ok(file) => {
-      let file = BufReader::new(file);
+  }
+}
 
-      let users: Vec<(String, Option<String>)> = file
-        .lines()
-        .filter_map(|line| {
-          line
-            .map(|line| {
-              let mut split = line.splitn(7, ':');
-              let username = split.next();
-              let uid = split.nth(1);
-              let name = split.nth(1);
+// The history is kept with the most recent command last, without duplicates.
+pub fn add_command_history(history: &mut Vec<String>, command: &str) {
+  let command = command.trim();
+
+  if command.is_empty() || command.contains('\n') {
+    return;
+  }
 
-              match uid.map(|uid| uid.parse::<u16>()) {
-                Some(Ok(uid)) => match (username, name) {
-                  (Some(username), Some("")) => Some((uid, username.to_string(), None)),
-                  (Some(username), Some(name)) => Some((uid, username.to_string(), Some(name.to_string()))),
-                  _ => None,
-                },
+  history.retain(|entry| entry != command);
+  history.push(command.to_string());
 
-                _ => None,
-              }
-            })
-            .ok()
-            .flatten()
-            .filter(|(uid, _, _)| uid >= &min_uid && uid <= &max_uid)
-            .map(|(_, username, name)| (username, name))
-        })
-        .collect();
+  if history.len() > COMMAND_HISTORY_SIZE {
+    history.drain(..history.len() - COMMAND_HISTORY_SIZE);
+  }
+
+  write_cache(COMMAND_HISTORY, &(history.join("\n") + "\n"));
+}
 
-      users
+// Users are enumerated through the system's name service, so that accounts
+// that are not declared in /etc/passwd (LDAP, systemd-homed...) are listed.
+pub fn get_users(min_uid: u32, max_uid: u32, hidden_users: &[String], hidden_shells: &[String]) -> Vec<(String, Option<String>)> {
+  let mut users = vec![];
+
+  unsafe {
+    libc::setpwent();
+
+    loop {
+      let entry = libc::getpwent();
+
+      if entry.is_null() {
+        break;
+      }
+
+      let entry = &*entry;
+
+      if entry.pw_uid < min_uid || entry.pw_uid > max_uid || entry.pw_name.is_null() {
+        continue;
+      }
+
+      let username = CStr::from_ptr(entry.pw_name).to_string_lossy().to_string();
+
+      if hidden_users.contains(&username) {
+        continue;
+      }
+
+      // Service accounts are usually recognized by a shell refusing logins.
+      if !entry.pw_shell.is_null() && hidden_shells.iter().any(|shell| shell.as_str() == CStr::from_ptr(entry.pw_shell).to_string_lossy()) {
+        continue;
+      }
+      // The GECOS field may also hold the office and phone numbers of the user,
+      // separated by commas after their full name.
+      let name = match entry.pw_gecos.is_null() {
+        true => None,
+        false => CStr::from_ptr(entry.pw_gecos)
+          .to_string_lossy()
+          .split(',')
+          .next()
+          .map(|name| name.trim().to_string())
+          .filter(|name| !name.is_empty()),
+      };
+
+      users.push((username, name));
     }
+
+    libc::endpwent();
   }
+
+  users
 }
 
-pub fn get_min_max_uids(min_uid: Option<u16>, max_uid: Option<u16>) -> (u16, u16) {
+pub fn get_min_max_uids(min_uid: Option<u32>, max_uid: Option<u32>) -> (u32, u32) {
   if let (Some(min_uid), Some(max_uid)) = (min_uid, max_uid) {
     return (min_uid, max_uid);
   }
@@ -138,14 +550,14 @@ pub fn get_min_max_uids(min_uid: Option<u16>, max_uid: Option<u16>) -> (u16, u16
     Ok(file) => {
       let file = BufReader::new(file);
 
-      let uids: (u16, u16) = file.lines().fold(default, |acc, line| {
+      let uids: (u32, u32) = file.lines().fold(default, |acc, line| {
         line
           .map(|line| {
             let mut tokens = line.split_whitespace();
 
             match (overrides, tokens.next(), tokens.next()) {
-              ((None, _), Some("UID_MIN"), Some(value)) => (value.parse::<u16>().unwrap_or(acc.0), acc.1),
-              ((_, None), Some("UID_MAX"), Some(value)) => (acc.0, value.parse::<u16>().unwrap_or(acc.1)),
+              ((None, _), Some("UID_MIN"), Some(value)) => (value.parse::<u32>().unwrap_or(acc.0), acc.1),
+              ((_, None), Some("UID_MAX"), Some(value)) => (acc.0, value.parse::<u32>().unwrap_or(acc.1)),
               _ => acc,
             }
           })
@@ -157,44 +569,308 @@ pub fn get_min_max_uids(min_uid: Option<u16>, max_uid: Option<u16>) -> (u16, u16
   }
 }
 
-pub fn get_sessions(greeter: &Greeter) -> Result<Vec<(String, String)>, Box<dyn Error>> {
-  let sessions = match greeter.sessions_path {
-    Some(ref dirs) => env::split_paths(&dirs).collect(),
-    None => vec![PathBuf::from(X_SESSIONS), PathBuf::from(WAYLAND_SESSIONS)],
+pub fn get_sessions(greeter: &Greeter) -> Result<Vec<Session>, Box<dyn Error>> {
+  let split = |dirs: &Vec<String>, session_type: SessionType| dirs.iter().flat_map(env::split_paths).map(|dir| (dir, session_type)).collect::<Vec<_>>();
+
+  let sessions = match (greeter.sessions_path.is_empty(), greeter.xsessions_path.is_empty()) {
+    (true, true) => vec![(PathBuf::from(X_SESSIONS), SessionType::X11), (PathBuf::from(WAYLAND_SESSIONS), SessionType::Wayland)],
+    _ => [split(&greeter.xsessions_path, SessionType::X11), split(&greeter.sessions_path, SessionType::Wayland)].concat(),
   };
 
-  let mut files = sessions
-    .iter()
-    .flat_map(fs::read_dir)
-    .flat_map(|directory| directory.flat_map(|entry| entry.map(|entry| load_desktop_file(entry.path()))).flatten())
-    .collect::<Vec<_>>();
+  let mut files: Vec<Session> = Vec::new();
+
+  for (dir, session_type) in sessions {
+    if let Ok(directory) = fs::read_dir(dir) {
+      let wrapper = match session_type {
+        SessionType::X11 => greeter.xsession_wrapper.as_deref(),
+        _ => None,
+      };
+
+      for session in directory.flatten().flat_map(|entry| load_desktop_file(entry.path(), session_type, wrapper, &greeter.session_args)) {
+        if !files.iter().any(|file| file.name == session.name) {
+          files.push(session);
+        }
+      }
+    }
+  }
 
   if let Some(command) = &greeter.command {
-    files.insert(0, (command.clone(), command.clone()));
+    files.insert(0, Session::from_command(command));
   }
 
   Ok(files)
 }
 
-fn load_desktop_file<P>(path: P) -> Result<(String, String), Box<dyn Error>>
+fn load_desktop_file<P>(path: P, session_type: SessionType, wrapper: Option<&str>, session_args: &HashMap<String, String>) -> Result<Session, Box<dyn Error>>
 where
   P: AsRef<Path>,
 {
+  let id = path.as_ref().file_stem().map(|stem| stem.to_string_lossy().into_owned());
   let desktop = Ini::load_from_file(path)?;
   let section = desktop.section(Some("Desktop Entry")).ok_or("no Desktop Entry section in desktop file")?;
 
   let name = section.get("Name").ok_or("no Name property in desktop file")?;
   let exec = section.get("Exec").ok_or("no Exec property in desktop file")?;
+  let comment = section.get("Comment").map(str::trim).filter(|comment| !comment.is_empty()).map(str::to_string);
 
-  Ok((name.to_string(), exec.to_string()))
+  let mut args = parse_exec(exec).into_iter().map(|arg| shell_quote(&arg)).collect::<Vec<_>>();
+
+  // Arguments configured for a session replace those from its desktop file.
+  if let Some(overridden) = session_args.get(name) {
+    args.truncate(1);
+    args.push(overridden.clone());
+  }
+
+  if args.is_empty() {
+    return Err("empty Exec property in desktop file".into());
+  }
+
+  // DesktopNames is a semicolon-separated list, while XDG_CURRENT_DESKTOP is
+  // colon-separated.
+  let desktop_names = section
+    .get("DesktopNames")
+    .map(|names| names.split(';').map(str::trim).filter(|name| !name.is_empty()).collect::<Vec<_>>().join(":"))
+    .filter(|names| !names.is_empty());
+
+  Ok(Session {
+    name: name.to_string(),
+    command: args.join(" "),
+    comment,
+    session_type,
+    desktop_names,
+    wrapper: wrapper.map(str::to_string),
+    id,
+  })
+}
+
+// Splits an Exec property into arguments, following the quoting rules of the
+// Desktop Entry specification. Field codes are dropped, since sessions are not
+// started with files or URLs.
+fn parse_exec(exec: &str) -> Vec<String> {
+  let mut args = Vec::new();
+  let mut arg = String::new();
+  let mut in_arg = false;
+  let mut quoted = false;
+  let mut chars = exec.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '"' => {
+        quoted = !quoted;
+        in_arg = true;
+      }
+
+      '\\' if quoted => arg.extend(chars.next()),
+
+      '%' => {
+        if chars.next() == Some('%') {
+          arg.push('%');
+        }
+      }
+
+      c if c.is_whitespace() && !quoted => {
+        if in_arg || !arg.is_empty() {
+          args.push(mem::take(&mut arg));
+        }
+
+        in_arg = false;
+      }
+
+      c => {
+        arg.push(c);
+        in_arg = true;
+      }
+    }
+  }
+
+  if in_arg || !arg.is_empty() {
+    args.push(arg);
+  }
+
+  args
+}
+
+// greetd runs session commands through a shell, so arguments with special
+// characters have to be quoted to be passed along unchanged.
+pub fn shell_quote(arg: &str) -> String {
+  if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+    return arg.to_string();
+  }
+
+  format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Led {
+  NumLock,
+  CapsLock,
+}
+
+impl Led {
+  fn vt_flag(self) -> libc::c_char {
+    match self {
+      Led::NumLock => K_NUMLOCK,
+      Led::CapsLock => K_CAPSLOCK,
+    }
+  }
+
+  fn evdev_code(self) -> usize {
+    match self {
+      Led::NumLock => LED_NUML,
+      Led::CapsLock => LED_CAPSL,
+    }
+  }
 }
 
 pub fn capslock_status() -> bool {
-  let mut command = Command::new("kbdinfo");
-  command.args(["gkbled", "capslock"]);
+  led_status(Led::CapsLock)
+}
+
+pub fn numlock_status() -> bool {
+  led_status(Led::NumLock)
+}
+
+// The keyboard flags of the virtual console are used when running on one.
+// Otherwise, such as when running in a terminal under a compositor, the LEDs
+// of the input devices are looked up instead.
+fn led_status(led: Led) -> bool {
+  match vt_led_status(led) {
+    Some(status) => status,
+    None => evdev_led_status(led),
+  }
+}
+
+fn vt_led_status(led: Led) -> Option<bool> {
+  let mut flags: libc::c_char = 0;
 
-  match command.output() {
-    Ok(output) => output.status.code() == Some(0),
-    Err(_) => false,
+  if unsafe { libc::ioctl(io::stdin().as_raw_fd(), KDGKBLED as _, &mut flags) } < 0 {
+    return None;
   }
+
+  Some(flags & led.vt_flag() != 0)
+}
+
+// The LED is considered lit if it is on any of the devices that can be read,
+// which usually requires being a member of the `input` group.
+fn evdev_led_status(led: Led) -> bool {
+  let entries = match fs::read_dir(INPUT_DEVICES) {
+    Ok(entries) => entries,
+    Err(_) => return false,
+  };
+
+  entries.flatten().filter(|entry| entry.file_name().to_string_lossy().starts_with("event")).any(|entry| {
+    let file = match File::open(entry.path()) {
+      Ok(file) => file,
+      Err(_) => return false,
+    };
+
+    let mut leds = [0u8; LED_BYTES];
+
+    if unsafe { libc::ioctl(file.as_raw_fd(), EVIOCGLED as _, leds.as_mut_ptr()) } < 0 {
+      return false;
+    }
+
+    let code = led.evdev_code();
+
+    leds[code / 8] & (1 << (code % 8)) != 0
+  })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatteryStatus {
+  pub capacity: u8,
+  pub charging: bool,
+}
+
+// Aggregates all batteries reported by the kernel, averaging their capacity,
+// and considering them charging if any of them is.
+pub fn battery_status() -> Option<BatteryStatus> {
+  let mut batteries = Vec::new();
+
+  for entry in fs::read_dir(POWER_SUPPLY).ok()?.flatten() {
+    let path = entry.path();
+    let read = |name: &str| fs::read_to_string(path.join(name)).map(|value| value.trim().to_string());
+
+    if read("type").ok().as_deref() != Some("Battery") {
+      continue;
+    }
+
+    if let Ok(Ok(capacity)) = read("capacity").map(|capacity| capacity.parse::<u32>()) {
+      batteries.push((capacity.min(100), read("status").ok().as_deref() == Some("Charging")));
+    }
+  }
+
+  if batteries.is_empty() {
+    return None;
+  }
+
+  let capacity = batteries.iter().map(|(capacity, _)| capacity).sum::<u32>() / batteries.len() as u32;
+  let charging = batteries.iter().any(|(_, charging)| *charging);
+
+  Some(BatteryStatus { capacity: capacity as u8, charging })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkStatus {
+  Down,
+  Wired,
+  Wireless(Option<String>),
+}
+
+// Only physical interfaces (those backed by a device) are considered, wired
+// connections taking precedence over wireless ones.
+pub fn network_status() -> NetworkStatus {
+  let mut status = NetworkStatus::Down;
+
+  let entries = match fs::read_dir(NETWORK_INTERFACES) {
+    Ok(entries) => entries,
+    Err(_) => return status,
+  };
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+
+    if !path.join("device").exists() || fs::read_to_string(path.join("operstate")).map(|state| state.trim() == "up").ok() != Some(true) {
+      continue;
+    }
+
+    if !path.join("wireless").exists() && !path.join("phy80211").exists() {
+      return NetworkStatus::Wired;
+    }
+
+    if status == NetworkStatus::Down {
+      status = NetworkStatus::Wireless(get_ssid(&entry.file_name()));
+    }
+  }
+
+  status
+}
+
+fn get_ssid(interface: &OsStr) -> Option<String> {
+  let output = Command::new("iw").arg("dev").arg(interface).arg("link").output().ok()?;
+  let output = String::from_utf8_lossy(&output.stdout);
+
+  output.lines().find_map(|line| line.trim().strip_prefix("SSID: ").map(str::to_string))
+}
+
+// Changes the NumLock flag of the virtual console's keyboard, which also sets
+// the corresponding LED. This only works when running on a Linux VT.
+pub fn set_numlock(enabled: bool) -> io::Result<()> {
+  let fd = io::stdin().as_raw_fd();
+  let mut flags: libc::c_char = 0;
+
+  if unsafe { libc::ioctl(fd, KDGKBLED as _, &mut flags) } < 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  let flags = match enabled {
+    true => flags | K_NUMLOCK,
+    false => flags & !K_NUMLOCK,
+  };
+
+  if unsafe { libc::ioctl(fd, KDSKBLED as _, flags as libc::c_ulong) } < 0 {
+    return Err(io::Error::last_os_error());
+  }
+
+  Ok(())
 }
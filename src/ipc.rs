@@ -1,105 +1,306 @@
-use std::{error::Error, sync::Arc};
+use std::{
+  error::Error,
+  fmt::{self, Display},
+  io::{self, ErrorKind},
+  mem,
+  ops::Deref,
+  sync::{Arc, Weak},
+  time::Duration,
+};
 
-use greetd_ipc::{codec::TokioCodec, AuthMessageType, ErrorType, Request, Response};
-use tokio::sync::{
-  mpsc::{Receiver, Sender},
-  Mutex, RwLock,
+use greetd_ipc::{
+  codec::{self, TokioCodec},
+  AuthMessageType, ErrorType, Request, Response,
+};
+use tokio::{
+  net::UnixStream,
+  sync::{
+    mpsc::{Receiver, Sender},
+    Mutex, RwLock,
+  },
+  time,
 };
+use zeroize::Zeroize;
 
 use crate::{
-  info::{write_last_user_session, write_last_username},
-  AuthStatus, Greeter, Mode,
+  completion::missing_program,
+  debug, demo,
+  event::Event,
+  hooks::{self, Hook},
+  info::{write_last_start, write_last_user_session, write_last_username},
+  trace, AuthStatus, Greeter, MessageLevel, Mode,
 };
 
+// How long greetd is given to acknowledge a cancellation, after which the
+// connection is dropped all the same.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Failures of the exchange with greetd, classified to tell users what went
+// wrong rather than only that something did.
+#[derive(Debug)]
+pub enum IpcError {
+  SocketNotFound(String),
+  Socket(String, String),
+  // Along with the number of attempts left before input is locked out.
+  AuthFailed(Option<u32>),
+  SessionStart(String),
+  Greetd(String),
+}
+
+impl IpcError {
+  pub fn from_io(socket: &str, err: &io::Error) -> IpcError {
+    match err.kind() {
+      ErrorKind::NotFound | ErrorKind::ConnectionRefused => IpcError::SocketNotFound(socket.to_string()),
+      _ => IpcError::Socket(socket.to_string(), err.to_string()),
+    }
+  }
+
+  pub fn from_codec(socket: &str, err: &codec::Error) -> IpcError {
+    match err {
+      codec::Error::Eof => IpcError::Socket(socket.to_string(), fl!("ipc_closed")),
+      err => IpcError::Socket(socket.to_string(), err.to_string()),
+    }
+  }
+
+  // Errors returned once authentication succeeded can only come from starting
+  // the session.
+  pub fn from_response(greeter: &Greeter, error_type: ErrorType, description: String) -> IpcError {
+    match error_type {
+      ErrorType::AuthError => IpcError::AuthFailed(greeter.lockout_attempts.map(|attempts| attempts.saturating_sub(greeter.failures + 1))),
+      ErrorType::Error if greeter.done => IpcError::SessionStart(description),
+      ErrorType::Error => IpcError::Greetd(description),
+    }
+  }
+}
+
+impl Display for IpcError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IpcError::SocketNotFound(socket) => write!(f, "{}", fl!("ipc_socket_not_found", socket = socket.clone())),
+      IpcError::Socket(socket, error) => write!(f, "{}", fl!("ipc_socket_failed", socket = socket.clone(), error = error.clone())),
+      &IpcError::AuthFailed(Some(remaining)) if remaining > 0 => write!(f, "{}", fl!("failed_remaining", remaining = remaining)),
+      IpcError::AuthFailed(_) => write!(f, "{}", fl!("failed")),
+      IpcError::SessionStart(error) => write!(f, "{}", fl!("ipc_session_failed", error = error.clone())),
+      IpcError::Greetd(error) => write!(f, "{}", fl!("ipc_greetd_error", error = error.clone())),
+    }
+  }
+}
+
+impl Error for IpcError {}
+
+// A request waiting to be sent to greetd, along with the answer it may carry,
+// which is wiped once the request is dropped, however the exchange ended.
+pub struct Outgoing(Request);
+
+impl Deref for Outgoing {
+  type Target = Request;
+
+  fn deref(&self) -> &Request {
+    &self.0
+  }
+}
+
+impl Drop for Outgoing {
+  fn drop(&mut self) {
+    if let Request::PostAuthMessageResponse { response: Some(response) } = &mut self.0 {
+      response.zeroize();
+    }
+  }
+}
+
 #[derive(Clone)]
 pub struct Ipc(Arc<IpcHandle>);
 
 pub struct IpcHandle {
-  tx: RwLock<Sender<Request>>,
-  rx: Mutex<Receiver<Request>>,
+  tx: RwLock<Sender<Outgoing>>,
+  rx: Mutex<Receiver<Outgoing>>,
+  // The last request sent to greetd, with answers redacted, for debugging.
+  last_request: Mutex<Option<String>>,
+  // The connection on which an exchange was given up before greetd answered
+  // it, its response being still to come.
+  abandoned: Mutex<Weak<RwLock<UnixStream>>>,
 }
 
 impl Ipc {
   pub fn new() -> Ipc {
-    let (tx, rx) = tokio::sync::mpsc::channel::<Request>(10);
+    let (tx, rx) = tokio::sync::mpsc::channel::<Outgoing>(10);
 
     Ipc(Arc::new(IpcHandle {
       tx: RwLock::new(tx),
       rx: Mutex::new(rx),
+      last_request: Mutex::new(None),
+      abandoned: Mutex::new(Weak::new()),
     }))
   }
 
   pub async fn send(&self, request: Request) {
-    let _ = self.0.tx.read().await.send(request).await;
+    let _ = self.0.tx.read().await.send(Outgoing(request)).await;
+  }
+
+  pub async fn last_request(&self) -> Option<String> {
+    self.0.last_request.lock().await.clone()
   }
 
-  pub async fn next(&mut self) -> Option<Request> {
+  pub async fn next(&mut self) -> Option<Outgoing> {
     self.0.rx.lock().await.recv().await
   }
 
-  pub async fn handle(&mut self, greeter: Arc<RwLock<Greeter>>) -> Result<(), Box<dyn Error>> {
+  // Sends the next queued request to greetd and returns its response, which
+  // is left to the event loop to act upon.
+  pub async fn handle(&mut self, greeter: Arc<RwLock<Greeter>>) -> Result<Option<Event>, Box<dyn Error>> {
     let request = self.next().await;
 
     if let Some(request) = request {
-      let stream = {
-        let greeter = greeter.read().await;
+      let demo = greeter.read().await.demo;
 
-        greeter.stream.as_ref().unwrap().clone()
-      };
+      debug!("greetd request: {}", debug::redact_request(&request));
+      trace::request(&request);
+
+      *self.0.last_request.lock().await = Some(debug::redact_request(&request));
+
+      let canceling = matches!(*request, Request::CancelSession);
+
+      let response = match demo {
+        true => demo::respond(&request).await,
+
+        false if canceling => {
+          let stream = greeter.read().await.stream.as_ref().unwrap().clone();
+          let abandoned = mem::take(&mut *self.0.abandoned.lock().await)
+            .upgrade()
+            .map(|abandoned| Arc::ptr_eq(&abandoned, &stream))
+            .unwrap_or(false);
+
+          // The response to the request which was given up comes first, and
+          // must be read for the acknowledgment not to be mistaken for it.
+          let exchange = async {
+            if abandoned {
+              trace::response(&Response::read_from(&mut *stream.write().await).await?);
+            }
+
+            request.write_to(&mut *stream.write().await).await?;
+
+            Response::read_from(&mut *stream.write().await).await
+          };
+
+          // Whatever happened, the connection is replaced when the prompt is
+          // reset, which ends the session on the side of greetd.
+          match time::timeout(CANCEL_TIMEOUT, exchange).await {
+            Ok(Ok(response)) => {
+              debug!("greetd response: {response:?}");
+              trace::response(&response);
+            }
+
+            Ok(Err(err)) => debug!("greetd failure: {err:?}"),
+            Err(_) => debug!("greetd did not acknowledge the cancellation"),
+          }
+
+          return Ok(Some(Event::Canceled));
+        }
+
+        false => {
+          let (stream, socket, interrupt) = {
+            let greeter = greeter.read().await;
 
-      let response = {
-        request.write_to(&mut *stream.write().await).await?;
+            (greeter.stream.as_ref().unwrap().clone(), greeter.socket.clone(), greeter.interrupt.clone())
+          };
 
-        let response = Response::read_from(&mut *stream.write().await).await?;
+          let exchange = async {
+            request.write_to(&mut *stream.write().await).await?;
 
-        greeter.write().await.working = false;
+            Response::read_from(&mut *stream.write().await).await
+          };
 
-        response
+          // PAM modules waiting on a device may never answer, so the exchange
+          // is given up when the attempt is canceled.
+          let exchange = tokio::select! {
+            result = exchange => result,
+            _ = interrupt.notified() => {
+              *self.0.abandoned.lock().await = Arc::downgrade(&stream);
+
+              return Ok(None);
+            }
+          };
+
+          // Failures are left to the event loop as well.
+          match exchange {
+            Ok(response) => response,
+
+            Err(err) => {
+              let err = IpcError::from_codec(&socket, &err);
+
+              debug!("greetd failure: {err:?}");
+
+              return Ok(Some(Event::IpcFailed(err)));
+            }
+          }
+        }
       };
 
-      self.parse_response(&mut *greeter.write().await, response).await?;
+      debug!("greetd response: {response:?}");
+      trace::response(&response);
+
+      // Even demo responses to cancellations only acknowledge them.
+      if canceling {
+        return Ok(Some(Event::Canceled));
+      }
+
+      return Ok(Some(Event::Ipc(response)));
     }
 
-    Ok(())
+    Ok(None)
   }
 
-  async fn parse_response(&mut self, greeter: &mut Greeter, response: Response) -> Result<(), Box<dyn Error>> {
+  pub async fn parse_response(&self, greeter: &mut Greeter, response: Response) {
+    greeter.working = false;
+    greeter.last_response = Some(format!("{response:?}"));
+
     match response {
       Response::AuthMessage { auth_message_type, auth_message } => match auth_message_type {
         AuthMessageType::Secret => {
+          greeter.device_prompt = None;
           greeter.mode = Mode::Password;
           greeter.working = false;
-          greeter.secret = true;
-          greeter.set_prompt(&auth_message);
+          greeter.set_prompt(&auth_message, true);
         }
 
         AuthMessageType::Visible => {
+          greeter.device_prompt = None;
           greeter.mode = Mode::Password;
           greeter.working = false;
-          greeter.secret = false;
-          greeter.set_prompt(&auth_message);
+          greeter.set_prompt(&auth_message, false);
         }
 
         AuthMessageType::Error => {
-          greeter.message = Some(auth_message);
+          greeter.push_message(MessageLevel::Error, auth_message.trim_end());
 
           self.send(Request::PostAuthMessageResponse { response: None }).await;
         }
 
-        AuthMessageType::Info => {
+        AuthMessageType::Info if Greeter::is_device_prompt(&auth_message) => {
           greeter.remove_prompt();
+          greeter.clear_messages();
+          greeter.device_prompt = Some(auth_message.trim_end().to_string());
+          greeter.mode = Mode::Processing;
 
-          if let Some(message) = &mut greeter.message {
-            message.push('\n');
-            message.push_str(auth_message.trim_end());
-          } else {
-            greeter.message = Some(auth_message.trim_end().to_string());
-          }
+          self.send(Request::PostAuthMessageResponse { response: None }).await;
+        }
+
+        AuthMessageType::Info => {
+          greeter.remove_prompt();
+          greeter.push_message(MessageLevel::Info, auth_message.trim_end());
 
           self.send(Request::PostAuthMessageResponse { response: None }).await;
         }
       },
 
+      // Lockers only check that the user is there, their session being
+      // already running.
+      Response::Success if greeter.lock => {
+        Ipc::cancel(greeter).await;
+
+        crate::exit(greeter, AuthStatus::Success).await;
+      }
+
       Response::Success => {
         if greeter.done {
           if greeter.remember {
@@ -112,19 +313,17 @@ impl Ipc {
             }
           }
 
+          if let Some(command) = &greeter.command {
+            write_last_start(&greeter.username, command);
+          }
+
           crate::exit(greeter, AuthStatus::Success).await;
-        } else if let Some(command) = &greeter.command {
+        } else if greeter.session_command().is_some() {
+          greeter.device_prompt = None;
           greeter.done = true;
-          greeter.mode = Mode::Processing;
-
-          #[cfg(not(debug_assertions))]
-          self.send(Request::StartSession { cmd: vec![command.clone()] }).await;
 
-          #[cfg(debug_assertions)]
-          {
-            let _ = command;
-
-            crate::exit(greeter, AuthStatus::Success).await;
+          if !hooks::queue(greeter, Hook::PostAuth) {
+            self.start_session(greeter).await;
           }
         }
       }
@@ -132,24 +331,81 @@ impl Ipc {
       Response::Error { error_type, description } => {
         Ipc::cancel(greeter).await;
 
-        match error_type {
-          ErrorType::AuthError => {
-            greeter.message = Some(fl!("failed"));
-          }
+        let err = IpcError::from_response(greeter, error_type, description);
 
-          ErrorType::Error => {
-            greeter.message = Some(description);
-          }
+        if let IpcError::AuthFailed(_) = err {
+          greeter.failures += 1;
         }
 
+        // The user is kept when the session failed to start, for the attempt
+        // to be retried.
+        let username = match err {
+          IpcError::SessionStart(_) => Some(greeter.username.clone()),
+          _ => None,
+        };
+
+        greeter.push_message(MessageLevel::Error, err.to_string());
+
         greeter.reset().await;
+        greeter.check_lockout();
+
+        if let Some(username) = username {
+          greeter.offer_retry(username);
+        }
       }
     }
+  }
+
+  // Sessions whose program cannot be found are held, with the authentication,
+  // for users to fix the command rather than having to authenticate again
+  // after greetd failed to start it. Starting the same command again goes
+  // ahead anyway.
+  pub async fn start_session(&self, greeter: &mut Greeter) {
+    let command = match greeter.session_command() {
+      Some(command) => command,
+      None => return,
+    };
+
+    let missing = match greeter.demo {
+      true => None,
+      false => greeter.command.as_deref().and_then(|command| missing_program(command, &greeter.command_path)),
+    };
+
+    if let Some(program) = missing.filter(|_| greeter.start_pending != greeter.command) {
+      greeter.start_pending = greeter.command.clone();
+      greeter.mode = Mode::Password;
+      greeter.remove_prompt();
+
+      greeter.push_message(MessageLevel::Error, fl!("command_not_found", command = program));
+      greeter.push_message(
+        MessageLevel::Info,
+        fl!("command_not_found_hint", command = greeter.kb_command.to_string(), sessions = greeter.kb_sessions.to_string()),
+      );
+
+      return;
+    }
+
+    greeter.start_pending = None;
+    greeter.mode = Mode::Processing;
+
+    #[cfg(not(debug_assertions))]
+    self.send(Request::StartSession { cmd: command }).await;
 
-    Ok(())
+    #[cfg(debug_assertions)]
+    {
+      let _ = command;
+
+      crate::exit(greeter, AuthStatus::Success).await;
+    }
   }
 
   pub async fn cancel(greeter: &mut Greeter) {
+    if greeter.demo {
+      return;
+    }
+
+    greeter.interrupt.notify_waiters();
+
     let _ = Request::CancelSession.write_to(&mut *greeter.stream().await).await;
   }
 }
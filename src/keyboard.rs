@@ -1,300 +1,996 @@
-use std::{error::Error, sync::Arc};
+use std::{
+  error::Error,
+  fmt::{self, Display},
+  mem,
+  sync::Arc,
+  time::{Duration, Instant},
+};
 
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use greetd_ipc::Request;
-use tokio::sync::RwLock;
+use tokio::{sync::RwLock, time};
+use zeroize::Zeroize;
 
 use crate::{
-  event::{Event, Events},
-  info::{get_last_user_session, write_last_session},
+  completion::{self, Completion},
+  compose::{compose_str, Composed},
+  debug,
+  editor::LineEditor,
+  event::{Event, Events, Signal},
+  hooks::{self, Hook},
+  info::{add_command_history, get_last_user_session, write_last_session},
   ipc::Ipc,
+  layout,
   power::power,
-  ui::POWER_OPTIONS,
-  Greeter, Mode,
+  ui::{get_message_lines, navigate_menu, osk, visible_sessions, visible_users, Target, MESSAGES_MAX_HEIGHT},
+  Greeter, MessageLevel, Mode,
 };
 
-pub async fn handle(greeter: Arc<RwLock<Greeter>>, events: &mut Events, ipc: Ipc) -> Result<(), Box<dyn Error>> {
-  if let Some(Event::Key(input)) = events.next().await {
-    let mut greeter = greeter.write().await;
-
-    match input {
-      KeyEvent {
-        code: KeyCode::Char('u'),
-        modifiers: KeyModifiers::CONTROL,
-      } => match greeter.mode {
-        Mode::Username => greeter.username = String::new(),
-        Mode::Password => greeter.answer = String::new(),
-        Mode::Command => greeter.new_command = String::new(),
-        _ => {}
-      },
+const FRAME_DURATION: Duration = Duration::from_millis(25);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Binding {
+  Function(u8),
+  Control(char),
+}
+
+impl Binding {
+  // Bindings are either a function key (`F5`, or simply `5`) or a letter
+  // pressed along with Control (`ctrl-p`).
+  pub fn parse(value: &str) -> Option<Binding> {
+    let value = value.trim().to_lowercase();
+    let function = value.strip_prefix('f').unwrap_or(&value);
+
+    if let Ok(key @ 1..=12) = function.parse::<u8>() {
+      return Some(Binding::Function(key));
+    }
+
+    let mut chars = value.strip_prefix("ctrl-").or_else(|| value.strip_prefix("c-"))?.chars();
+
+    match (chars.next(), chars.next()) {
+      (Some(key), None) if key.is_ascii_lowercase() && !['a', 'b', 'e', 'f', 'k', 'u', 'w', 'x'].contains(&key) => Some(Binding::Control(key)),
+      _ => None,
+    }
+  }
+
+  pub fn key(&self) -> KeyEvent {
+    match self {
+      Binding::Function(key) => KeyEvent::new(KeyCode::F(*key), KeyModifiers::NONE),
+      Binding::Control(key) => KeyEvent::new(KeyCode::Char(*key), KeyModifiers::CONTROL),
+    }
+  }
+
+  pub fn matches(&self, input: &KeyEvent) -> bool {
+    match self {
+      Binding::Function(key) => input.code == KeyCode::F(*key),
+      Binding::Control(key) => input.code == KeyCode::Char(*key) && input.modifiers == KeyModifiers::CONTROL,
+    }
+  }
+}
+
+impl Display for Binding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Binding::Function(key) => write!(f, "F{key}"),
+      Binding::Control(key) => write!(f, "^{}", key.to_ascii_uppercase()),
+    }
+  }
+}
+
+// Returns whether the interface is to be left, as asked for with the debug key.
+pub async fn handle(greeter: Arc<RwLock<Greeter>>, events: &mut Events, ipc: Ipc) -> Result<bool, Box<dyn Error>> {
+  // While a transition is animated, events are not waited for longer than a
+  // frame, for the screen to be redrawn.
+  let transition = greeter.read().await.transition_progress();
+
+  let event = match transition {
+    Some(_) => time::timeout(FRAME_DURATION, events.next()).await.unwrap_or(None),
+    None => events.next().await,
+  };
+
+  let mut greeter = greeter.write().await;
+  let mode = greeter.mode;
+  let idle = greeter.idle;
+  let ticked = matches!(event, Some(Event::Tick) | None);
+
+  // Keys moving through the on-screen keyboard and clicks on it tell what is
+  // typed as much as characters do, so only their occurrence is logged while
+  // a secret may be entered.
+  let secret = greeter.mode == Mode::Password || greeter.osk;
+  let mut escape = false;
+
+  match event {
+    Some(Event::Tick) => tick(&mut greeter, &ipc).await,
+
+    Some(Event::Key(input)) => {
+      match secret {
+        true => debug!("key: <redacted>"),
+        false => debug!("key: {}", debug::redact_key(&input)),
+      }
+
+      escape = handle_key(&mut greeter, input, &ipc).await;
+    }
 
-      #[cfg(debug_assertions)]
-      KeyEvent {
-        code: KeyCode::Char('x'),
-        modifiers: KeyModifiers::CONTROL,
-      } => {
-        use crate::greeter::AuthStatus;
+    Some(Event::Paste(mut text)) => {
+      debug!("paste");
 
-        crate::exit(&mut greeter, AuthStatus::Cancel).await;
+      paste(&mut greeter, &text, &ipc).await;
+
+      text.zeroize();
+    }
+
+    Some(Event::Mouse(input)) => {
+      match secret {
+        true => debug!("mouse: <redacted>"),
+        false => debug!("mouse: {:?} at {},{}", input.kind, input.column, input.row),
       }
 
-      KeyEvent { code: KeyCode::Esc, .. } => {
-        Ipc::cancel(&mut greeter).await;
+      escape = handle_mouse(&mut greeter, input, &ipc).await;
+    }
+
+    // Responses to requests sent before the attempt was canceled are of no use
+    // anymore.
+    Some(Event::Ipc(_)) if greeter.cancelling => {}
+    Some(Event::Ipc(response)) => ipc.parse_response(&mut greeter, response).await,
+
+    // A lost connection leaves nothing to wait for, so the attempt is
+    // abandoned.
+    Some(Event::IpcFailed(err)) => {
+      greeter.push_message(MessageLevel::Error, err.to_string());
+      greeter.reset().await;
+    }
+
+    Some(Event::Canceled) => {
+      if greeter.cancelling {
         greeter.reset().await;
       }
+    }
 
-      KeyEvent { code: KeyCode::Left, .. } => greeter.cursor_offset -= 1,
-      KeyEvent { code: KeyCode::Right, .. } => greeter.cursor_offset += 1,
+    // The status bar may display something else once reloaded, to be probed
+    // in its place.
+    Some(Event::Signal(Signal::Hangup)) => match greeter.reload() {
+      Ok(()) => {
+        let probes = greeter.probes();
+        let widgets = greeter.widgets.clone();
 
-      KeyEvent { code: KeyCode::F(2), .. } => {
-        greeter.previous_mode = match greeter.mode {
-          Mode::Users | Mode::Command | Mode::Sessions | Mode::Power => greeter.previous_mode,
-          _ => greeter.mode,
-        };
+        greeter.tasks.update(&probes, &widgets, &events.sender());
+      }
 
-        greeter.new_command = greeter.command.clone().unwrap_or_default();
-        greeter.mode = Mode::Command;
+      Err(err) => greeter.push_message(MessageLevel::Error, format!("{}: {err}", fl!("reload_failed"))),
+    },
+
+    Some(Event::Notifications(notifications)) => greeter.notifications = notifications,
+    // The login goes on once the hook of the current attempt is done, those
+    // of attempts which were given up being ignored.
+    Some(Event::Hook(id, hook, message)) => {
+      if greeter.hook_waiting == Some((id, hook)) && !greeter.cancelling {
+        greeter.hook_waiting = None;
+
+        if let Some((level, message)) = message {
+          greeter.push_message(level, message);
+        }
+
+        match hook {
+          Hook::PreAuth => ipc.send(Request::CreateSession { username: greeter.username.clone() }).await,
+          Hook::PostAuth => ipc.start_session(&mut greeter).await,
+        }
+      }
+    }
+
+    // The default command of a user is only selected if they are still the one
+    // logging in.
+    Some(Event::UserCommand(username, command)) => {
+      let command = greeter.resolve_user_command(&username, command);
+
+      if username == greeter.username {
+        greeter.select_user_command(command);
+      }
+    }
+
+    Some(Event::LoginHistory(username, login, failures)) => {
+      if username == greeter.username {
+        greeter.show_login_history(login, failures);
       }
+    }
 
-      KeyEvent { code: KeyCode::F(3), .. } => {
-        greeter.previous_mode = match greeter.mode {
-          Mode::Users | Mode::Command | Mode::Sessions | Mode::Power => greeter.previous_mode,
-          _ => greeter.mode,
-        };
+    Some(Event::Battery(battery)) => greeter.battery = battery,
+    Some(Event::Network(network)) => greeter.network = Some(network),
 
-        greeter.mode = Mode::Sessions;
+    Some(Event::Widget(index, output)) => {
+      if let (Some(widget), Some(output)) = (greeter.widgets.get_mut(index), output) {
+        widget.output = Some(output);
       }
+    }
+
+    Some(Event::Executables(executables)) => greeter.executables = executables,
+
+    Some(Event::Resize) | None => {}
+  }
+
+  start_kiosk(&mut greeter, &ipc).await;
+
+  // Ticks only need the screen to be drawn again when they change what it
+  // shows, so that slow consoles are not laid out several times a second.
+  if !ticked || greeter.mode != mode || greeter.idle != idle || greeter.second_elapsed() {
+    greeter.dirty = true;
+  }
 
-      KeyEvent { code: KeyCode::F(12), .. } => {
-        greeter.previous_mode = match greeter.mode {
-          Mode::Users | Mode::Command | Mode::Sessions | Mode::Power => greeter.previous_mode,
-          _ => greeter.mode,
-        };
+  if greeter.mode != mode {
+    debug!("mode: {mode:?} -> {:?}", greeter.mode);
 
+    greeter.start_transition(mode);
+  }
+
+  Ok(escape)
+}
+
+async fn handle_key(greeter: &mut Greeter, input: KeyEvent, ipc: &Ipc) -> bool {
+  // A key press on an idle screen only brings the interface back.
+  if greeter.wake() {
+    return false;
+  }
+
+  if greeter.mode == Mode::Lockout || greeter.cancelling {
+    return false;
+  }
+
+  // Any key press during the automatic login countdown cancels it.
+  if greeter.mode == Mode::Autologin {
+    greeter.autologin_deadline = None;
+    greeter.username = String::new();
+    greeter.mode = Mode::Username;
+
+    return false;
+  }
+
+  // Power actions awaiting confirmation are run right away with Enter, and
+  // canceled with Escape.
+  if greeter.mode == Mode::PowerConfirm {
+    match input.code {
+      KeyCode::Enter => confirm_power(greeter),
+      KeyCode::Esc => {
+        greeter.power_confirm_deadline = None;
         greeter.mode = Mode::Power;
       }
 
-      KeyEvent { code: KeyCode::Up, .. } => {
-        if let Mode::Users = greeter.mode {
-          if greeter.selected_user > 0 {
-            greeter.selected_user -= 1;
-          }
+      _ => {}
+    }
+
+    return false;
+  }
+
+  let input = match on_screen_keyboard(greeter, input) {
+    Some(input) => input,
+    None => return false,
+  };
+
+  if input.code != KeyCode::Tab {
+    greeter.completion = None;
+  }
+
+  // Bindings cannot use any of the keys handled by the line editor, so those
+  // can be dispatched first.
+  if edit(greeter, input) {
+    // One-time codes are sent as soon as all their digits were typed.
+    if greeter.otp_complete() {
+      submit_answer(greeter, ipc).await;
+    }
+
+    return false;
+  }
+
+  if filter_menu(greeter, input) || move_selection(greeter, input) {
+    return false;
+  }
+
+  match input {
+    input if !greeter.lock && greeter.kb_command.matches(&input) => {
+      greeter.new_command = greeter.command.clone().unwrap_or_default();
+      greeter.command_history_index = None;
+      greeter.open(Mode::Command);
+    }
+
+    input if !greeter.lock && greeter.kb_environment.matches(&input) => {
+      greeter.new_environment = greeter.environment.join(" ");
+      greeter.open(Mode::Environment);
+    }
+
+    input if !greeter.lock && greeter.kb_sessions.matches(&input) => {
+      greeter.menu_filter.clear();
+      greeter.open(Mode::Sessions);
+    }
+
+    input if !greeter.lock && greeter.kb_power.matches(&input) => {
+      greeter.open(Mode::Power);
+    }
+
+    input if !greeter.layouts.is_empty() && greeter.kb_layouts.matches(&input) => {
+      greeter.selected_layout = greeter.current_layout;
+      greeter.open(Mode::Layouts);
+    }
+
+    input if greeter.kb_debug.map(|binding| binding.matches(&input)).unwrap_or(false) => return true,
+
+    input if !greeter.notifications.is_empty() && greeter.kb_dismiss.matches(&input) => {
+      greeter.notifications.clear();
+    }
+
+    input if greeter.locales.len() > 1 && greeter.kb_locale.matches(&input) => {
+      greeter.selected_locale = greeter.current_locale;
+      greeter.open(Mode::Locale);
+    }
+
+    #[cfg(debug_assertions)]
+    KeyEvent {
+      code: KeyCode::Char('x'),
+      modifiers: KeyModifiers::CONTROL,
+      ..
+    } => {
+      use crate::greeter::AuthStatus;
+
+      crate::exit(greeter, AuthStatus::Cancel).await;
+    }
+
+    KeyEvent { code: KeyCode::Esc, .. } => cancel(greeter, ipc).await,
+
+    KeyEvent { code: KeyCode::PageUp, .. } if greeter.messages_scroll < get_message_lines(greeter).len().saturating_sub(MESSAGES_MAX_HEIGHT) => greeter.messages_scroll += 1,
+
+    KeyEvent { code: KeyCode::PageDown, .. } => greeter.messages_scroll = greeter.messages_scroll.saturating_sub(1),
+
+    KeyEvent { code: KeyCode::Up, .. } if greeter.mode == Mode::Command => browse_history(greeter, true),
+    KeyEvent { code: KeyCode::Down, .. } if greeter.mode == Mode::Command => browse_history(greeter, false),
+
+    KeyEvent { code: KeyCode::Tab, .. } => match greeter.mode {
+      Mode::Command => complete_command(greeter),
+      Mode::Username if greeter.user_completion => complete_username(greeter, ipc).await,
+      Mode::Username if !greeter.username.is_empty() => validate_username(greeter, ipc).await,
+      _ => {}
+    },
+
+    KeyEvent { code: KeyCode::Enter, .. } => match greeter.mode {
+      Mode::Username if !greeter.username.is_empty() => validate_username(greeter, ipc).await,
+
+      Mode::Username if greeter.user_menu => {
+        greeter.menu_filter.clear();
+        greeter.open(Mode::Users);
+      }
+
+      Mode::Username => {}
+
+      Mode::Password if greeter.start_pending.is_some() => {
+        greeter.clear_messages();
+
+        ipc.start_session(greeter).await;
+      }
+
+      Mode::Password => submit_answer(greeter, ipc).await,
+
+      Mode::Command => {
+        let cmd = &greeter.command;
+
+        greeter.selected_session = greeter.sessions.iter().position(|session| Some(&session.command) == cmd.as_ref()).unwrap_or(0);
+        greeter.command = Some(greeter.new_command.clone());
+
+        if greeter.remember_session {
+          write_last_session(&greeter.new_command);
         }
 
-        if let Mode::Sessions = greeter.mode {
-          if greeter.selected_session > 0 {
-            greeter.selected_session -= 1;
-          }
+        let command = greeter.new_command.clone();
+
+        if !greeter.demo {
+          add_command_history(&mut greeter.command_history, &command);
         }
 
-        if let Mode::Power = greeter.mode {
-          if greeter.selected_power_option > 0 {
-            greeter.selected_power_option -= 1;
-          }
+        greeter.mode = greeter.previous_mode;
+      }
+
+      Mode::Environment => {
+        match Greeter::parse_environment(&greeter.new_environment) {
+          Ok(environment) => greeter.environment = environment,
+          Err(assignment) => greeter.push_message(MessageLevel::Error, fl!("invalid_environment", assignment = assignment)),
         }
+
+        greeter.mode = greeter.previous_mode;
       }
 
-      KeyEvent { code: KeyCode::Down, .. } => {
-        if let Mode::Users = greeter.mode {
-          if greeter.selected_user < greeter.users.len() - 1 {
-            greeter.selected_user += 1;
-          }
+      Mode::Users if visible_users(greeter).is_empty() => {}
+
+      Mode::Users => {
+        let username = greeter.users.get(greeter.selected_user).cloned();
+
+        if let Some((username, name)) = username {
+          greeter.username = username;
+          greeter.username_mask = name;
         }
 
-        if let Mode::Sessions = greeter.mode {
-          if greeter.selected_session < greeter.sessions.len() - 1 {
-            greeter.selected_session += 1;
+        validate_username(greeter, ipc).await;
+      }
+
+      Mode::Sessions if visible_sessions(greeter).is_empty() => {}
+
+      Mode::Sessions => {
+        let session = greeter.sessions.get(greeter.selected_session).map(|session| session.command.clone());
+
+        if let Some(command) = session {
+          if greeter.remember_session {
+            write_last_session(&command);
           }
+
+          greeter.command = Some(command);
         }
 
-        if let Mode::Power = greeter.mode {
-          if greeter.selected_power_option < POWER_OPTIONS.len() - 1 {
-            greeter.selected_power_option += 1;
-          }
+        greeter.menu_filter.clear();
+        greeter.mode = greeter.previous_mode;
+      }
+
+      Mode::Power => match greeter.power_confirm_delay {
+        Some(delay) => {
+          greeter.power_confirm_deadline = Some(Instant::now() + delay);
+          greeter.mode = Mode::PowerConfirm;
         }
+
+        None => confirm_power(greeter),
+      },
+
+      Mode::Layouts => {
+        let index = greeter.selected_layout;
+
+        layout::apply(greeter, index);
+
+        greeter.mode = greeter.previous_mode;
       }
 
-      KeyEvent {
-        code: KeyCode::Char('a'),
-        modifiers: KeyModifiers::CONTROL,
-      } => {
-        let value = {
-          match greeter.mode {
-            Mode::Username => &greeter.username,
-            _ => &greeter.answer,
-          }
-        };
+      Mode::Locale => {
+        let index = greeter.selected_locale;
 
-        greeter.cursor_offset = -(value.chars().count() as i16);
+        greeter.set_language(index);
+        greeter.mode = greeter.previous_mode;
       }
 
-      KeyEvent {
-        code: KeyCode::Char('e'),
-        modifiers: KeyModifiers::CONTROL,
-      } => greeter.cursor_offset = 0,
+      Mode::PowerConfirm | Mode::Processing | Mode::Autologin | Mode::Lockout => {}
+    },
 
-      KeyEvent { code: KeyCode::Tab, .. } => match greeter.mode {
-        Mode::Username if !greeter.username.is_empty() => validate_username(&mut greeter, &ipc).await,
-        _ => {}
-      },
+    _ => {}
+  }
 
-      KeyEvent { code: KeyCode::Enter, .. } => match greeter.mode {
-        Mode::Username if !greeter.username.is_empty() => validate_username(&mut greeter, &ipc).await,
+  false
+}
 
-        Mode::Username if greeter.user_menu => {
-          greeter.previous_mode = match greeter.mode {
-            Mode::Users | Mode::Command | Mode::Sessions | Mode::Power => greeter.previous_mode,
-            _ => greeter.mode,
-          };
+// Advances timers and animations, on every tick of whichever frontend is used.
+pub async fn tick(greeter: &mut Greeter, ipc: &Ipc) {
+  greeter.animate();
+  greeter.check_idle();
 
-          greeter.mode = Mode::Users;
-        }
+  if greeter.prompt_timed_out() {
+    cancel(greeter, ipc).await;
+    greeter.clear_messages();
+  }
 
-        Mode::Username => {}
+  if greeter.mode == Mode::Autologin && greeter.autologin_deadline.map(|deadline| deadline <= Instant::now()).unwrap_or(true) {
+    greeter.autologin_deadline = None;
+    greeter.mode = Mode::Processing;
 
-        Mode::Password => {
-          greeter.working = true;
-          greeter.message = None;
+    validate_username(greeter, ipc).await;
+  }
 
-          ipc
-            .send(Request::PostAuthMessageResponse {
-              response: Some(greeter.answer.clone()),
-            })
-            .await;
+  if greeter.mode == Mode::PowerConfirm && greeter.power_confirm_deadline.map(|deadline| deadline <= Instant::now()).unwrap_or(true) {
+    confirm_power(greeter);
+  }
 
-          greeter.answer = String::new();
-        }
+  if greeter.mode == Mode::Lockout && greeter.lockout_deadline.map(|deadline| deadline <= Instant::now()).unwrap_or(true) {
+    greeter.lockout_deadline = None;
+    greeter.mode = Mode::Username;
+  }
 
-        Mode::Command => {
-          let cmd = &greeter.command;
+  start_kiosk(greeter, ipc).await;
+}
 
-          greeter.selected_session = greeter.sessions.iter().position(|(_, command)| Some(command) == cmd.as_ref()).unwrap_or(0);
-          greeter.command = Some(greeter.new_command.clone());
+// Runs the selected power action, going back to where the power menu was
+// opened from.
+fn confirm_power(greeter: &mut Greeter) {
+  greeter.power_confirm_deadline = None;
 
-          if greeter.remember_session {
-            write_last_session(&greeter.new_command);
-          }
+  if let Some((option, _)) = greeter.power_options.get(greeter.selected_power_option).cloned() {
+    power(greeter, option);
+  }
 
-          greeter.mode = greeter.previous_mode;
-        }
+  greeter.mode = greeter.previous_mode;
+}
 
-        Mode::Users => {
-          let username = greeter.users.get(greeter.selected_user).cloned();
+// Pasted text is inserted at once into the value being edited, without going
+// through key bindings. Line breaks and tabs become spaces, other control
+// characters being left out, and one-time codes only keep their digits, being
+// submitted if complete.
+async fn paste(greeter: &mut Greeter, text: &str, ipc: &Ipc) {
+  if greeter.wake() || greeter.mode == Mode::Lockout || greeter.cancelling {
+    return;
+  }
 
-          if let Some((username, name)) = username {
-            greeter.username = username;
-            greeter.username_mask = name;
-          }
+  let secret = greeter.mode == Mode::Password && greeter.prompt.as_ref().map(|prompt| prompt.secret).unwrap_or(false);
 
-          validate_username(&mut greeter, &ipc).await;
-        }
+  if secret && greeter.deny_secret_paste {
+    greeter.push_message(MessageLevel::Error, fl!("paste_denied"));
 
-        Mode::Sessions => {
-          let session = match greeter.sessions.get(greeter.selected_session) {
-            Some((_, command)) => Some(command.clone()),
-            _ => None,
-          };
+    return;
+  }
 
-          if let Some(command) = session {
-            if greeter.remember_session {
-              write_last_session(&command);
-            }
+  let otp = greeter.mode == Mode::Password && greeter.is_otp();
 
-            greeter.command = Some(command);
-          }
+  let mut text = text
+    .trim_end_matches(['\r', '\n'])
+    .chars()
+    .map(|c| if matches!(c, '\t' | '\n' | '\r') { ' ' } else { c })
+    .filter(|c| !c.is_control() && (!otp || c.is_ascii_digit()))
+    .collect::<String>();
+
+  let value = match greeter.mode {
+    Mode::Username => &mut greeter.username,
+    Mode::Password if greeter.start_pending.is_none() => &mut greeter.answer,
+    Mode::Command => &mut greeter.new_command,
+    Mode::Environment => &mut greeter.new_environment,
+    _ => return text.zeroize(),
+  };
+
+  if greeter.mode != Mode::Password {
+    text = compose_str(&text);
+  }
+
+  LineEditor::new(value, &mut greeter.cursor_offset).insert_str(&text);
+
+  text.zeroize();
+  greeter.completion = None;
+
+  if greeter.otp_complete() {
+    submit_answer(greeter, ipc).await;
+  }
+}
+
+// Clicks are translated into the key presses they stand for, after selecting
+// the clicked menu entry or on-screen keyboard key, and the wheel moves the
+// selection of menus.
+async fn handle_mouse(greeter: &mut Greeter, input: MouseEvent, ipc: &Ipc) -> bool {
+  // A click on an idle screen only brings the interface back.
+  if greeter.wake() {
+    return false;
+  }
 
-          greeter.mode = greeter.previous_mode;
+  let code = match input.kind {
+    MouseEventKind::ScrollUp => KeyCode::Up,
+    MouseEventKind::ScrollDown => KeyCode::Down,
+
+    MouseEventKind::Down(MouseButton::Left) => match target_at(greeter, input.column, input.row) {
+      Some(Target::Key(key)) => return handle_key(greeter, key, ipc).await,
+
+      Some(Target::Entry(index)) => {
+        match greeter.menu_selection() {
+          Some(selection) => *selection = index,
+          None => return false,
         }
 
-        Mode::Power => {
-          if let Some((option, _)) = POWER_OPTIONS.get(greeter.selected_power_option) {
-            power(&mut greeter, *option);
-          }
+        KeyCode::Enter
+      }
+
+      Some(Target::Osk(row, column)) => {
+        greeter.osk_selection = (row, column);
+
+        KeyCode::Enter
+      }
+
+      None => return false,
+    },
+
+    _ => return false,
+  };
+
+  handle_key(greeter, KeyEvent::new(code, KeyModifiers::NONE), ipc).await
+}
+
+// Widgets drawn last are on top of the others.
+fn target_at(greeter: &Greeter, x: u16, y: u16) -> Option<Target> {
+  greeter
+    .hitboxes
+    .iter()
+    .rev()
+    .find(|(area, _)| x >= area.left() && x < area.right() && y >= area.top() && y < area.bottom())
+    .map(|(_, target)| *target)
+}
+
+// While the on-screen keyboard is displayed, arrows move its selection, and
+// Enter is translated into a press of the selected key, to be handled as if it
+// had been typed.
+fn on_screen_keyboard(greeter: &mut Greeter, input: KeyEvent) -> Option<KeyEvent> {
+  if !greeter.osk || (greeter.mode != Mode::Username && greeter.mode != Mode::Password) {
+    return Some(input);
+  }
+
+  let (row, column) = greeter.osk_selection;
+  let length = osk::row_length(row);
+
+  // Moving between rows of different lengths keeps the selection at about the
+  // same horizontal position.
+  let move_to = |row: usize| (row, column * osk::row_length(row) / length);
+
+  greeter.osk_selection = match input.code {
+    KeyCode::Up => move_to((row + osk::rows() - 1) % osk::rows()),
+    KeyCode::Down => move_to((row + 1) % osk::rows()),
+    KeyCode::Left => (row, (column + length - 1) % length),
+    KeyCode::Right => (row, (column + 1) % length),
+
+    KeyCode::Enter => {
+      let code = match osk::key(row, column, greeter.osk_shift)? {
+        osk::Key::Char(c) => KeyCode::Char(c),
+        osk::Key::Space => KeyCode::Char(' '),
+        osk::Key::Backspace => KeyCode::Backspace,
+        osk::Key::Enter => KeyCode::Enter,
 
-          greeter.mode = greeter.previous_mode;
+        osk::Key::Shift => {
+          greeter.osk_shift = !greeter.osk_shift;
+
+          return None;
         }
+      };
 
-        Mode::Processing => {}
-      },
+      greeter.osk_shift = false;
 
-      KeyEvent { code: KeyCode::Char(c), .. } => insert_key(&mut greeter, c).await,
+      return Some(KeyEvent::new(code, KeyModifiers::NONE));
+    }
 
-      KeyEvent { code: KeyCode::Backspace, .. } | KeyEvent { code: KeyCode::Delete, .. } => delete_key(&mut greeter, input.code).await,
+    _ => return Some(input),
+  };
 
-      _ => {}
+  None
+}
+
+// Applies line editing keys to the input of the current mode.
+fn edit(greeter: &mut Greeter, input: KeyEvent) -> bool {
+  let otp = greeter.mode == Mode::Password && greeter.is_otp();
+
+  let value = match greeter.mode {
+    Mode::Username => &mut greeter.username,
+    Mode::Password if greeter.start_pending.is_some() => return false,
+    Mode::Password => &mut greeter.answer,
+    Mode::Command => &mut greeter.new_command,
+    Mode::Environment => &mut greeter.new_environment,
+    _ => return false,
+  };
+
+  // Arrows move the cursor visually, towards the start of the value when
+  // pointing to the side it starts from.
+  let input = match (greeter.rtl, input.code) {
+    (true, KeyCode::Left) => KeyEvent::new(KeyCode::Right, input.modifiers),
+    (true, KeyCode::Right) => KeyEvent::new(KeyCode::Left, input.modifiers),
+    _ => input,
+  };
+
+  // One-time codes only take digits, typed or erased at their end.
+  if otp {
+    let modified = input.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+
+    greeter.cursor_offset = 0;
+
+    return match input.code {
+      KeyCode::Char(c) if (modified && (c == 'u' || c == 'w')) || (!modified && c.is_ascii_digit()) => LineEditor::new(value, &mut greeter.cursor_offset).handle(input),
+      KeyCode::Backspace => LineEditor::new(value, &mut greeter.cursor_offset).handle(input),
+      KeyCode::Char(_) => !modified,
+      KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End | KeyCode::Delete => true,
+      _ => false,
+    };
+  }
+
+  let mut editor = LineEditor::new(value, &mut greeter.cursor_offset);
+
+  // Answers are sent to PAM exactly as typed, which is how they were typed
+  // when they were set, while other values have their accents composed.
+  if greeter.mode != Mode::Password {
+    match input.code {
+      KeyCode::Char(c) if !input.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+        match greeter.composer.feed(editor.previous(), c) {
+          Composed::Replace(c) => editor.replace_previous(c),
+          Composed::Insert(text) => editor.insert_str(&text),
+          Composed::Pending => {}
+        }
+
+        return true;
+      }
+
+      _ => {
+        if let Some(accent) = greeter.composer.flush() {
+          editor.insert_str(&accent.to_string());
+        }
+      }
     }
   }
 
-  Ok(())
+  editor.handle(input)
 }
 
-async fn insert_key(greeter: &mut Greeter, c: char) {
-  let value = match greeter.mode {
-    Mode::Username => &greeter.username,
-    Mode::Password => &greeter.answer,
-    Mode::Command => &greeter.new_command,
-    Mode::Users | Mode::Sessions | Mode::Power | Mode::Processing => return,
+// Usernames are completed against the enumerated users, which leaves out the
+// hidden ones. Those matching none of them are still validated with Tab, for
+// accounts which cannot be enumerated.
+async fn complete_username(greeter: &mut Greeter, ipc: &Ipc) {
+  let username = match &mut greeter.completion {
+    Some(completion) => completion.next().to_string(),
+
+    None => match Completion::new(&greeter.username, greeter.users.iter().map(|(username, _)| username.clone())) {
+      Some(completion) => {
+        let username = completion.current().to_string();
+
+        greeter.completion = Some(completion);
+
+        username
+      }
+
+      None if !greeter.username.is_empty() => return validate_username(greeter, ipc).await,
+      None => return,
+    },
   };
 
-  let index = (value.chars().count() as i16 + greeter.cursor_offset) as usize;
-  let left = value.chars().take(index);
-  let right = value.chars().skip(index);
+  greeter.username = username;
+  greeter.username_mask = None;
+  greeter.cursor_offset = 0;
+}
 
-  let value = left.chain(vec![c].into_iter()).chain(right).collect();
-  let mode = greeter.mode;
+// Only the end of the command can be completed, which is where it is typed.
+fn complete_command(greeter: &mut Greeter) {
+  if greeter.cursor_offset != 0 {
+    return;
+  }
 
-  match mode {
-    Mode::Username => greeter.username = value,
-    Mode::Password => greeter.answer = value,
-    Mode::Command => greeter.new_command = value,
-    _ => {}
+  let command = match &mut greeter.completion {
+    Some(completion) => completion.next().to_string(),
+
+    None => match completion::complete_command(&greeter.new_command, &greeter.executables) {
+      Some(completion) => {
+        let command = completion.current().to_string();
+
+        greeter.completion = Some(completion);
+
+        command
+      }
+
+      None => return,
+    },
   };
+
+  greeter.new_command = command;
 }
 
-async fn delete_key(greeter: &mut Greeter, key: KeyCode) {
-  let value = match greeter.mode {
-    Mode::Username => &greeter.username,
-    Mode::Password => &greeter.answer,
-    Mode::Command => &greeter.new_command,
-    Mode::Users | Mode::Sessions | Mode::Power | Mode::Processing => return,
+// Walks through previously entered commands, the command being edited before
+// browsing being restored when going past the most recent one.
+fn browse_history(greeter: &mut Greeter, older: bool) {
+  let index = match (greeter.command_history_index, older) {
+    (None, true) if !greeter.command_history.is_empty() => {
+      greeter.command_draft = greeter.new_command.clone();
+
+      Some(greeter.command_history.len() - 1)
+    }
+
+    (Some(index), true) => Some(index.saturating_sub(1)),
+    (Some(index), false) if index + 1 < greeter.command_history.len() => Some(index + 1),
+    (Some(_), false) => None,
+    (None, _) => return,
   };
 
-  let index = match key {
-    KeyCode::Backspace => (value.chars().count() as i16 + greeter.cursor_offset - 1) as usize,
-    KeyCode::Delete => (value.chars().count() as i16 + greeter.cursor_offset) as usize,
-    _ => 0,
+  greeter.new_command = match index {
+    Some(index) => greeter.command_history[index].clone(),
+    None => mem::take(&mut greeter.command_draft),
   };
 
-  if value.chars().nth(index as usize).is_some() {
-    let left = value.chars().take(index);
-    let right = value.chars().skip(index + 1);
+  greeter.command_history_index = index;
+  greeter.cursor_offset = 0;
+}
 
-    let value = left.chain(right).collect();
+// Moves the selection of the menu of the current mode.
+fn move_selection(greeter: &mut Greeter, input: KeyEvent) -> bool {
+  if !input.modifiers.is_empty() {
+    return false;
+  }
 
-    match greeter.mode {
-      Mode::Username => greeter.username = value,
-      Mode::Password => greeter.answer = value,
-      Mode::Command => greeter.new_command = value,
-      Mode::Users | Mode::Sessions | Mode::Power | Mode::Processing => return,
-    };
+  let selection = match navigate_menu(greeter, input.code) {
+    Some(selection) => selection,
+    None => return false,
+  };
+
+  if let Some(selected) = greeter.menu_selection() {
+    *selected = selection;
+  }
+
+  true
+}
+
+// Typing in the user and session menus edits the filter of their entries.
+fn filter_menu(greeter: &mut Greeter, input: KeyEvent) -> bool {
+  if greeter.mode != Mode::Users && greeter.mode != Mode::Sessions {
+    return false;
+  }
+
+  match input.code {
+    KeyCode::Char(c) if !input.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => greeter.menu_filter.push(c),
+    KeyCode::Backspace => {
+      greeter.menu_filter.pop();
+    }
+
+    _ => return false,
+  }
+
+  select_visible(greeter);
 
-    if let KeyCode::Delete = key {
-      greeter.cursor_offset += 1;
+  true
+}
+
+// Moves the selection of the current menu to the first entry matching the
+// filter if the selected entry was filtered out.
+fn select_visible(greeter: &mut Greeter) {
+  match greeter.mode {
+    Mode::Users => {
+      let visible = visible_users(greeter);
+
+      if !visible.iter().any(|(index, _, _)| *index == greeter.selected_user) {
+        if let Some((index, _, _)) = visible.first() {
+          greeter.selected_user = *index;
+        }
+      }
+    }
+
+    Mode::Sessions => {
+      let visible = visible_sessions(greeter);
+
+      if !visible.iter().any(|(index, _, _)| *index == greeter.selected_session) {
+        if let Some((index, _, _)) = visible.first() {
+          greeter.selected_session = *index;
+        }
+      }
     }
+
+    _ => {}
+  }
+}
+
+pub async fn validate_username(greeter: &mut Greeter, ipc: &Ipc) {
+  greeter.username = greeter.username_normalization.apply(&greeter.username);
+
+  if greeter.username.is_empty() {
+    greeter.cursor_offset = 0;
+    greeter.mode = Mode::Username;
+    return;
   }
+
+  greeter.clear_messages();
+
+  create_session(greeter, ipc).await;
 }
 
-async fn validate_username(greeter: &mut Greeter, ipc: &Ipc) {
+// Gives up the attempt in progress, the prompt being reset once greetd
+// acknowledged it, for the next attempt not to start while PAM is still busy
+// with this one. Nothing needs to be waited for before the username is sent.
+pub async fn cancel(greeter: &mut Greeter, ipc: &Ipc) {
+  if !greeter.working && greeter.mode == Mode::Username && greeter.previous_mode == Mode::Username {
+    greeter.reset().await;
+
+    return;
+  }
+
+  greeter.cancelling = true;
+  greeter.working = true;
+  greeter.device_prompt = None;
+  greeter.mode = Mode::Processing;
+  greeter.remove_prompt();
+
+  // An exchange still waiting on greetd is given up first, for the request to
+  // be sent right away.
+  greeter.interrupt.notify_waiters();
+
+  ipc.send(Request::CancelSession).await;
+}
+
+// Kiosks never ask for the username, authentication starting over as soon as
+// the greeter goes back to the username prompt, messages from the previous
+// attempt being kept. Returns whether it did.
+pub async fn start_kiosk(greeter: &mut Greeter, ipc: &Ipc) -> bool {
+  let username = match &greeter.kiosk_user {
+    Some(username) if greeter.mode == Mode::Username && !greeter.working => username.clone(),
+    _ => return false,
+  };
+
+  greeter.username = username;
+  greeter.username_mask = None;
+  greeter.mode = Mode::Processing;
+
+  create_session(greeter, ipc).await;
+
+  true
+}
+
+async fn create_session(greeter: &mut Greeter, ipc: &Ipc) {
   greeter.working = true;
-  greeter.message = None;
 
-  ipc.send(Request::CreateSession { username: greeter.username.clone() }).await;
-  greeter.answer = String::new();
+  if !hooks::queue(greeter, Hook::PreAuth) {
+    ipc.send(Request::CreateSession { username: greeter.username.clone() }).await;
+  }
 
+  greeter.answer.zeroize();
+  greeter.lookup_login_history();
+
+  // The session last started by the user takes precedence over their default
+  // command, which is then not looked up.
   if greeter.remember_user_session {
-    if let Ok(command) = get_last_user_session(&greeter.username) {
-      greeter.selected_session = greeter.sessions.iter().position(|(_, cmd)| Some(cmd) == Some(&command)).unwrap_or(0);
-      greeter.command = Some(command);
+    if let Ok(command) = get_last_user_session(&greeter.username).map(|command| command.trim().to_string()) {
+      if greeter.is_selectable(&command) {
+        greeter.select_user_command(Some(command));
+
+        return;
+      }
     }
   }
+
+  greeter.lookup_user_command(&greeter.username.clone());
+}
+
+pub async fn submit_answer(greeter: &mut Greeter, ipc: &Ipc) {
+  greeter.working = true;
+  greeter.mode = Mode::Processing;
+  greeter.clear_messages();
+  greeter.answer_prompt();
+
+  // The answer is handed over rather than copied, to be wiped along with the
+  // request.
+  ipc
+    .send(Request::PostAuthMessageResponse {
+      response: Some(mem::take(&mut *greeter.answer)),
+    })
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+  use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+  use super::Binding;
+
+  #[test]
+  fn function_keys_are_parsed() {
+    assert_eq!(Binding::parse("F1"), Some(Binding::Function(1)));
+    assert_eq!(Binding::parse("f12"), Some(Binding::Function(12)));
+    assert_eq!(Binding::parse(" 5 "), Some(Binding::Function(5)));
+  }
+
+  #[test]
+  fn function_keys_are_bounded() {
+    assert_eq!(Binding::parse("F0"), None);
+    assert_eq!(Binding::parse("F13"), None);
+    assert_eq!(Binding::parse("F-1"), None);
+  }
+
+  #[test]
+  fn control_keys_are_parsed_whatever_their_case() {
+    assert_eq!(Binding::parse("ctrl-p"), Some(Binding::Control('p')));
+    assert_eq!(Binding::parse("Ctrl-P"), Some(Binding::Control('p')));
+    assert_eq!(Binding::parse("C-l"), Some(Binding::Control('l')));
+  }
+
+  #[test]
+  fn editing_keys_cannot_be_bound() {
+    for key in ["ctrl-a", "ctrl-u", "ctrl-w", "Ctrl-X"] {
+      assert_eq!(Binding::parse(key), None, "{key}");
+    }
+  }
+
+  #[test]
+  fn invalid_bindings_are_rejected() {
+    for value in ["", "ctrl-", "ctrl-pp", "ctrl-1", "alt-p", "p", "F1x"] {
+      assert_eq!(Binding::parse(value), None, "{value}");
+    }
+  }
+
+  #[test]
+  fn bindings_only_match_their_key() {
+    let binding = Binding::Control('p');
+
+    assert!(binding.matches(&KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL)));
+    assert!(!binding.matches(&KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE)));
+    assert!(!binding.matches(&KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+
+    assert!(Binding::Function(5).matches(&Binding::Function(5).key()));
+    assert!(!Binding::Function(5).matches(&KeyEvent::new(KeyCode::F(6), KeyModifiers::NONE)));
+  }
 }
@@ -0,0 +1,65 @@
+use std::{process::Stdio, sync::Arc};
+
+use tokio::{process::Command, sync::RwLock};
+
+use crate::{Greeter, MessageLevel};
+
+const DEFAULT_LAYOUT_COMMAND: &str = "loadkeys";
+
+// Queues the command applying the layout at the given index, configured with
+// the layout name as its last argument, to be run away from the event loop as
+// power actions are.
+pub fn apply(greeter: &mut Greeter, index: usize) {
+  let layout = match greeter.layouts.get(index) {
+    Some(layout) => layout.clone(),
+    None => return,
+  };
+
+  if greeter.demo {
+    greeter.current_layout = index;
+    greeter.session_layout = Some(layout);
+    return;
+  }
+
+  let program = greeter.option("layout-command").unwrap_or_else(|| DEFAULT_LAYOUT_COMMAND.to_string());
+  let mut args = program.split_whitespace();
+
+  let mut command = Command::new(args.next().unwrap_or_default());
+  command.args(args);
+  command.arg(&layout);
+
+  command.stdin(Stdio::null());
+  command.stdout(Stdio::null());
+
+  greeter.layout_action = Some((index, command));
+  greeter.layout_command_notify.notify_one();
+}
+
+pub async fn run(greeter: &Arc<RwLock<Greeter>>, index: usize, mut command: Command) {
+  let message = match command.output().await {
+    Ok(result) if result.status.success() => None,
+
+    Ok(result) => {
+      let status = format!("{} {}", fl!("command_exited"), result.status);
+      let output = String::from_utf8(result.stderr).unwrap_or_default();
+
+      Some(format!("{status}\n{output}"))
+    }
+
+    Err(err) => Some(format!("{}: {err}", fl!("command_failed"))),
+  };
+
+  let mut greeter = greeter.write().await;
+
+  greeter.clear_messages();
+  greeter.dirty = true;
+
+  match message {
+    None => {
+      greeter.current_layout = index;
+      greeter.session_layout = greeter.layouts.get(index).cloned();
+    }
+
+    Some(message) => greeter.push_message(MessageLevel::Error, message),
+  }
+}
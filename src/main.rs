@@ -1,29 +1,58 @@
 #[macro_use]
 extern crate smart_default;
+#[macro_use]
+extern crate tracing;
 
 #[macro_use]
 mod macros;
 
+mod completion;
+mod compose;
+mod config;
+mod dbus;
+mod debug;
+mod demo;
+mod editor;
+mod escape;
 mod event;
 mod greeter;
+mod health;
+mod hooks;
 mod info;
 mod ipc;
 mod keyboard;
+mod layout;
+mod plain;
 mod power;
+mod secret;
+mod tasks;
+#[cfg(any(test, feature = "testing"))]
+#[cfg_attr(not(test), allow(dead_code))]
+mod testing;
+mod trace;
 mod ui;
+mod widget;
 
 use std::{error::Error, io, process, sync::Arc};
 
 use crossterm::{
+  event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
   execute,
   terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen},
 };
 use greetd_ipc::Request;
 use ratatui::{backend::CrosstermBackend, Terminal};
-use tokio::sync::RwLock;
+use tokio::{
+  signal::unix::{signal, SignalKind},
+  sync::RwLock,
+};
 
 pub use self::greeter::*;
-use self::{event::Events, ipc::Ipc};
+use self::{
+  event::{Event, Events, Signal},
+  hooks::Hook,
+  ipc::Ipc,
+};
 
 #[tokio::main]
 async fn main() {
@@ -37,33 +66,98 @@ async fn main() {
 }
 
 async fn run() -> Result<(), Box<dyn Error>> {
-  let greeter = Greeter::new().await;
-  let mut stdout = io::stdout();
-
-  enable_raw_mode()?;
-  execute!(stdout, EnterAlternateScreen)?;
-
-  let backend = CrosstermBackend::new(stdout);
-  let mut terminal = Terminal::new(backend)?;
+  let mut greeter = Greeter::new().await;
 
-  terminal.clear()?;
+  if let Some(numlock) = greeter.numlock {
+    let _ = info::set_numlock(numlock);
+  }
 
-  let mut events = Events::new().await;
   let ipc = Ipc::new();
 
-  if greeter.remember && !greeter.username.is_empty() {
-    ipc.send(Request::CreateSession { username: greeter.username.clone() }).await;
+  if greeter.kiosk_user.is_none() && greeter.remember && !greeter.username.is_empty() && greeter.mode != Mode::Autologin && !greeter.start_failed {
+    if !hooks::queue(&mut greeter, Hook::PreAuth) {
+      ipc.send(Request::CreateSession { username: greeter.username.clone() }).await;
+    }
+
+    greeter.lookup_login_history();
   }
 
+  keyboard::start_kiosk(&mut greeter, &ipc).await;
+
   let greeter = Arc::new(RwLock::new(greeter));
+  let mut events = Events::new();
 
+  // Responses are handed to the event loop, which is the only one to change
+  // the state of the greeter.
   tokio::task::spawn({
     let greeter = greeter.clone();
     let mut ipc = ipc.clone();
+    let sender = events.sender();
+
+    async move {
+      loop {
+        let event = ipc.handle(greeter.clone()).await.ok().flatten();
+
+        if let Some(event) = event {
+          let _ = sender.send(event).await;
+        }
+      }
+    }
+  });
+
+  tokio::task::spawn(hooks::watch(greeter.clone(), events.sender()));
+
+  tokio::task::spawn({
+    let greeter = greeter.clone();
+    let notify = greeter.read().await.login_history_notify.clone();
+    let sender = events.sender();
+
+    async move {
+      loop {
+        notify.notified().await;
+
+        let lookup = {
+          let mut greeter = greeter.write().await;
+
+          greeter.login_history_lookup.take().map(|username| (username, greeter.last_login, greeter.failed_logins))
+        };
+
+        if let Some((username, last_login, failed_logins)) = lookup {
+          let (login, failures) = info::get_login_history(username.clone(), last_login, failed_logins).await;
+
+          if sender.send(Event::LoginHistory(username, login, failures)).await.is_err() {
+            return;
+          }
+        }
+      }
+    }
+  });
+
+  tokio::task::spawn({
+    let greeter = greeter.clone();
+    let notify = greeter.read().await.user_command_notify.clone();
+    let sender = events.sender();
 
     async move {
       loop {
-        let _ = ipc.handle(greeter.clone()).await;
+        notify.notified().await;
+
+        let username = greeter.write().await.user_command_lookup.take();
+
+        if let Some(username) = username {
+          let command = tokio::task::spawn_blocking({
+            let username = username.clone();
+
+            move || info::get_user_command(&username)
+          })
+          .await
+          .ok()
+          .flatten();
+
+          if sender.send(Event::UserCommand(username, command)).await.is_err() {
+            return;
+          }
+        }
       }
     }
   });
@@ -76,22 +170,115 @@ async fn run() -> Result<(), Box<dyn Error>> {
       loop {
         notify.notified().await;
 
-        let command = greeter.write().await.power_command.take();
+        let action = greeter.write().await.power_action.take();
+
+        if let Some(action) = action {
+          power::run(&greeter, action).await;
+        }
+      }
+    }
+  });
+
+  tokio::task::spawn({
+    let greeter = greeter.clone();
+    let notify = greeter.read().await.layout_command_notify.clone();
+
+    async move {
+      loop {
+        notify.notified().await;
+
+        let action = greeter.write().await.layout_action.take();
 
-        if let Some(command) = command {
-          power::run(&greeter, command).await;
+        if let Some((index, command)) = action {
+          layout::run(&greeter, index, command).await;
         }
       }
     }
   });
 
+  {
+    let mut greeter = greeter.write().await;
+    let probes = greeter.probes();
+    let widgets = greeter.widgets.clone();
+
+    greeter.tasks.update(&probes, &widgets, &events.sender());
+  }
+
+  // Completing commands needs the executables on the search path, which are
+  // only listed once.
+  if !greeter.read().await.demo && !greeter.read().await.plain {
+    tokio::task::spawn({
+      let sender = events.sender();
+      let dirs = greeter.read().await.command_path.clone();
+
+      async move {
+        let _ = sender.send(Event::Executables(completion::executables(dirs).await)).await;
+      }
+    });
+  }
+
+  tokio::task::spawn({
+    let sender = events.sender();
+
+    async move {
+      if let Ok(mut hangup) = signal(SignalKind::hangup()) {
+        while hangup.recv().await.is_some() {
+          let _ = sender.send(Event::Signal(Signal::Hangup)).await;
+        }
+      }
+    }
+  });
+
+  if greeter.read().await.plain {
+    tokio::task::spawn({
+      let greeter = greeter.clone();
+      let ipc = ipc.clone();
+
+      async move {
+        loop {
+          let _ = keyboard::handle(greeter.clone(), &mut events, ipc.clone()).await;
+        }
+      }
+    });
+
+    return plain::run(greeter, ipc).await;
+  }
+
+  let mut stdout = io::stdout();
+
+  enable_raw_mode()?;
+
+  // Serial terminals may not have an alternate screen, the screen being
+  // cleared instead.
+  if !greeter.read().await.serial {
+    execute!(stdout, EnterAlternateScreen)?;
+  }
+
+  if greeter.read().await.mouse {
+    execute!(stdout, EnableMouseCapture)?;
+  }
+
+  // Pasted text is then received at once rather than as typed keys, which
+  // would each be acted upon. Terminals without bracketed paste ignore this.
+  execute!(stdout, EnableBracketedPaste)?;
+
+  let backend = CrosstermBackend::new(stdout);
+  let mut terminal = Terminal::new(backend)?;
+
+  terminal.clear()?;
+
+  events.listen(greeter.read().await.tick_rate());
+
   loop {
     if let Some(status) = greeter.read().await.exit {
       return Err(status.into());
     }
 
     ui::draw(greeter.clone(), &mut terminal).await?;
-    keyboard::handle(greeter.clone(), &mut events, ipc.clone()).await?;
+
+    if keyboard::handle(greeter.clone(), &mut events, ipc.clone()).await? {
+      escape::run(&greeter, &mut terminal, &mut events, &ipc).await?;
+    }
   }
 }
 
@@ -101,29 +288,29 @@ pub async fn exit(greeter: &mut Greeter, status: AuthStatus) {
     AuthStatus::Cancel | AuthStatus::Failure => Ipc::cancel(greeter).await,
   }
 
-  clear_screen();
-  let _ = disable_raw_mode();
+  if greeter.mouse {
+    let _ = execute!(io::stdout(), DisableMouseCapture);
+  }
+
+  if !greeter.plain {
+    let _ = execute!(io::stdout(), DisableBracketedPaste);
+    clear_screen(greeter.serial);
+    let _ = disable_raw_mode();
+  }
+
+  debug!("exiting: {status:?}");
 
   greeter.exit = Some(status);
 }
 
-pub fn clear_screen() {
+pub fn clear_screen(serial: bool) {
   let backend = CrosstermBackend::new(io::stdout());
 
   if let Ok(mut terminal) = Terminal::new(backend) {
-    let _ = terminal.hide_cursor();
+    if !serial {
+      let _ = terminal.hide_cursor();
+    }
+
     let _ = terminal.clear();
   }
 }
-
-#[cfg(debug_assertions)]
-pub fn log(msg: &str) {
-  use std::io::Write;
-
-  let time = chrono::Utc::now();
-
-  let mut file = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/tuigreet.log").unwrap();
-  file.write_all(format!("{:?} - ", time).as_ref()).unwrap();
-  file.write_all(msg.as_ref()).unwrap();
-  file.write_all("\n".as_bytes()).unwrap();
-}
@@ -0,0 +1,316 @@
+use std::{
+  error::Error,
+  io::{self, BufRead, Write},
+  os::unix::io::AsRawFd,
+  sync::Arc,
+  thread,
+  time::{Duration, Instant},
+};
+
+use nix::sys::termios::{self, LocalFlags, SetArg};
+use tokio::{
+  sync::{mpsc, RwLock},
+  time,
+};
+
+use crate::{
+  info::write_last_session,
+  ipc::Ipc,
+  keyboard::{start_kiosk, submit_answer, tick, validate_username},
+  ui::{split_alignment, MESSAGES},
+  AuthStatus, Greeter, Mode,
+};
+
+// Time after which waiting for some processing is announced.
+const WAIT_ANNOUNCE_DELAY: Duration = Duration::from_secs(1);
+
+// In plain mode, the terminal is left in canonical mode and everything is
+// printed line after line, so that console screen readers and braille displays
+// can follow the prompts. Editing and echo are left to the line discipline.
+pub async fn run(greeter: Arc<RwLock<Greeter>>, ipc: Ipc) -> Result<(), Box<dyn Error>> {
+  // Unicode isolation marks around arguments would be printed as is by the
+  // console, and spelled out by some screen readers.
+  MESSAGES.set_use_isolating(false);
+
+  let mut lines = Lines::new();
+  let mut printed = 0;
+  let mut announced = None;
+
+  let tick_rate = {
+    let mut greeter = greeter.write().await;
+
+    print_header(&greeter);
+
+    // A remembered username is being sent to greetd already.
+    if greeter.mode == Mode::Username && !greeter.username.is_empty() {
+      greeter.mode = Mode::Processing;
+    }
+
+    Duration::from_millis(greeter.tick_rate())
+  };
+
+  loop {
+    let (mode, announcement, key) = {
+      let mut greeter = greeter.write().await;
+
+      if let Some(status) = greeter.exit {
+        return Err(status.into());
+      }
+
+      // Menus cannot be displayed, and are never opened in plain mode.
+      if greeter.mode.is_overlay() || greeter.mode == Mode::PowerConfirm {
+        greeter.mode = Mode::Username;
+      }
+
+      print_messages(&greeter, &mut printed);
+
+      (greeter.mode, announcement(&greeter), (greeter.mode, greeter.device_prompt.clone()))
+    };
+
+    match mode {
+      Mode::Username => {
+        announced = None;
+
+        if start_kiosk(&mut *greeter.write().await, &ipc).await {
+          continue;
+        }
+
+        let username = match lines.prompt(&fl!("username"), false).await {
+          Some(username) => username,
+          None => break,
+        };
+
+        if username.is_empty() {
+          if choose_session(&greeter, &mut lines).await.is_none() {
+            break;
+          }
+
+          continue;
+        }
+
+        let mut greeter = greeter.write().await;
+
+        greeter.username = username;
+        greeter.username_mask = None;
+        greeter.mode = Mode::Processing;
+
+        validate_username(&mut greeter, &ipc).await;
+        printed = 0;
+      }
+
+      Mode::Password => {
+        announced = None;
+
+        let (prompt, secret) = {
+          let greeter = greeter.read().await;
+
+          match &greeter.prompt {
+            Some(prompt) => (prompt.message.clone(), prompt.secret),
+            None => (String::new(), false),
+          }
+        };
+
+        let answer = match lines.prompt(&prompt, secret).await {
+          Some(answer) => answer,
+          None => break,
+        };
+
+        let mut greeter = greeter.write().await;
+
+        greeter.answer = answer.into();
+
+        submit_answer(&mut greeter, &ipc).await;
+        printed = 0;
+      }
+
+      _ => {
+        if let Some(text) = announcement.filter(|_| announced.as_ref() != Some(&key)) {
+          println!("{text}");
+
+          announced = Some(key);
+        }
+
+        tokio::select! {
+          _ = time::sleep(tick_rate) => tick(&mut *greeter.write().await, &ipc).await,
+
+          line = lines.next() => match line {
+            None => break,
+
+            // Entering a line during the automatic login countdown cancels it.
+            Some(_) if mode == Mode::Autologin => {
+              let mut greeter = greeter.write().await;
+
+              greeter.autologin_deadline = None;
+              greeter.username = String::new();
+              greeter.mode = Mode::Username;
+            }
+
+            Some(_) => {}
+          },
+        }
+      }
+    }
+  }
+
+  crate::exit(&mut *greeter.write().await, AuthStatus::Cancel).await;
+
+  Err(AuthStatus::Cancel.into())
+}
+
+fn print_header(greeter: &Greeter) {
+  println!("{}", fl!("title_authenticate", hostname = greeter.hostname()));
+
+  if let Some((_, identity)) = &greeter.identity {
+    println!("{identity}");
+  }
+
+  if let Some(greeting) = &greeter.greeting {
+    for line in greeting.trim_end().lines() {
+      println!("{}", split_alignment(line).1);
+    }
+  }
+
+  if greeter.sessions.len() > 1 {
+    println!("{}", fl!("plain_sessions_hint"));
+  }
+
+  println!();
+}
+
+// Messages are cleared when a new answer is submitted, at which point all of
+// them are printed again.
+fn print_messages(greeter: &Greeter, printed: &mut usize) {
+  if greeter.messages.len() < *printed {
+    *printed = 0;
+  }
+
+  for message in &greeter.messages[*printed..] {
+    println!("{}", message.text);
+  }
+
+  *printed = greeter.messages.len();
+}
+
+// Returns the line describing what the greeter is waiting for, which is only
+// printed once for a given mode and device prompt, countdowns included.
+fn announcement(greeter: &Greeter) -> Option<String> {
+  let seconds = |deadline: Option<Instant>| {
+    deadline
+      .map(|deadline| deadline.saturating_duration_since(Instant::now()).as_secs_f64().ceil() as u64)
+      .unwrap_or_default()
+  };
+
+  match greeter.mode {
+    Mode::Autologin => Some(format!(
+      "{} {}",
+      fl!("autologin", username = greeter.username.clone(), seconds = seconds(greeter.autologin_deadline)),
+      fl!("plain_autologin_cancel")
+    )),
+
+    Mode::Lockout => Some(fl!("lockout", attempts = greeter.lockout_attempts.unwrap_or(1), seconds = seconds(greeter.lockout_deadline))),
+
+    Mode::Processing => match &greeter.device_prompt {
+      Some(prompt) => Some(prompt.clone()),
+      None if greeter.cancelling => Some(fl!("canceling")),
+      None if greeter.processing_since.map(|since| since.elapsed() >= WAIT_ANNOUNCE_DELAY).unwrap_or(false) => Some(fl!("wait")),
+      None => None,
+    },
+
+    _ => None,
+  }
+}
+
+// Lists the available sessions by number, an empty or invalid answer keeping
+// the current one.
+async fn choose_session(greeter: &Arc<RwLock<Greeter>>, lines: &mut Lines) -> Option<()> {
+  {
+    let greeter = greeter.read().await;
+
+    println!("{}", fl!("title_session"));
+
+    for (index, session) in greeter.sessions.iter().enumerate() {
+      let marker = if index == greeter.selected_session { '*' } else { ' ' };
+
+      println!("{marker} {}. {}", index + 1, session.name);
+    }
+  }
+
+  let answer = lines.prompt(&fl!("plain_session"), false).await?;
+
+  let mut greeter = greeter.write().await;
+  let index = answer
+    .trim()
+    .parse::<usize>()
+    .ok()
+    .and_then(|number| number.checked_sub(1))
+    .filter(|index| *index < greeter.sessions.len());
+
+  if let Some(index) = index {
+    let command = greeter.sessions[index].command.clone();
+
+    if greeter.remember_session {
+      write_last_session(&command);
+    }
+
+    greeter.selected_session = index;
+    greeter.command = Some(command);
+  }
+
+  Some(())
+}
+
+// Lines are read from a separate thread, reading from the standard input
+// being blocking.
+struct Lines {
+  rx: mpsc::Receiver<String>,
+}
+
+impl Lines {
+  fn new() -> Lines {
+    let (tx, rx) = mpsc::channel(10);
+
+    thread::spawn(move || {
+      for line in io::stdin().lock().lines().map_while(Result::ok) {
+        if tx.blocking_send(line).is_err() {
+          break;
+        }
+      }
+    });
+
+    Lines { rx }
+  }
+
+  async fn next(&mut self) -> Option<String> {
+    self.rx.recv().await
+  }
+
+  // Echo is turned off while a secret is being typed.
+  async fn prompt(&mut self, prompt: &str, secret: bool) -> Option<String> {
+    print!("{} ", prompt.trim_end());
+
+    let _ = io::stdout().flush();
+
+    if secret {
+      set_echo(false);
+    }
+
+    let line = self.next().await;
+
+    if secret {
+      set_echo(true);
+      println!();
+    }
+
+    line
+  }
+}
+
+fn set_echo(enabled: bool) {
+  let fd = io::stdin().as_raw_fd();
+
+  if let Ok(mut attributes) = termios::tcgetattr(fd) {
+    attributes.local_flags.set(LocalFlags::ECHO, enabled);
+
+    let _ = termios::tcsetattr(fd, SetArg::TCSANOW, &attributes);
+  }
+}
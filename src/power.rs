@@ -1,39 +1,138 @@
-use std::{process::Stdio, sync::Arc};
+use std::{fs, process::Stdio, sync::Arc};
 
+use smart_default::SmartDefault;
 use tokio::{process::Command, sync::RwLock};
 
-use crate::{Greeter, Mode};
+use crate::{dbus::MethodCall, Greeter, MessageLevel, Mode};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PowerOption {
   Shutdown,
   Reboot,
+  Suspend,
+  Hibernate,
+  HybridSleep,
+  Custom(usize),
+}
+
+impl PowerOption {
+  // Sleep states are named after their systemd unit.
+  pub fn parse_sleep(value: &str) -> Option<PowerOption> {
+    match value.trim() {
+      "suspend" => Some(PowerOption::Suspend),
+      "hibernate" => Some(PowerOption::Hibernate),
+      "hybrid-sleep" => Some(PowerOption::HybridSleep),
+      _ => None,
+    }
+  }
+
+  // Custom actions are labeled as the user configured them.
+  pub fn label(&self) -> Option<String> {
+    match self {
+      PowerOption::Shutdown => Some(fl!("shutdown")),
+      PowerOption::Reboot => Some(fl!("reboot")),
+      PowerOption::Suspend => Some(fl!("suspend")),
+      PowerOption::Hibernate => Some(fl!("hibernate")),
+      PowerOption::HybridSleep => Some(fl!("hybrid_sleep")),
+      PowerOption::Custom(_) => None,
+    }
+  }
+
+  // Sleep states are only offered when the kernel supports them, hybrid sleep
+  // requiring both suspending to memory and to disk.
+  pub fn is_supported(&self) -> bool {
+    let states = fs::read_to_string("/sys/power/state").unwrap_or_default();
+    let states = states.split_whitespace().collect::<Vec<_>>();
+
+    match self {
+      PowerOption::Suspend => states.contains(&"mem"),
+      PowerOption::Hibernate => states.contains(&"disk"),
+      PowerOption::HybridSleep => states.contains(&"mem") && states.contains(&"disk"),
+      _ => true,
+    }
+  }
+}
+
+// How shutting down and rebooting are requested when no command was provided
+// for them.
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum PowerBackend {
+  #[default]
+  Shell,
+  Logind,
+}
+
+pub enum PowerAction {
+  Command(Command),
+  Logind(MethodCall),
 }
 
 pub fn power(greeter: &mut Greeter, option: PowerOption) {
-  let mut command = match greeter.power_commands.get(&option) {
-    None => {
-      let mut command = Command::new("shutdown");
+  if greeter.demo {
+    return;
+  }
 
-      match option {
-        PowerOption::Shutdown => command.arg("-h"),
-        PowerOption::Reboot => command.arg("-r"),
+  let mut command = match greeter.power_commands.get(&option) {
+    None if greeter.power_backend == PowerBackend::Logind => {
+      let method = match option {
+        PowerOption::Shutdown => "PowerOff",
+        PowerOption::Reboot => "Reboot",
+        PowerOption::Suspend => "Suspend",
+        PowerOption::Hibernate => "Hibernate",
+        PowerOption::HybridSleep => "HybridSleep",
+        PowerOption::Custom(_) => return,
       };
 
-      command.arg("now");
-      command
+      // Authorization is not asked for interactively, polkit only allowing
+      // those actions for the local seat.
+      let call = MethodCall::new("org.freedesktop.login1", "/org/freedesktop/login1", "org.freedesktop.login1.Manager", method).boolean(false);
+
+      greeter.power_action = Some(PowerAction::Logind(call));
+      greeter.power_command_notify.notify_one();
+
+      return;
     }
 
+    None => match option {
+      PowerOption::Shutdown | PowerOption::Reboot => {
+        let mut command = Command::new("shutdown");
+
+        match option {
+          PowerOption::Reboot => command.arg("-r"),
+          _ => command.arg("-h"),
+        };
+
+        command.arg("now");
+        command
+      }
+
+      PowerOption::Suspend | PowerOption::Hibernate | PowerOption::HybridSleep => {
+        let mut command = Command::new("systemctl");
+
+        match option {
+          PowerOption::Suspend => command.arg("suspend"),
+          PowerOption::Hibernate => command.arg("hibernate"),
+          _ => command.arg("hybrid-sleep"),
+        };
+
+        command
+      }
+
+      PowerOption::Custom(_) => return,
+    },
+
+    // Arguments may be separated by any amount of whitespace, as they often are
+    // in configuration files.
     Some(args) => {
       let command = match greeter.power_setsid {
         true => {
           let mut command = Command::new("setsid");
-          command.args(args.split(' '));
+          command.args(args.split_whitespace());
           command
         }
 
         false => {
-          let mut args = args.split(' ');
+          let mut args = args.split_whitespace();
 
           let mut command = Command::new(args.next().unwrap_or_default());
           command.args(args);
@@ -49,25 +148,37 @@ pub fn power(greeter: &mut Greeter, option: PowerOption) {
   command.stdout(Stdio::null());
   command.stderr(Stdio::null());
 
-  greeter.power_command = Some(command);
+  greeter.power_action = Some(PowerAction::Command(command));
   greeter.power_command_notify.notify_one();
 }
 
-pub async fn run(greeter: &Arc<RwLock<Greeter>>, mut command: Command) {
-  greeter.write().await.mode = Mode::Processing;
+pub async fn run(greeter: &Arc<RwLock<Greeter>>, action: PowerAction) {
+  {
+    let mut greeter = greeter.write().await;
 
-  let message = match command.output().await {
-    Ok(result) => match (result.status, result.stderr) {
-      (status, _) if status.success() => None,
-      (status, output) => {
-        let status = format!("{} {status}", fl!("command_exited"));
-        let output = String::from_utf8(output).unwrap_or_default();
+    greeter.mode = Mode::Processing;
+    greeter.dirty = true;
+  }
 
-        Some(format!("{status}\n{output}"))
-      }
+  let message = match action {
+    PowerAction::Command(mut command) => match command.output().await {
+      Ok(result) => match (result.status, result.stderr) {
+        (status, _) if status.success() => None,
+        (status, output) => {
+          let status = format!("{} {status}", fl!("command_exited"));
+          let output = String::from_utf8(output).unwrap_or_default();
+
+          Some(format!("{status}\n{output}"))
+        }
+      },
+
+      Err(err) => Some(format!("{}: {err}", fl!("command_failed"))),
     },
 
-    Err(err) => Some(format!("{}: {err}", fl!("command_failed"))),
+    PowerAction::Logind(call) => match call.call().await {
+      Ok(()) => None,
+      Err(err) => Some(format!("{}: {err}", fl!("command_failed"))),
+    },
   };
 
   let mode = greeter.read().await.previous_mode;
@@ -75,5 +186,10 @@ pub async fn run(greeter: &Arc<RwLock<Greeter>>, mut command: Command) {
   let mut greeter = greeter.write().await;
 
   greeter.mode = mode;
-  greeter.message = message;
+  greeter.dirty = true;
+  greeter.clear_messages();
+
+  if let Some(message) = message {
+    greeter.push_message(MessageLevel::Error, message);
+  }
 }
@@ -0,0 +1,43 @@
+use std::ops::{Deref, DerefMut};
+
+use zeroize::Zeroize;
+
+// Answers to authentication prompts, which are wiped from memory as soon as
+// they are replaced or dropped, rather than whenever the allocator reuses
+// their memory.
+#[derive(Default)]
+pub struct Secret(String);
+
+impl From<String> for Secret {
+  fn from(value: String) -> Secret {
+    Secret(value)
+  }
+}
+
+impl Deref for Secret {
+  type Target = String;
+
+  fn deref(&self) -> &String {
+    &self.0
+  }
+}
+
+// Edits that reallocate the underlying string are expected to wipe the
+// previous value themselves, as the line editor does.
+impl DerefMut for Secret {
+  fn deref_mut(&mut self) -> &mut String {
+    &mut self.0
+  }
+}
+
+impl Zeroize for Secret {
+  fn zeroize(&mut self) {
+    self.0.zeroize();
+  }
+}
+
+impl Drop for Secret {
+  fn drop(&mut self) {
+    self.0.zeroize();
+  }
+}
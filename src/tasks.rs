@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use tokio::{
+  sync::mpsc::Sender,
+  task::{self, JoinHandle},
+  time,
+};
+
+use crate::{
+  event::Event,
+  health,
+  info::{battery_status, network_status},
+  widget::{self, Widget},
+};
+
+const BATTERY_INTERVAL: Duration = Duration::from_secs(30);
+const NETWORK_INTERVAL: Duration = Duration::from_secs(5);
+
+// Something displayed by the greeter which has to be looked up, and may take
+// a while to, such as reading sysfs or running a command.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Probe {
+  Battery,
+  Network,
+  Health,
+  Widget(usize),
+}
+
+// The background tasks probing the system, each at its own interval, their
+// results being sent to the event loop for drawing to only display the last
+// ones. The probes wanted are known once options are parsed, and again when
+// the configuration is reloaded.
+#[derive(Default)]
+pub struct Tasks {
+  running: Vec<(Probe, JoinHandle<()>)>,
+}
+
+impl Tasks {
+  // Starts the probes which are not running yet, and stops those which are
+  // not wanted anymore.
+  pub fn update(&mut self, probes: &[Probe], widgets: &[Widget], sender: &Sender<Event>) {
+    self.running.retain(|(probe, handle)| {
+      let wanted = probes.contains(probe);
+
+      if !wanted {
+        handle.abort();
+      }
+
+      wanted
+    });
+
+    for &probe in probes {
+      if self.running.iter().all(|(running, _)| *running != probe) {
+        if let Some(handle) = spawn(probe, widgets, sender.clone()) {
+          self.running.push((probe, handle));
+        }
+      }
+    }
+  }
+}
+
+impl Drop for Tasks {
+  fn drop(&mut self) {
+    for (_, handle) in &self.running {
+      handle.abort();
+    }
+  }
+}
+
+// Health checks only run once, other probes being run again after their
+// interval, until the event loop is gone.
+fn spawn(probe: Probe, widgets: &[Widget], sender: Sender<Event>) -> Option<JoinHandle<()>> {
+  let widget = match probe {
+    Probe::Widget(index) => Some(widgets.get(index)?.clone()),
+    _ => None,
+  };
+
+  let handle = task::spawn(async move {
+    loop {
+      let (event, interval) = match (probe, &widget) {
+        (Probe::Battery, _) => (Event::Battery(task::spawn_blocking(battery_status).await.ok().flatten()), Some(BATTERY_INTERVAL)),
+        (Probe::Network, _) => match task::spawn_blocking(network_status).await {
+          Ok(network) => (Event::Network(network), Some(NETWORK_INTERVAL)),
+          Err(_) => return,
+        },
+        (Probe::Health, _) => (Event::Notifications(health::check().await), None),
+        (Probe::Widget(index), Some(widget)) => (Event::Widget(index, widget::run(&widget.command).await), Some(widget.interval)),
+        (Probe::Widget(_), None) => return,
+      };
+
+      if sender.send(event).await.is_err() {
+        return;
+      }
+
+      match interval {
+        Some(interval) => time::sleep(interval).await,
+        None => return,
+      }
+    }
+  });
+
+  Some(handle)
+}
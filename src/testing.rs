@@ -0,0 +1,357 @@
+use std::{env, fs, path::PathBuf, sync::Arc, time::Duration};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{backend::TestBackend, Terminal};
+use tokio::{
+  sync::{mpsc::Sender, RwLock},
+  time,
+};
+
+use crate::{
+  event::{Event, Events},
+  ipc::Ipc,
+  keyboard, ui, Greeter, Mode,
+};
+
+const DEFAULT_WIDTH: u16 = 80;
+const DEFAULT_HEIGHT: u16 = 24;
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(5);
+const PUMP_TIMEOUT: Duration = Duration::from_millis(20);
+const UPDATE_SNAPSHOTS: &str = "TUIGREET_UPDATE_SNAPSHOTS";
+
+// Runs the greeter in demo mode against a terminal kept in memory, input
+// being scripted and the screen read back as text. Everything which depends on
+// the machine, such as the configuration file or the status bar segments
+// reading the hardware, is left out so that screens are the same everywhere.
+pub struct Harness {
+  pub greeter: Arc<RwLock<Greeter>>,
+  terminal: Terminal<TestBackend>,
+  input: Sender<Event>,
+  events: Events,
+  ipc: Ipc,
+}
+
+impl Harness {
+  pub async fn new(args: &[&str]) -> Harness {
+    Harness::with_size(args, DEFAULT_WIDTH, DEFAULT_HEIGHT).await
+  }
+
+  pub async fn with_size(args: &[&str], width: u16, height: u16) -> Harness {
+    // Messages are translated once for the whole process, in the language
+    // found in the environment.
+    for variable in ["LANGUAGE", "LC_ALL", "LC_MESSAGES"] {
+      env::remove_var(variable);
+    }
+
+    env::set_var("LANG", "en_US.UTF-8");
+
+    let defaults = ["tuigreet", "--demo", "--config", "/dev/null", "--status-right", "layout"];
+    let args = defaults.iter().chain(args).map(|arg| arg.to_string()).collect();
+
+    let greeter = Arc::new(RwLock::new(Greeter::from_args(args).await));
+    let terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    let events = Events::new();
+    let input = events.sender();
+    let ipc = Ipc::new();
+
+    tokio::task::spawn({
+      let greeter = greeter.clone();
+      let mut ipc = ipc.clone();
+      let sender = events.sender();
+
+      async move {
+        loop {
+          let event = ipc.handle(greeter.clone()).await.ok().flatten();
+
+          if let Some(event) = event {
+            let _ = sender.send(event).await;
+          }
+        }
+      }
+    });
+
+    Harness {
+      greeter,
+      terminal,
+      input,
+      events,
+      ipc,
+    }
+  }
+
+  pub async fn key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+    self.send(Event::Key(KeyEvent::new(code, modifiers))).await;
+  }
+
+  pub async fn send(&mut self, event: Event) {
+    let _ = self.input.send(event).await;
+    self.pump().await;
+  }
+
+  // Handles the events sent so far, along with the responses they bring,
+  // until none comes for a while.
+  async fn pump(&mut self) {
+    while time::timeout(PUMP_TIMEOUT, keyboard::handle(self.greeter.clone(), &mut self.events, self.ipc.clone())).await.is_ok() {}
+  }
+
+  // Types the given script, where keys other than characters are written
+  // between angle brackets, such as <Enter>, <F3> or <C-u>.
+  pub async fn input(&mut self, script: &str) {
+    let mut chars = script.chars();
+
+    while let Some(c) = chars.next() {
+      if c != '<' {
+        self.key(KeyCode::Char(c), KeyModifiers::NONE).await;
+        continue;
+      }
+
+      let name = chars.by_ref().take_while(|c| *c != '>').collect::<String>();
+      let (code, modifiers) = parse_key(&name).unwrap_or_else(|| panic!("unknown key <{}>", name));
+
+      self.key(code, modifiers).await;
+    }
+  }
+
+  // Waits for the greeter to get to the given mode, which may take a
+  // response from the demo backend.
+  pub async fn wait_for(&mut self, mode: Mode) {
+    let wait = async {
+      while self.greeter.read().await.mode != mode {
+        self.pump().await;
+      }
+    };
+
+    if time::timeout(SETTLE_TIMEOUT, wait).await.is_err() {
+      panic!("timed out waiting for {mode:?}, still in {:?}", self.greeter.read().await.mode);
+    }
+  }
+
+  // Draws the interface, returning the screen as lines of text.
+  pub async fn render(&mut self) -> String {
+    ui::draw(self.greeter.clone(), &mut self.terminal).await.unwrap();
+
+    let buffer = self.terminal.backend().buffer();
+    let width = buffer.area.width as usize;
+
+    buffer
+      .content
+      .chunks(width)
+      .map(|row| row.iter().map(|cell| cell.symbol.as_str()).collect::<String>().trim_end().to_string())
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  // Compares the screen to the one recorded under the given name, which is
+  // recorded again when TUIGREET_UPDATE_SNAPSHOTS is set.
+  pub async fn assert_snapshot(&mut self, name: &str) {
+    let screen = self.render().await;
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/snapshots").join(format!("{name}.txt"));
+
+    if env::var_os(UPDATE_SNAPSHOTS).is_some() {
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(&path, format!("{screen}\n")).unwrap();
+
+      return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!("no snapshot at {}, run with {UPDATE_SNAPSHOTS}=1 to record it", path.display()));
+
+    // Blank rows at the bottom of the screen are not kept in snapshots.
+    assert_eq!(expected.trim_end_matches('\n'), screen.trim_end_matches('\n'), "screen differs from snapshot {name}");
+  }
+}
+
+fn parse_key(name: &str) -> Option<(KeyCode, KeyModifiers)> {
+  if let Some(key) = name.strip_prefix("C-") {
+    return Some((KeyCode::Char(key.chars().next()?), KeyModifiers::CONTROL));
+  }
+
+  let code = match name {
+    "Enter" => KeyCode::Enter,
+    "Esc" => KeyCode::Esc,
+    "Tab" => KeyCode::Tab,
+    "Backspace" => KeyCode::Backspace,
+    "Delete" => KeyCode::Delete,
+    "Up" => KeyCode::Up,
+    "Down" => KeyCode::Down,
+    "Left" => KeyCode::Left,
+    "Right" => KeyCode::Right,
+    "Home" => KeyCode::Home,
+    "End" => KeyCode::End,
+    "PageUp" => KeyCode::PageUp,
+    "PageDown" => KeyCode::PageDown,
+    _ => KeyCode::F(name.strip_prefix('F')?.parse().ok()?),
+  };
+
+  Some((code, KeyModifiers::NONE))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Harness;
+  use crate::{event::Event, Mode};
+
+  #[tokio::test]
+  async fn username() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("ali").await;
+    harness.assert_snapshot("username").await;
+  }
+
+  #[tokio::test]
+  async fn username_completion() {
+    let mut harness = Harness::new(&["--user-completion"]).await;
+
+    harness.input("<Tab><Tab>").await;
+    harness.assert_snapshot("username_completion").await;
+  }
+
+  #[tokio::test]
+  async fn password() {
+    let mut harness = Harness::new(&["--asterisks"]).await;
+
+    harness.input("alice<Enter>").await;
+    harness.wait_for(Mode::Password).await;
+    harness.input("sec").await;
+    harness.assert_snapshot("password").await;
+  }
+
+  #[tokio::test]
+  async fn password_hint() {
+    let mut harness = Harness::new(&["--password-min-length", "6", "--password-max-length", "8"]).await;
+
+    harness.input("alice<Enter>").await;
+    harness.wait_for(Mode::Password).await;
+    harness.input("1234").await;
+    harness.assert_snapshot("password_hint").await;
+  }
+
+  #[tokio::test]
+  async fn users() {
+    let mut harness = Harness::new(&["--user-menu"]).await;
+
+    harness.input("<Enter><Down>").await;
+    harness.wait_for(Mode::Users).await;
+    harness.assert_snapshot("users").await;
+  }
+
+  #[tokio::test]
+  async fn command() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F2>").await;
+    harness.wait_for(Mode::Command).await;
+    harness.assert_snapshot("command").await;
+  }
+
+  #[tokio::test]
+  async fn command_completion() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F2><C-u>start<Tab>").await;
+    harness.wait_for(Mode::Command).await;
+    harness.assert_snapshot("command_completion").await;
+  }
+
+  #[tokio::test]
+  async fn paste() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F2><C-u>").await;
+    harness.send(Event::Paste("sway\n--unsupported-gpu\x07\n".to_string())).await;
+    harness.assert_snapshot("paste").await;
+  }
+
+  #[tokio::test]
+  async fn environment() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F5>LANG=C").await;
+    harness.wait_for(Mode::Environment).await;
+    harness.assert_snapshot("environment").await;
+  }
+
+  #[tokio::test]
+  async fn sessions() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F3><Down>").await;
+    harness.wait_for(Mode::Sessions).await;
+    harness.assert_snapshot("sessions").await;
+  }
+
+  #[tokio::test]
+  async fn power() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F12>").await;
+    harness.wait_for(Mode::Power).await;
+    harness.assert_snapshot("power").await;
+  }
+
+  #[tokio::test]
+  async fn power_confirm() {
+    let mut harness = Harness::new(&["--power-confirm", "10"]).await;
+
+    harness.input("<F12><Enter>").await;
+    harness.wait_for(Mode::PowerConfirm).await;
+    harness.assert_snapshot("power_confirm").await;
+  }
+
+  #[tokio::test]
+  async fn layouts() {
+    let mut harness = Harness::new(&["--layouts", "us,fr,de"]).await;
+
+    harness.input("<F4>").await;
+    harness.wait_for(Mode::Layouts).await;
+    harness.assert_snapshot("layouts").await;
+  }
+
+  #[tokio::test]
+  async fn locale() {
+    let mut harness = Harness::new(&[]).await;
+
+    harness.input("<F6>").await;
+    harness.wait_for(Mode::Locale).await;
+    harness.assert_snapshot("locale").await;
+  }
+
+  #[tokio::test]
+  async fn processing() {
+    let mut harness = Harness::new(&["--spinner", "none"]).await;
+
+    harness.input("alice<Enter>").await;
+    harness.wait_for(Mode::Password).await;
+    harness.input("demo<Enter>").await;
+    harness.wait_for(Mode::Processing).await;
+    harness.assert_snapshot("processing").await;
+  }
+
+  #[tokio::test]
+  async fn autologin() {
+    let mut harness = Harness::new(&["--autologin", "alice", "--autologin-delay", "30"]).await;
+
+    harness.wait_for(Mode::Autologin).await;
+    harness.assert_snapshot("autologin").await;
+  }
+
+  #[tokio::test]
+  async fn idle_big_clock() {
+    let mut harness = Harness::new(&["--idle-timeout", "60", "--idle-big-clock"]).await;
+
+    harness.greeter.write().await.idle = true;
+    harness.assert_snapshot("idle_big_clock").await;
+  }
+
+  #[tokio::test]
+  async fn lockout() {
+    let mut harness = Harness::new(&["--lockout-attempts", "1", "--lockout-duration", "600"]).await;
+
+    harness.input("alice<Enter>").await;
+    harness.wait_for(Mode::Password).await;
+    harness.input("wrong<Enter>").await;
+    harness.wait_for(Mode::Lockout).await;
+    harness.assert_snapshot("lockout").await;
+  }
+}
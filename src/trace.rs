@@ -0,0 +1,142 @@
+use std::{
+  collections::VecDeque,
+  error::Error,
+  fs::{self, File, OpenOptions},
+  io::{self, Read, Write},
+  os::unix::fs::OpenOptionsExt,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use greetd_ipc::{ErrorType, Request, Response};
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+const SALT_LENGTH: usize = 16;
+
+// The exchange with greetd is recorded as one JSON object per line, either a
+// request or a response, with the time it was sent or received, for a bug to
+// be replayed as it happened.
+struct Capture {
+  file: File,
+  start: Instant,
+  salt: [u8; SALT_LENGTH],
+}
+
+lazy_static! {
+  static ref CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+  static ref REPLAY: Mutex<Option<VecDeque<(String, Response, Duration)>>> = Mutex::new(None);
+}
+
+// Answers are hashed with a salt which is not written out, so that they can
+// only be told apart from each other, such as when the same password is typed
+// again, and not guessed from the capture.
+pub fn init_capture(path: &str) -> io::Result<()> {
+  let mut salt = [0; SALT_LENGTH];
+
+  File::open("/dev/urandom")?.read_exact(&mut salt)?;
+
+  let file = OpenOptions::new().create(true).write(true).truncate(true).mode(0o600).open(path)?;
+
+  *CAPTURE.lock().unwrap() = Some(Capture { file, start: Instant::now(), salt });
+
+  Ok(())
+}
+
+pub fn request(request: &Request) {
+  record("request", |capture| {
+    // Answers are hashed straight from the request, never being copied.
+    match request {
+      Request::PostAuthMessageResponse { response: Some(response) } => {
+        let mut hasher = Sha256::new();
+
+        hasher.update(capture.salt);
+        hasher.update(response);
+
+        Some(json!({
+          "type": "post_auth_message_response",
+          "response": format!("sha256:{:x}", hasher.finalize()),
+        }))
+      }
+
+      request => serde_json::to_value(request).ok(),
+    }
+  });
+}
+
+pub fn response(response: &Response) {
+  record("response", |_| serde_json::to_value(response).ok());
+}
+
+fn record<F>(kind: &str, value: F)
+where
+  F: FnOnce(&Capture) -> Option<Value>,
+{
+  if let Ok(mut capture) = CAPTURE.lock() {
+    if let Some(capture) = capture.as_mut() {
+      if let Some(value) = value(capture) {
+        let entry = json!({
+          "time": chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+          "elapsed_ms": capture.start.elapsed().as_millis() as u64,
+          kind: value,
+        });
+
+        let _ = writeln!(capture.file, "{entry}");
+      }
+    }
+  }
+}
+
+// Loads a capture to be replayed, each request being paired with the next
+// response, which is given back after the delay it took to come.
+pub fn init_replay(path: &str) -> Result<(), Box<dyn Error>> {
+  let mut requests = VecDeque::new();
+  let mut replay = VecDeque::new();
+
+  for (number, line) in fs::read_to_string(path)?.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+    let entry = serde_json::from_str::<Value>(line).map_err(|err| format!("line {}: {err}", number + 1))?;
+    let elapsed = Duration::from_millis(entry["elapsed_ms"].as_u64().unwrap_or_default());
+
+    if let Some(request) = entry.get("request") {
+      requests.push_back((request["type"].as_str().unwrap_or_default().to_string(), elapsed));
+    } else if let Some(response) = entry.get("response") {
+      let response = serde_json::from_value::<Response>(response.clone()).map_err(|err| format!("line {}: {err}", number + 1))?;
+      let (request, sent) = requests.pop_front().ok_or_else(|| format!("line {}: response without a request", number + 1))?;
+
+      replay.push_back((request, response, elapsed.saturating_sub(sent)));
+    }
+  }
+
+  *REPLAY.lock().unwrap() = Some(replay);
+
+  Ok(())
+}
+
+// Returns the next recorded response when replaying, whatever the request,
+// differences being written to the debug log. Once all were replayed, greetd
+// is made to fail.
+pub async fn replay(request: &Request) -> Option<Response> {
+  let next = REPLAY.lock().ok()?.as_mut()?.pop_front();
+
+  let (recorded, response, delay) = match next {
+    Some(next) => next,
+
+    None => {
+      return Some(Response::Error {
+        error_type: ErrorType::Error,
+        description: "nothing left to replay".to_string(),
+      })
+    }
+  };
+
+  let kind = serde_json::to_value(request).ok().and_then(|request| request["type"].as_str().map(str::to_string)).unwrap_or_default();
+
+  if kind != recorded {
+    debug!("replay: {kind} answered as {recorded}");
+  }
+
+  tokio::time::sleep(delay).await;
+
+  Some(response)
+}
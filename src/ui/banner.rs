@@ -0,0 +1,23 @@
+use ratatui::{backend::Backend, layout::Rect, widgets::Paragraph};
+
+use crate::{
+  ui::{Frame, Themed},
+  Greeter,
+};
+
+pub fn get_banner_height(greeter: &Greeter) -> u16 {
+  match &greeter.banner {
+    Some(banner) => banner.lines().count() as u16,
+    None => 0,
+  }
+}
+
+pub fn draw<B: Backend>(greeter: &Greeter, f: &mut Frame<B>, area: Rect) {
+  if let Some(banner) = &greeter.banner {
+    let banner = Paragraph::new(banner.as_str())
+      .alignment(greeter.banner_alignment)
+      .style(greeter.theme.of(&[Themed::Banner, Themed::Greet, Themed::Text]));
+
+    f.render_widget(banner, area);
+  }
+}
@@ -0,0 +1,151 @@
+use unicode_width::UnicodeWidthChar;
+
+// Languages written from right to left, as their primary language subtag.
+const RTL_LANGUAGES: [&str; 9] = ["ar", "ckb", "dv", "fa", "he", "ps", "sd", "ur", "yi"];
+
+pub fn is_rtl_language(language: &str) -> bool {
+  RTL_LANGUAGES.contains(&language)
+}
+
+// A character along with the combining marks following it, which are kept
+// together when a run of text is reversed.
+struct Cluster {
+  start: usize,
+  text: String,
+  width: i32,
+  rtl: bool,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Class {
+  Ltr,
+  Rtl,
+  Neutral,
+}
+
+fn class(c: char) -> Class {
+  match c as u32 {
+    0x0590..=0x08ff | 0xfb1d..=0xfdff | 0xfe70..=0xfeff | 0x10800..=0x10fff | 0x1e800..=0x1efff => Class::Rtl,
+    _ if c.is_alphanumeric() => Class::Ltr,
+    _ => Class::Neutral,
+  }
+}
+
+fn mirror(c: char) -> char {
+  match c {
+    '(' => ')',
+    ')' => '(',
+    '[' => ']',
+    ']' => '[',
+    '{' => '}',
+    '}' => '{',
+    '<' => '>',
+    '>' => '<',
+    c => c,
+  }
+}
+
+// Terminals such as the Linux console display characters in the order they
+// are written, so text is laid out in its visual order before being drawn. This
+// is a simplification of the Unicode bidirectional algorithm, without explicit
+// embeddings, where neutral characters take the direction of the text around
+// them when both sides agree, and the direction of the paragraph otherwise.
+fn layout(text: &str, rtl: bool) -> Vec<Cluster> {
+  let mut clusters: Vec<(Cluster, Class)> = Vec::new();
+
+  for (index, c) in text.chars().enumerate() {
+    match clusters.last_mut() {
+      Some((cluster, _)) if c.width() == Some(0) && !c.is_control() => cluster.text.push(c),
+
+      _ => clusters.push((
+        Cluster {
+          start: index,
+          text: c.to_string(),
+          width: c.width().unwrap_or(0) as i32,
+          rtl,
+        },
+        class(c),
+      )),
+    }
+  }
+
+  let base = if rtl { Class::Rtl } else { Class::Ltr };
+
+  for index in 0..clusters.len() {
+    let before = clusters[..index].iter().rev().map(|(_, class)| *class).find(|class| *class != Class::Neutral).unwrap_or(base);
+    let after = clusters[index + 1..].iter().map(|(_, class)| *class).find(|class| *class != Class::Neutral).unwrap_or(base);
+
+    clusters[index].0.rtl = match clusters[index].1 {
+      Class::Neutral if before == after => before == Class::Rtl,
+      Class::Neutral => rtl,
+      class => class == Class::Rtl,
+    };
+  }
+
+  let mut clusters = clusters.into_iter().map(|(cluster, _)| cluster).collect::<Vec<_>>();
+
+  // Runs going against the direction of the paragraph are reversed, then the
+  // whole line when it goes from right to left.
+  let mut start = 0;
+
+  while start < clusters.len() {
+    let mut end = start;
+
+    while end < clusters.len() && clusters[end].rtl == clusters[start].rtl {
+      end += 1;
+    }
+
+    if clusters[start].rtl != rtl {
+      clusters[start..end].reverse();
+    }
+
+    start = end;
+  }
+
+  if rtl {
+    clusters.reverse();
+  }
+
+  for cluster in clusters.iter_mut().filter(|cluster| cluster.rtl) {
+    cluster.text = cluster.text.chars().map(mirror).collect();
+  }
+
+  clusters
+}
+
+pub fn reorder(text: &str, rtl: bool) -> String {
+  if !rtl && !text.chars().any(|c| class(c) == Class::Rtl) {
+    return text.to_string();
+  }
+
+  layout(text, rtl).into_iter().map(|cluster| cluster.text).collect()
+}
+
+// Returns the column, from the start of the reordered text, of the cursor
+// placed before the character at the given offset, which is on the left of the
+// first column of the text when it ends with right-to-left characters.
+pub fn cursor_column(text: &str, offset: usize, rtl: bool) -> i32 {
+  let clusters = layout(text, rtl);
+
+  let mut column = 0;
+  let mut last = None;
+
+  for cluster in &clusters {
+    if cluster.start <= offset && offset < cluster.start + cluster.text.chars().count() {
+      return column;
+    }
+
+    if last.map(|(start, _, _)| cluster.start > start).unwrap_or(true) {
+      last = Some((cluster.start, column, cluster));
+    }
+
+    column += cluster.width;
+  }
+
+  match last {
+    Some((_, column, cluster)) if cluster.rtl => column - 1,
+    Some((_, column, cluster)) => column + cluster.width,
+    None if rtl => -1,
+    None => 0,
+  }
+}
@@ -0,0 +1,215 @@
+use std::{
+  convert::TryFrom,
+  env, fs,
+  path::{Path, PathBuf},
+};
+
+use ratatui::style::Color;
+
+const TERMINFO_DIRS: [&str; 3] = ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"];
+const TERMINFO_MAGIC: u16 = 0o432;
+const TERMINFO_MAGIC_32BIT: u16 = 0o1036;
+const TERMINFO_MAX_COLORS: usize = 13;
+
+// The 16 colors as xterm displays them by default, which most terminals are
+// close to.
+const PALETTE: [(u8, u8, u8); 16] = [
+  (0, 0, 0),
+  (205, 0, 0),
+  (0, 205, 0),
+  (205, 205, 0),
+  (0, 0, 238),
+  (205, 0, 205),
+  (0, 205, 205),
+  (229, 229, 229),
+  (127, 127, 127),
+  (255, 0, 0),
+  (0, 255, 0),
+  (255, 255, 0),
+  (92, 92, 255),
+  (255, 0, 255),
+  (0, 255, 255),
+  (255, 255, 255),
+];
+
+const NAMED: [Color; 16] = [
+  Color::Black,
+  Color::Red,
+  Color::Green,
+  Color::Yellow,
+  Color::Blue,
+  Color::Magenta,
+  Color::Cyan,
+  Color::Gray,
+  Color::DarkGray,
+  Color::LightRed,
+  Color::LightGreen,
+  Color::LightYellow,
+  Color::LightBlue,
+  Color::LightMagenta,
+  Color::LightCyan,
+  Color::White,
+];
+
+// The levels of each channel in the 6x6x6 color cube of 256-color terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// The colors the terminal can display, from the fewest to the most.
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum ColorMode {
+  Ansi,
+  Indexed,
+  #[default]
+  TrueColor,
+}
+
+impl ColorMode {
+  // Terminals advertising true colors do so through COLORTERM, others being
+  // described by their terminfo entry, or only by their name when it is
+  // missing. The Linux console, for one, only displays 16 colors.
+  pub fn detect() -> ColorMode {
+    if let Ok(colorterm) = env::var("COLORTERM") {
+      if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorMode::TrueColor;
+      }
+    }
+
+    let term = env::var("TERM").unwrap_or_default();
+
+    match max_colors(&term) {
+      Some(colors) if colors >= 1 << 24 => ColorMode::TrueColor,
+      Some(colors) if colors >= 256 => ColorMode::Indexed,
+      Some(_) => ColorMode::Ansi,
+      None if term.contains("256color") => ColorMode::Indexed,
+      None => ColorMode::Ansi,
+    }
+  }
+
+  // Replaces the color with the closest one the terminal can display.
+  pub fn degrade(&self, color: Color) -> Color {
+    match (self, color) {
+      (ColorMode::TrueColor, color) => color,
+      (ColorMode::Indexed, Color::Rgb(r, g, b)) => Color::Indexed(nearest_indexed((r, g, b))),
+      (ColorMode::Ansi, Color::Rgb(r, g, b)) => NAMED[nearest(&PALETTE, (r, g, b))],
+      (ColorMode::Ansi, Color::Indexed(index)) => NAMED[nearest(&PALETTE, indexed_rgb(index))],
+      (_, color) => color,
+    }
+  }
+}
+
+// Reads the number of colors from the compiled terminfo entry of the
+// terminal, looked up where ncurses does.
+fn max_colors(term: &str) -> Option<u32> {
+  let first = term.chars().next()?;
+
+  if term.contains('/') {
+    return None;
+  }
+
+  let mut dirs = Vec::new();
+
+  dirs.extend(env::var_os("TERMINFO").map(PathBuf::from));
+  dirs.extend(env::var_os("HOME").map(|home| Path::new(&home).join(".terminfo")));
+  dirs.extend(env::var_os("TERMINFO_DIRS").iter().flat_map(env::split_paths));
+  dirs.extend(TERMINFO_DIRS.iter().map(PathBuf::from));
+
+  dirs
+    .iter()
+    .flat_map(|dir| [dir.join(first.to_string()).join(term), dir.join(format!("{:x}", first as u32)).join(term)])
+    .find_map(|path| fs::read(path).ok())
+    .and_then(|entry| parse_max_colors(&entry))
+}
+
+// Compiled entries start with a header of six numbers, followed by the names
+// of the terminal, its boolean capabilities, padding to an even offset, and
+// its numeric capabilities, on 16 or 32 bits.
+fn parse_max_colors(entry: &[u8]) -> Option<u32> {
+  let short = |index: usize| entry.get(index * 2..index * 2 + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+
+  let width = match short(0)? {
+    TERMINFO_MAGIC => 2,
+    TERMINFO_MAGIC_32BIT => 4,
+    _ => return None,
+  };
+
+  let (names, booleans, numbers) = (short(1)? as usize, short(2)? as usize, short(3)? as usize);
+
+  if TERMINFO_MAX_COLORS >= numbers {
+    return None;
+  }
+
+  let start = 12 + names + booleans;
+  let offset = start + start % 2 + TERMINFO_MAX_COLORS * width;
+  let bytes = entry.get(offset..offset + width)?;
+
+  let colors = match width {
+    2 => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+    _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+  };
+
+  u32::try_from(colors).ok()
+}
+
+fn nearest(colors: &[(u8, u8, u8)], color: (u8, u8, u8)) -> usize {
+  let distance = |(r, g, b): (u8, u8, u8)| {
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+
+    channel(r, color.0) + channel(g, color.1) + channel(b, color.2)
+  };
+
+  (0..colors.len()).min_by_key(|index| distance(colors[*index])).unwrap_or(0)
+}
+
+// Only the color cube and the grayscale ramp are considered, the first 16
+// colors depending on the palette of the terminal.
+fn nearest_indexed(color: (u8, u8, u8)) -> u8 {
+  let candidates = (16..=255).map(indexed_rgb).collect::<Vec<_>>();
+
+  nearest(&candidates, color) as u8 + 16
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+  match index {
+    0..=15 => PALETTE[index as usize],
+
+    16..=231 => {
+      let index = index - 16;
+
+      (CUBE_LEVELS[(index / 36) as usize], CUBE_LEVELS[(index / 6 % 6) as usize], CUBE_LEVELS[(index % 6) as usize])
+    }
+
+    _ => {
+      let level = 8 + (index - 232) * 10;
+
+      (level, level, level)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ratatui::style::Color;
+
+  use super::{parse_max_colors, ColorMode};
+
+  #[test]
+  fn colors_are_degraded_to_the_closest_one() {
+    assert_eq!(ColorMode::Indexed.degrade(Color::Rgb(255, 135, 0)), Color::Indexed(208));
+    assert_eq!(ColorMode::Indexed.degrade(Color::Rgb(30, 30, 30)), Color::Indexed(234));
+    assert_eq!(ColorMode::Ansi.degrade(Color::Rgb(250, 10, 10)), Color::LightRed);
+    assert_eq!(ColorMode::Ansi.degrade(Color::Indexed(21)), Color::Blue);
+    assert_eq!(ColorMode::Ansi.degrade(Color::Cyan), Color::Cyan);
+    assert_eq!(ColorMode::TrueColor.degrade(Color::Rgb(1, 2, 3)), Color::Rgb(1, 2, 3));
+  }
+
+  #[test]
+  fn number_of_colors_is_read_from_terminfo() {
+    let mut entry = vec![0o432u16, 4, 1, 14, 0, 0].into_iter().flat_map(u16::to_le_bytes).collect::<Vec<_>>();
+
+    entry.extend(b"abc\0\x01\0");
+    entry.extend((0..14).flat_map(|index: i16| if index == 13 { 256i16 } else { -1 }.to_le_bytes()));
+
+    assert_eq!(parse_max_colors(&entry), Some(256));
+    assert_eq!(parse_max_colors(&entry[..20]), None);
+  }
+}
@@ -1,32 +1,43 @@
 use std::error::Error;
 
 use ratatui::{
-  layout::{Constraint, Direction, Layout, Rect},
+  backend::Backend,
+  layout::{Alignment, Constraint, Direction, Layout, Rect},
   text::Span,
-  widgets::{Block, BorderType, Borders, Paragraph},
+  widgets::{Block, Borders, Paragraph},
 };
 
 use crate::{
   ui::util::*,
-  ui::{prompt_value, Frame},
+  ui::{prompt_value, Frame, Themed},
   Greeter,
 };
 
-pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn Error>> {
+pub fn draw<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
   let size = f.size();
 
   let width = greeter.width();
   let height = get_height(greeter);
   let container_padding = greeter.container_padding();
-  let x = (size.width - width) / 2;
-  let y = (size.height - height) / 2;
+  let (x, y) = get_container_position(greeter, size, width, height);
 
   let container = Rect::new(x, y, width, height);
-  let frame = Rect::new(x + container_padding, y + container_padding, width - container_padding, height - container_padding);
+  let frame = Rect::new(
+    x + container_padding,
+    y + container_padding,
+    width.saturating_sub(container_padding),
+    height.saturating_sub(container_padding),
+  );
 
-  let block = Block::default().title(titleize(&fl!("title_command"))).borders(Borders::ALL).border_type(BorderType::Plain);
+  let title = Span::styled(titleize(&reorder(greeter, &fl!("title_command"))), greeter.theme.of(&[Themed::Title]));
+  let block = Block::default()
+    .title(title)
+    .title_alignment(mirror_alignment(greeter, Alignment::Left))
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
 
-  f.render_widget(block, container);
+  f.render_widget(Container::new(greeter, block), container);
 
   let constraints = [
     Constraint::Length(1), // Username
@@ -35,19 +46,24 @@ pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn
   let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
   let cursor = chunks[0];
 
-  let command_label_text = prompt_value(Some(fl!("new_command")));
+  let label_area = mirror_area(greeter, container, Rect::new(chunks[0].x, chunks[0].y, text_width(&fl!("new_command")), 1));
+  let value_area = mirror_area(
+    greeter,
+    container,
+    Rect::new(1 + chunks[0].x + text_width(&fl!("new_command")), chunks[0].y, get_input_width(greeter, &Some(fl!("new_command"))), 1),
+  );
+
+  let value = reorder(greeter, &greeter.new_command);
+  let command_label_text = prompt_value(&greeter.theme, Some(reorder(greeter, &fl!("new_command"))));
   let command_label = Paragraph::new(command_label_text);
-  let command_value_text = Span::from(greeter.new_command.clone());
-  let command_value = Paragraph::new(command_value_text);
+  let command_value_text = Span::styled(value.clone(), greeter.theme.of(&[Themed::Input, Themed::Text]));
+  let command_value = Paragraph::new(command_value_text).alignment(mirror_alignment(greeter, Alignment::Left));
 
-  f.render_widget(command_label, chunks[0]);
-  f.render_widget(
-    command_value,
-    Rect::new(1 + chunks[0].x + fl!("new_command").len() as u16, chunks[0].y, get_input_width(greeter, &Some(fl!("new_command"))), 1),
-  );
+  f.render_widget(command_label, label_area);
+  f.render_widget(command_value, value_area);
 
   let new_command = greeter.new_command.clone();
-  let offset = get_cursor_offset(greeter, new_command.chars().count());
+  let column = get_cursor_column(greeter, &new_command, None);
 
-  Ok((2 + cursor.x + fl!("new_command").len() as u16 + offset as u16, cursor.y + 1))
+  Ok((get_input_cursor(greeter, value_area, &value, column), cursor.y + 1))
 }
@@ -0,0 +1,74 @@
+use std::error::Error;
+
+use ratatui::{
+  backend::Backend,
+  layout::{Alignment, Constraint, Direction, Layout, Rect},
+  text::Span,
+  widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{
+  ui::util::*,
+  ui::{prompt_value, Frame, Themed},
+  Greeter,
+};
+
+pub fn draw<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
+  let size = f.size();
+
+  let width = greeter.width();
+  let height = get_height(greeter);
+  let container_padding = greeter.container_padding();
+  let (x, y) = get_container_position(greeter, size, width, height);
+
+  let container = Rect::new(x, y, width, height);
+  let frame = Rect::new(
+    x + container_padding,
+    y + container_padding,
+    width.saturating_sub(container_padding),
+    height.saturating_sub(container_padding),
+  );
+
+  let title = Span::styled(titleize(&reorder(greeter, &fl!("title_environment"))), greeter.theme.of(&[Themed::Title]));
+  let block = Block::default()
+    .title(title)
+    .title_alignment(mirror_alignment(greeter, Alignment::Left))
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
+
+  f.render_widget(Container::new(greeter, block), container);
+
+  let constraints = [
+    Constraint::Length(1), // Environment
+  ];
+
+  let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
+  let cursor = chunks[0];
+
+  let label_area = mirror_area(greeter, container, Rect::new(chunks[0].x, chunks[0].y, text_width(&fl!("new_environment")), 1));
+  let value_area = mirror_area(
+    greeter,
+    container,
+    Rect::new(
+      1 + chunks[0].x + text_width(&fl!("new_environment")),
+      chunks[0].y,
+      get_input_width(greeter, &Some(fl!("new_environment"))),
+      1,
+    ),
+  );
+
+  let value = reorder(greeter, &greeter.new_environment);
+  let command_label_text = prompt_value(&greeter.theme, Some(reorder(greeter, &fl!("new_environment"))));
+  let command_label = Paragraph::new(command_label_text);
+  let command_value_text = Span::styled(value.clone(), greeter.theme.of(&[Themed::Input, Themed::Text]));
+  let command_value = Paragraph::new(command_value_text).alignment(mirror_alignment(greeter, Alignment::Left));
+
+  f.render_widget(command_label, label_area);
+  f.render_widget(command_value, value_area);
+
+  let new_environment = greeter.new_environment.clone();
+  let column = get_cursor_column(greeter, &new_environment, None);
+
+  Ok((get_input_cursor(greeter, value_area, &value, column), cursor.y + 1))
+}
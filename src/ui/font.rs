@@ -0,0 +1,65 @@
+pub const FONT_HEIGHT: usize = 5;
+
+// Every glyph is drawn on five rows, lit pixels being represented by `#`.
+fn glyph(c: char) -> Option<[&'static str; FONT_HEIGHT]> {
+  let glyph = match c.to_ascii_uppercase() {
+    'A' => [" ### ", "#   #", "#####", "#   #", "#   #"],
+    'B' => ["#### ", "#   #", "#### ", "#   #", "#### "],
+    'C' => [" ####", "#    ", "#    ", "#    ", " ####"],
+    'D' => ["#### ", "#   #", "#   #", "#   #", "#### "],
+    'E' => ["#####", "#    ", "#### ", "#    ", "#####"],
+    'F' => ["#####", "#    ", "#### ", "#    ", "#    "],
+    'G' => [" ####", "#    ", "#  ##", "#   #", " ### "],
+    'H' => ["#   #", "#   #", "#####", "#   #", "#   #"],
+    'I' => ["###", " # ", " # ", " # ", "###"],
+    'J' => ["    #", "    #", "    #", "#   #", " ### "],
+    'K' => ["#   #", "#  # ", "###  ", "#  # ", "#   #"],
+    'L' => ["#    ", "#    ", "#    ", "#    ", "#####"],
+    'M' => ["#   #", "## ##", "# # #", "#   #", "#   #"],
+    'N' => ["#   #", "##  #", "# # #", "#  ##", "#   #"],
+    'O' => [" ### ", "#   #", "#   #", "#   #", " ### "],
+    'P' => ["#### ", "#   #", "#### ", "#    ", "#    "],
+    'Q' => [" ### ", "#   #", "# # #", "#  # ", " ## #"],
+    'R' => ["#### ", "#   #", "#### ", "#  # ", "#   #"],
+    'S' => [" ####", "#    ", " ### ", "    #", "#### "],
+    'T' => ["#####", "  #  ", "  #  ", "  #  ", "  #  "],
+    'U' => ["#   #", "#   #", "#   #", "#   #", " ### "],
+    'V' => ["#   #", "#   #", "#   #", " # # ", "  #  "],
+    'W' => ["#   #", "#   #", "# # #", "## ##", "#   #"],
+    'X' => ["#   #", " # # ", "  #  ", " # # ", "#   #"],
+    'Y' => ["#   #", " # # ", "  #  ", "  #  ", "  #  "],
+    'Z' => ["#####", "   # ", "  #  ", " #   ", "#####"],
+    '0' => [" ### ", "#  ##", "# # #", "##  #", " ### "],
+    '1' => ["  #  ", " ##  ", "  #  ", "  #  ", " ### "],
+    '2' => [" ### ", "#   #", "  ## ", " #   ", "#####"],
+    '3' => ["#### ", "    #", " ### ", "    #", "#### "],
+    '4' => ["#   #", "#   #", "#####", "    #", "    #"],
+    '5' => ["#####", "#    ", "#### ", "    #", "#### "],
+    '6' => [" ### ", "#    ", "#### ", "#   #", " ### "],
+    '7' => ["#####", "    #", "   # ", "  #  ", "  #  "],
+    '8' => [" ### ", "#   #", " ### ", "#   #", " ### "],
+    '9' => [" ### ", "#   #", " ####", "    #", " ### "],
+    '-' => ["    ", "    ", "####", "    ", "    "],
+    '_' => ["     ", "     ", "     ", "     ", "#####"],
+    '.' => [" ", " ", " ", " ", "#"],
+    ':' => [" ", "#", " ", "#", " "],
+    ' ' => ["  ", "  ", "  ", "  ", "  "],
+    _ => return None,
+  };
+
+  Some(glyph)
+}
+
+// Renders the given text with the embedded block font, one string per row,
+// with a blank column between glyphs. Unsupported characters are skipped.
+pub fn render(text: &str, pixel: char) -> Vec<String> {
+  let glyphs = text.chars().filter_map(glyph).collect::<Vec<_>>();
+
+  (0..FONT_HEIGHT)
+    .map(|row| {
+      let line = glyphs.iter().map(|glyph| glyph[row]).collect::<Vec<_>>().join(" ");
+
+      line.replace('#', &pixel.to_string())
+    })
+    .collect()
+}
@@ -0,0 +1,20 @@
+use ratatui::{
+  backend::Backend,
+  layout::{Alignment, Rect},
+  widgets::Paragraph,
+};
+
+use crate::{
+  ui::{util::*, Frame, Themed},
+  Greeter,
+};
+
+pub fn draw<B: Backend>(greeter: &Greeter, f: &mut Frame<B>, greeting: &[(Alignment, String)], area: Rect) {
+  for (index, (alignment, line)) in greeting.iter().enumerate().take(area.height as usize) {
+    let greeting_label = Paragraph::new(reorder(greeter, line))
+      .alignment(mirror_alignment(greeter, *alignment))
+      .style(greeter.theme.of(&[Themed::Greet, Themed::Text]));
+
+    f.render_widget(greeting_label, Rect::new(area.x, area.y + index as u16, area.width, 1));
+  }
+}
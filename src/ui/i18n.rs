@@ -1,14 +1,62 @@
+use std::{borrow::Cow, fs, path::Path};
+
 use i18n_embed::{
   fluent::{fluent_language_loader, FluentLanguageLoader},
-  DesktopLanguageRequester, LanguageLoader,
+  DesktopLanguageRequester, I18nAssets, LanguageLoader,
 };
 use lazy_static::lazy_static;
 use rust_embed::RustEmbed;
+use unic_langid::LanguageIdentifier;
+
+const OVERLAY_PATH: &str = "/etc/tuigreet/locales";
 
 #[derive(RustEmbed)]
 #[folder = "contrib/locales"]
+struct BundledLocalizations;
+
+// Translations dropped in the overlay directory, as <lang>/tuigreet.ftl, are
+// read before the bundled ones. Fluent keeping the first definition of every
+// message, theirs take precedence while missing ones are still found in the
+// bundled translations.
 struct Localizations;
 
+impl I18nAssets for Localizations {
+  fn get_file(&self, file_path: &str) -> Option<Cow<'_, [u8]>> {
+    let overlay = fs::read(Path::new(OVERLAY_PATH).join(file_path)).ok();
+    let bundled = BundledLocalizations::get(file_path).map(|file| file.data);
+
+    match (overlay, bundled) {
+      (Some(mut overlay), Some(bundled)) => {
+        overlay.push(b'\n');
+        overlay.extend_from_slice(&bundled);
+
+        Some(Cow::Owned(overlay))
+      }
+
+      (Some(overlay), None) => Some(Cow::Owned(overlay)),
+      (None, bundled) => bundled,
+    }
+  }
+
+  // Languages only found in the overlay directory are offered as well.
+  fn filenames_iter(&self) -> Box<dyn Iterator<Item = String>> {
+    let mut filenames = BundledLocalizations::iter().map(|filename| filename.to_string()).collect::<Vec<_>>();
+
+    if let Ok(entries) = fs::read_dir(OVERLAY_PATH) {
+      for entry in entries.flatten() {
+        if entry.path().join("tuigreet.ftl").is_file() {
+          filenames.push(format!("{}/tuigreet.ftl", entry.file_name().to_string_lossy()));
+        }
+      }
+    }
+
+    filenames.sort();
+    filenames.dedup();
+
+    Box::new(filenames.into_iter())
+  }
+}
+
 lazy_static! {
   pub static ref MESSAGES: FluentLanguageLoader = {
     let locales = Localizations;
@@ -20,3 +68,28 @@ lazy_static! {
     loader
   };
 }
+
+// Returns the bundled languages, along with their name as written in each of
+// them.
+pub fn available_languages() -> Vec<(LanguageIdentifier, String)> {
+  let mut languages = MESSAGES.available_languages(&Localizations).unwrap_or_default();
+  languages.sort_by_key(|language| language.to_string());
+
+  languages
+    .into_iter()
+    .map(|language| {
+      let loader = fluent_language_loader!();
+      let _ = loader.load_languages(&Localizations, &[&language, loader.fallback_language()]);
+
+      let name = loader.get("language_name");
+
+      (language, name)
+    })
+    .collect()
+}
+
+// Switches the interface to the given language, falling back to English for
+// missing messages.
+pub fn select_language(language: &LanguageIdentifier) {
+  let _ = i18n_embed::select(&*MESSAGES, &Localizations, std::slice::from_ref(language));
+}
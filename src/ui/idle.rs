@@ -0,0 +1,74 @@
+use ratatui::{
+  backend::Backend,
+  layout::{Alignment, Rect},
+  text::Span,
+  widgets::Paragraph,
+};
+
+use crate::{
+  ui::{
+    font::{render as render_font, FONT_HEIGHT},
+    format_time, get_time,
+    util::text_width,
+    Frame, Themed,
+  },
+  Greeter,
+};
+
+const BIG_CLOCK_FORMAT: &str = "%H:%M";
+
+// While idle, the screen is left blank, apart from the current date and time
+// if requested, which can also be drawn as a large clock.
+pub fn draw<B: Backend>(greeter: &Greeter, f: &mut Frame<B>) {
+  let size = f.size();
+
+  if size.height == 0 {
+    return;
+  }
+
+  if greeter.config().opt_present("idle-big-clock") {
+    draw_big_clock(greeter, f);
+  } else if greeter.config().opt_present("idle-clock") {
+    draw_time(greeter, f, size.y + size.height / 2);
+  }
+}
+
+// The hours and minutes are drawn with the block font of banners, above the
+// date and time, unless they do not fit on the screen.
+fn draw_big_clock<B: Backend>(greeter: &Greeter, f: &mut Frame<B>) {
+  let size = f.size();
+
+  let pixel = match greeter.ascii {
+    true => '#',
+    false => '█',
+  };
+
+  let rows = render_font(&format_time(greeter, greeter.time_zone.as_ref(), Some(BIG_CLOCK_FORMAT)), pixel);
+  let width = rows.iter().map(|row| text_width(row)).max().unwrap_or(0);
+  let height = FONT_HEIGHT as u16 + 2;
+
+  if width > size.width || height > size.height {
+    draw_time(greeter, f, size.y + size.height / 2);
+
+    return;
+  }
+
+  let y = size.y + (size.height - height) / 2;
+
+  for (index, row) in rows.into_iter().enumerate() {
+    let row = Paragraph::new(Span::styled(row, greeter.theme.of(&[Themed::Time]))).alignment(Alignment::Center);
+
+    f.render_widget(row, Rect::new(size.x, y + index as u16, size.width, 1));
+  }
+
+  draw_time(greeter, f, y + height - 1);
+}
+
+fn draw_time<B: Backend>(greeter: &Greeter, f: &mut Frame<B>, y: u16) {
+  let size = f.size();
+
+  let time_text = Span::styled(get_time(greeter), greeter.theme.of(&[Themed::Time]));
+  let time = Paragraph::new(time_text).alignment(Alignment::Center);
+
+  f.render_widget(time, Rect::new(size.x, y, size.width, 1));
+}
@@ -0,0 +1,12 @@
+use crate::{
+  ui::{menu::Menu, util::*},
+  Greeter,
+};
+
+pub fn menu(greeter: &Greeter) -> Menu<String> {
+  Menu {
+    title: titleize(&fl!("title_layout")),
+    items: greeter.layouts.iter().cloned().enumerate().collect(),
+    selected: greeter.selected_layout,
+  }
+}
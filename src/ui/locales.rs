@@ -0,0 +1,12 @@
+use crate::{
+  ui::{menu::Menu, util::*},
+  Greeter,
+};
+
+pub fn menu(greeter: &Greeter) -> Menu<String> {
+  Menu {
+    title: titleize(&fl!("title_locale")),
+    items: greeter.locales.iter().map(|(_, name)| name.clone()).enumerate().collect(),
+    selected: greeter.selected_locale,
+  }
+}
@@ -0,0 +1,54 @@
+use std::{error::Error, time::Instant};
+
+use ratatui::{
+  backend::Backend,
+  layout::{Alignment, Constraint, Direction, Layout, Rect},
+  text::Span,
+  widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{
+  ui::{util::*, Frame, Themed},
+  Greeter,
+};
+
+pub fn draw<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
+  let size = f.size();
+
+  let width = greeter.width();
+  let height: u16 = get_height(greeter) + 1;
+  let (x, y) = get_container_position(greeter, size, width, height);
+
+  let container = Rect::new(x, y, width, height);
+  let container_padding = greeter.container_padding();
+  let frame = Rect::new(
+    x + container_padding,
+    y + container_padding,
+    width.saturating_sub(2 * container_padding),
+    height.saturating_sub(2 * container_padding),
+  );
+
+  let block = Block::default()
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
+
+  let constraints = [Constraint::Length(1)];
+
+  let seconds = match greeter.lockout_deadline {
+    Some(deadline) => deadline.saturating_duration_since(Instant::now()).as_secs_f64().ceil() as u64,
+    None => 0,
+  };
+
+  let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
+  let text = Span::styled(
+    fl!("lockout", attempts = greeter.lockout_attempts.unwrap_or(1), seconds = seconds),
+    greeter.theme.of(&[Themed::Error, Themed::Text]),
+  );
+  let paragraph = Paragraph::new(text).alignment(Alignment::Center);
+
+  f.render_widget(Container::new(greeter, block), container);
+  f.render_widget(paragraph, chunks[0]);
+
+  Ok((1, 1))
+}
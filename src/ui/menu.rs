@@ -0,0 +1,160 @@
+use std::error::Error;
+
+use crossterm::event::KeyCode;
+use ratatui::{
+  backend::Backend,
+  layout::Rect,
+  style::{Modifier, Style},
+  text::{Span, Spans},
+  widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{
+  ui::{util::*, Frame, Target, Themed},
+  Greeter,
+};
+
+// An entry of a menu, rendered on a line of the given width.
+pub trait MenuItem {
+  fn render(&self, greeter: &Greeter, width: usize, selected: bool) -> Spans<'static>;
+}
+
+impl MenuItem for String {
+  fn render(&self, greeter: &Greeter, width: usize, selected: bool) -> Spans<'static> {
+    Spans::from(Span::styled(pad_to_width(self, width), style(greeter, selected)))
+  }
+}
+
+// An entry matching the filter of the menu, whose matched characters are
+// highlighted.
+pub struct Matched {
+  pub label: String,
+  pub positions: Vec<usize>,
+}
+
+impl MenuItem for Matched {
+  fn render(&self, greeter: &Greeter, width: usize, selected: bool) -> Spans<'static> {
+    highlight_matches(&pad_to_width(&self.label, width), &self.positions, style(greeter, selected))
+  }
+}
+
+// A list of items, along with their index in the underlying list, scrolled so
+// that the selected one is always visible when they do not all fit on the
+// screen.
+pub struct Menu<T> {
+  pub title: String,
+  pub items: Vec<(usize, T)>,
+  pub selected: usize,
+}
+
+impl<T> Menu<T> {
+  // Returns the item to select after the given key was pressed, the selection
+  // moving by one item with arrows, by the given number of items with Page Up
+  // and Page Down, and to the ends of the list with Home and End.
+  pub fn navigate(&self, code: KeyCode, page: usize) -> Option<usize> {
+    let last = self.items.len().checked_sub(1)?;
+    let row = self.items.iter().position(|(index, _)| *index == self.selected).unwrap_or(0);
+
+    let row = match code {
+      KeyCode::Up => row.saturating_sub(1),
+      KeyCode::Down => (row + 1).min(last),
+      KeyCode::PageUp => row.saturating_sub(page.max(1)),
+      KeyCode::PageDown => (row + page.max(1)).min(last),
+      KeyCode::Home => 0,
+      KeyCode::End => last,
+      _ => return None,
+    };
+
+    self.items.get(row).map(|(index, _)| *index)
+  }
+}
+
+impl<T: MenuItem> Menu<T> {
+  // The width needed to display the title and the longest entry unpadded.
+  pub fn content_width(&self, greeter: &Greeter) -> u16 {
+    let items = self.items.iter().map(|(_, item)| item.render(greeter, 0, false).width());
+
+    items.chain(Some(text_width(&self.title) as usize)).max().unwrap_or(0) as u16 + 4
+  }
+
+  pub fn draw<B: Backend>(&self, greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
+    let size = f.size();
+
+    // The title and status bars are kept clear.
+    let available = size
+      .height
+      .saturating_sub(get_height(greeter) + 2 + get_notifications_height(greeter) + (2 * greeter.window_padding()))
+      .max(1) as usize;
+    let visible = self.items.len().min(available);
+
+    greeter.menu_page = visible;
+
+    let width = greeter.width();
+    let height: u16 = get_height(greeter) + visible as u16;
+    let (x, y) = get_container_position(greeter, size, width, height);
+
+    let row = self.items.iter().position(|(index, _)| *index == self.selected).unwrap_or(0);
+
+    if row < greeter.menu_scroll {
+      greeter.menu_scroll = row;
+    } else if row >= greeter.menu_scroll + visible {
+      greeter.menu_scroll = row + 1 - visible;
+    }
+
+    greeter.menu_scroll = greeter.menu_scroll.min(self.items.len() - visible);
+
+    let container = Rect::new(x, y, width, height);
+
+    let title = Span::styled(self.title.clone(), greeter.theme.of(&[Themed::Title]));
+    let block = Block::default()
+      .title(title)
+      .borders(Borders::ALL)
+      .border_style(greeter.theme.of(&[Themed::Border]))
+      .style(greeter.theme.of(&[Themed::Container]));
+
+    f.render_widget(Container::new(greeter, block), container);
+
+    for (row, (index, item)) in self.items.iter().enumerate().skip(greeter.menu_scroll).take(visible) {
+      let frame = Rect::new(x + 2, y + 2 + (row - greeter.menu_scroll) as u16, width.saturating_sub(4), 1);
+      let option = Paragraph::new(item.render(greeter, width.saturating_sub(4) as usize, *index == self.selected));
+
+      f.render_widget(option, frame);
+
+      greeter.hitboxes.push((frame, Target::Entry(*index)));
+    }
+
+    if visible < self.items.len() {
+      self.draw_scrollbar(greeter, f, Rect::new((x + width).saturating_sub(2), y + 2, 1, visible as u16));
+    }
+
+    Ok((1, 1))
+  }
+
+  // The thumb is proportional to the part of the list which is visible.
+  fn draw_scrollbar<B: Backend>(&self, greeter: &Greeter, f: &mut Frame<B>, area: Rect) {
+    let visible = area.height as usize;
+    let total = self.items.len();
+
+    let thumb = (visible * visible / total).max(1);
+    let position = greeter.menu_scroll * (visible - thumb) / (total - visible);
+
+    let (track, handle) = match greeter.ascii {
+      true => ("|", "#"),
+      false => ("│", "█"),
+    };
+
+    for row in 0..visible {
+      let symbol = if row >= position && row < position + thumb { handle } else { track };
+      let symbol = Paragraph::new(Span::styled(symbol, greeter.theme.of(&[Themed::Border])));
+
+      f.render_widget(symbol, Rect::new(area.x, area.y + row as u16, 1, 1));
+    }
+  }
+}
+
+pub fn style(greeter: &Greeter, selected: bool) -> Style {
+  match selected {
+    true => greeter.theme.of(&[Themed::Text]).add_modifier(Modifier::REVERSED),
+    false => greeter.theme.of(&[Themed::Text]),
+  }
+}
@@ -1,9 +1,26 @@
+mod autologin;
+mod banner;
+mod bidi;
+mod color;
 mod command;
+mod environment;
+mod font;
+mod greeting;
 mod i18n;
+mod idle;
+mod layouts;
+mod locales;
+mod lockout;
+mod menu;
+pub mod osk;
 mod power;
 mod processing;
 mod prompt;
 mod sessions;
+mod small;
+mod spinner;
+mod status;
+mod theme;
 mod users;
 mod util;
 
@@ -14,66 +31,175 @@ use std::{
 };
 
 use chrono::prelude::*;
+use chrono_tz::Tz;
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-  backend::CrosstermBackend,
-  layout::{Alignment, Constraint, Direction, Layout},
-  style::{Modifier, Style},
+  backend::Backend,
+  layout::{Alignment, Constraint, Direction, Layout, Rect},
+  style::{Color, Modifier, Style},
   text::{Span, Spans},
-  widgets::Paragraph,
-  Frame as CrosstermFrame, Terminal,
+  widgets::{Block, Paragraph},
+  Frame as RatatuiFrame, Terminal,
 };
 use tokio::sync::RwLock;
 
 use crate::{
-  info::capslock_status,
-  ui::util::{should_hide_cursor, titleize},
-  Greeter, Mode,
+  demo,
+  ui::{
+    banner::get_banner_height,
+    util::{get_notifications_height, should_hide_cursor, titleize},
+  },
+  Animation, Greeter, IdentityPosition, Mode,
 };
 
-pub use self::{i18n::MESSAGES, power::OPTIONS as POWER_OPTIONS};
+pub use self::{
+  bidi::is_rtl_language,
+  color::ColorMode,
+  font::render as render_font,
+  i18n::MESSAGES,
+  i18n::{available_languages, select_language},
+  sessions::{allowed as session_allowed, resolve as resolve_session, visible as visible_sessions, Session, SessionType},
+  spinner::Spinner,
+  status::{parse as parse_status, Segments, DEFAULT_STATUS_LEFT, DEFAULT_STATUS_RIGHT},
+  theme::{Theme, Themed},
+  users::visible as visible_users,
+  util::{expand_template, get_message_lines, split_alignment, text_width, MESSAGES_MAX_HEIGHT},
+};
+
+// What clicking on an area of the screen does: pressing a key, selecting and
+// validating an entry of the current menu, or pressing a key of the on-screen
+// keyboard.
+#[derive(Debug, Copy, Clone)]
+pub enum Target {
+  Key(KeyEvent),
+  Entry(usize),
+  Osk(usize, usize),
+}
 
 const TITLEBAR_INDEX: usize = 1;
-const STATUSBAR_INDEX: usize = 3;
+const BANNER_INDEX: usize = 2;
+const MAIN_INDEX: usize = 3;
+const NOTIFICATIONS_INDEX: usize = 4;
+const STATUSBAR_INDEX: usize = 5;
 const STATUSBAR_LEFT_INDEX: usize = 1;
 const STATUSBAR_RIGHT_INDEX: usize = 2;
 
-pub(super) type Backend = CrosstermBackend<io::Stdout>;
-pub(super) type Term = Terminal<Backend>;
-pub(super) type Frame<'a> = CrosstermFrame<'a, Backend>;
+pub(super) type Frame<'a, B> = RatatuiFrame<'a, B>;
+
+// Drawing works on any backend, for the interface to be rendered in memory
+// when testing.
+//
+// Nothing is drawn, and the greeter is only locked for reading, while nothing
+// changed since the last frame.
+pub async fn draw<B: Backend>(greeter: Arc<RwLock<Greeter>>, terminal: &mut Terminal<B>) -> Result<(), Box<dyn Error>> {
+  if !greeter.read().await.dirty {
+    return Ok(());
+  }
 
-pub async fn draw(greeter: Arc<RwLock<Greeter>>, terminal: &mut Term) -> Result<(), Box<dyn Error>> {
   let mut greeter = greeter.write().await;
 
+  // Animations are drawn again on the next pass, including the frame which
+  // ends them.
+  greeter.dirty = greeter.animated();
+  greeter.drawn_at = Local::now().timestamp();
+
+  // Serial terminals do not all support hiding the cursor, which is parked in
+  // a corner instead.
   let hide_cursor = if should_hide_cursor(&greeter) {
-    terminal.hide_cursor()?;
+    if !greeter.serial {
+      terminal.hide_cursor()?;
+    }
+
     true
   } else {
     false
   };
 
+  // Clickable areas are recorded again by every widget being drawn.
+  greeter.hitboxes.clear();
+
   terminal.draw(|f| {
+    let content_width = match greeter.mode {
+      Mode::Sessions => self::sessions::menu(&greeter).content_width(&greeter),
+      Mode::Power => self::power::menu(&greeter).content_width(&greeter),
+      Mode::Users => self::users::menu(&greeter).content_width(&greeter),
+      Mode::Layouts => self::layouts::menu(&greeter).content_width(&greeter),
+      Mode::Locale => self::locales::menu(&greeter).content_width(&greeter),
+      _ => self::prompt::content_width(&greeter),
+    };
+
+    greeter.auto_width = content_width.min(f.size().width.saturating_sub(2 * greeter.window_padding()));
+
+    f.render_widget(Block::default().style(greeter.theme.of(&[Themed::Background])), f.size());
+
+    if greeter.serial {
+      f.set_cursor(0, f.size().bottom().saturating_sub(1));
+    }
+
+    if greeter.idle {
+      self::idle::draw(&greeter, f);
+      return;
+    }
+
+    if self::small::draw(&greeter, f) {
+      return;
+    }
+
     let size = f.size();
     let chunks = Layout::default()
       .constraints(
         [
-          Constraint::Length(greeter.window_padding()), // Top vertical padding
-          Constraint::Length(1),                        // Date and time
-          Constraint::Min(1),                           // Main area
-          Constraint::Length(1),                        // Status line
-          Constraint::Length(greeter.window_padding()), // Bottom vertical padding
+          Constraint::Length(greeter.window_padding()),           // Top vertical padding
+          Constraint::Length(1),                                  // Date and time
+          Constraint::Length(get_banner_height(&greeter)),        // Banner
+          Constraint::Min(1),                                     // Main area
+          Constraint::Length(get_notifications_height(&greeter)), // Notifications
+          Constraint::Length(1),                                  // Status line
+          Constraint::Length(greeter.window_padding()),           // Bottom vertical padding
         ]
         .as_ref(),
       )
       .split(size);
 
-    if greeter.config().opt_present("time") {
-      let time_text = Span::from(get_time(&greeter));
+    let identity = match &greeter.identity {
+      Some((IdentityPosition::Title, identity)) => Some(identity.clone()),
+      _ => None,
+    };
+
+    let time = greeter.config().opt_present("time").then(|| get_time(&greeter));
+    let title = vec![identity, time].into_iter().flatten().chain(get_clocks(&greeter)).collect::<Vec<_>>();
+
+    if !title.is_empty() {
+      let time_text = Span::styled(title.join("  "), greeter.theme.of(&[Themed::Time]));
       let time = Paragraph::new(time_text).alignment(Alignment::Center);
 
       f.render_widget(time, chunks[TITLEBAR_INDEX]);
     }
 
-    let status_block_size = (size.width - (2 * greeter.window_padding())) / 2;
+    self::banner::draw(&greeter, f, chunks[BANNER_INDEX]);
+
+    if !greeter.notifications.is_empty() {
+      let area = chunks[NOTIFICATIONS_INDEX];
+
+      let mut spans = vec![Span::styled(
+        greeter.notifications.join("  "),
+        Style::default().fg(Color::Red).patch(greeter.theme.of(&[Themed::Error])),
+      )];
+      spans.push(Span::raw(" "));
+      spans.push(status_label(&greeter.theme, greeter.kb_dismiss.to_string()));
+      spans.push(status_value(&greeter.theme, fl!("action_dismiss")));
+
+      let notifications = Spans::from(spans);
+      let x = area.x + area.width.saturating_sub(notifications.width() as u16) / 2;
+
+      let key = greeter.kb_dismiss.key();
+
+      greeter.hitboxes.push((Rect::new(area.x, area.y, area.width, 1), Target::Key(key)));
+
+      f.render_widget(Paragraph::new(notifications), Rect::new(x, area.y, area.width.saturating_sub(x - area.x), 1));
+    }
+
+    let status_block_size = size.width.saturating_sub(2 * greeter.window_padding()) / 2;
 
     let status_chunks = Layout::default()
       .direction(Direction::Horizontal)
@@ -88,39 +214,50 @@ pub async fn draw(greeter: Arc<RwLock<Greeter>>, terminal: &mut Term) -> Result<
       )
       .split(chunks[STATUSBAR_INDEX]);
 
-    let command = greeter.command.clone().unwrap_or_else(|| "-".to_string());
-    let status_left_text = Spans::from(vec![
-      status_label("ESC"),
-      status_value(fl!("action_reset")),
-      status_label("F2"),
-      status_value(fl!("action_command")),
-      status_label("F3"),
-      status_value(fl!("action_session")),
-      status_label("F12"),
-      status_value(fl!("action_power")),
-      status_label(fl!("status_command")),
-      status_value(command),
-    ]);
-    let status_left = Paragraph::new(status_left_text);
-
-    f.render_widget(status_left, status_chunks[STATUSBAR_LEFT_INDEX]);
-
-    if capslock_status() {
-      let status_right_text = status_label(fl!("status_caps"));
+    if let Some((status_left_text, buttons)) = status::render(&greeter, &greeter.status_left) {
+      let area = status_chunks[STATUSBAR_LEFT_INDEX];
+
+      for button in buttons {
+        greeter.hitboxes.push((Rect::new(area.x + button.offset, area.y, button.width, 1), Target::Key(button.key)));
+      }
+
+      f.render_widget(Paragraph::new(status_left_text), area);
+    }
+
+    if let Some((status_right_text, buttons)) = status::render(&greeter, &greeter.status_right) {
+      let area = status_chunks[STATUSBAR_RIGHT_INDEX];
+      let x = area.x + area.width.saturating_sub(status_right_text.width() as u16);
+
+      for button in buttons {
+        greeter.hitboxes.push((Rect::new(x + button.offset, area.y, button.width, 1), Target::Key(button.key)));
+      }
+
       let status_right = Paragraph::new(status_right_text).alignment(Alignment::Right);
 
-      f.render_widget(status_right, status_chunks[STATUSBAR_RIGHT_INDEX]);
+      f.render_widget(status_right, area);
     }
 
     let cursor = match greeter.mode {
       Mode::Command => self::command::draw(&mut greeter, f).ok(),
-      Mode::Sessions => self::sessions::draw(&mut greeter, f).ok(),
-      Mode::Power => self::power::draw(&mut greeter, f).ok(),
-      Mode::Users => self::users::draw(&mut greeter, f).ok(),
+      Mode::Environment => self::environment::draw(&mut greeter, f).ok(),
+      Mode::Sessions => self::sessions::menu(&greeter).draw(&mut greeter, f).ok(),
+      Mode::Power => self::power::menu(&greeter).draw(&mut greeter, f).ok(),
+      Mode::PowerConfirm => self::power::draw_confirm(&mut greeter, f).ok(),
+      Mode::Users => self::users::menu(&greeter).draw(&mut greeter, f).ok(),
+      Mode::Layouts => self::layouts::menu(&greeter).draw(&mut greeter, f).ok(),
+      Mode::Locale => self::locales::menu(&greeter).draw(&mut greeter, f).ok(),
       Mode::Processing => self::processing::draw(&mut greeter, f).ok(),
+      Mode::Autologin => self::autologin::draw(&mut greeter, f).ok(),
+      Mode::Lockout => self::lockout::draw(&mut greeter, f).ok(),
       _ => self::prompt::draw(&mut greeter, f).ok(),
     };
 
+    // Terminals cannot blend colors, so the main area fades in by being dimmed
+    // during the first half of transitions.
+    if greeter.animation == Animation::Fade && greeter.transition_progress().map(|progress| progress < 0.5).unwrap_or(false) {
+      f.render_widget(Block::default().style(Style::default().add_modifier(Modifier::DIM)), chunks[MAIN_INDEX]);
+    }
+
     if !hide_cursor {
       if let Some(cursor) = cursor {
         f.set_cursor(cursor.0 - 1, cursor.1 - 1);
@@ -133,35 +270,66 @@ pub async fn draw(greeter: Arc<RwLock<Greeter>>, terminal: &mut Term) -> Result<
   Ok(())
 }
 
+// Returns the entry to select in the menu of the current mode after the given
+// key was pressed, if it moves the selection.
+pub fn navigate_menu(greeter: &Greeter, code: KeyCode) -> Option<usize> {
+  match greeter.mode {
+    Mode::Users => self::users::menu(greeter).navigate(code, greeter.menu_page),
+    Mode::Sessions => self::sessions::menu(greeter).navigate(code, greeter.menu_page),
+    Mode::Power => self::power::menu(greeter).navigate(code, greeter.menu_page),
+    Mode::Layouts => self::layouts::menu(greeter).navigate(code, greeter.menu_page),
+    Mode::Locale => self::locales::menu(greeter).navigate(code, greeter.menu_page),
+    _ => None,
+  }
+}
+
 fn get_time(greeter: &Greeter) -> String {
-  let format = match greeter.config().opt_str("time-format") {
+  format_time(greeter, greeter.time_zone.as_ref(), None)
+}
+
+// The clocks in other time zones use the format of the main one unless they
+// were given theirs.
+fn get_clocks(greeter: &Greeter) -> Vec<String> {
+  greeter.clocks.iter().map(|(zone, format)| format_time(greeter, Some(zone), format.as_deref())).collect()
+}
+
+fn format_time(greeter: &Greeter, zone: Option<&Tz>, format: Option<&str>) -> String {
+  let format = match format.map(str::to_string).or_else(|| greeter.config().opt_str("time-format")) {
     Some(format) => format,
     None => fl!("date"),
   };
 
-  Local::now().format_localized(&format, greeter.locale).to_string()
+  let now = match greeter.demo {
+    true => demo::now(),
+    false => Local::now(),
+  };
+
+  match zone {
+    Some(zone) => now.with_timezone(zone).format_localized(&format, greeter.locale).to_string(),
+    None => now.format_localized(&format, greeter.locale).to_string(),
+  }
 }
 
-fn status_label<'s, S>(text: S) -> Span<'s>
+fn status_label<'s, S>(theme: &Theme, text: S) -> Span<'s>
 where
   S: Into<String>,
 {
-  Span::styled(text.into(), Style::default().add_modifier(Modifier::REVERSED))
+  Span::styled(text.into(), theme.of(&[Themed::ActionButton]).add_modifier(Modifier::REVERSED))
 }
 
-fn status_value<'s, S>(text: S) -> Span<'s>
+fn status_value<'s, S>(theme: &Theme, text: S) -> Span<'s>
 where
   S: Into<String>,
 {
-  Span::from(titleize(&text.into()))
+  Span::styled(titleize(&text.into()), theme.of(&[Themed::Action]))
 }
 
-fn prompt_value<'s, S>(text: Option<S>) -> Span<'s>
+fn prompt_value<'s, S>(theme: &Theme, text: Option<S>) -> Span<'s>
 where
   S: Into<String>,
 {
   match text {
-    Some(text) => Span::styled(text.into(), Style::default().add_modifier(Modifier::BOLD)),
+    Some(text) => Span::styled(text.into(), theme.of(&[Themed::Prompt]).add_modifier(Modifier::BOLD)),
     None => Span::from(""),
   }
 }
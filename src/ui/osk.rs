@@ -0,0 +1,122 @@
+use ratatui::{
+  backend::Backend,
+  layout::{Alignment, Rect},
+  style::Modifier,
+  text::{Span, Spans, Text},
+  widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{
+  ui::{util::*, Frame, Target, Themed},
+  Greeter,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Key {
+  Char(char),
+  Shift,
+  Space,
+  Backspace,
+  Enter,
+}
+
+// Every row of characters is given without and with Shift.
+const CHARACTERS: [(&str, &str); 4] = [("1234567890", "!@#$%^&*()"), ("qwertyuiop", "QWERTYUIOP"), ("asdfghjkl-", "ASDFGHJKL_"), ("zxcvbnm,./", "ZXCVBNM<>?")];
+
+const SPECIAL_KEYS: [Key; 4] = [Key::Shift, Key::Space, Key::Backspace, Key::Enter];
+
+// Each character key is three cells wide, with a blank cell between keys.
+const KEY_WIDTH: u16 = 4;
+
+pub fn rows() -> usize {
+  CHARACTERS.len() + 1
+}
+
+pub fn row_length(row: usize) -> usize {
+  match CHARACTERS.get(row) {
+    Some((characters, _)) => characters.chars().count(),
+    None => SPECIAL_KEYS.len(),
+  }
+}
+
+pub fn key(row: usize, column: usize, shift: bool) -> Option<Key> {
+  match CHARACTERS.get(row) {
+    Some((lower, upper)) => {
+      let characters = if shift { upper } else { lower };
+
+      characters.chars().nth(column).map(Key::Char)
+    }
+
+    None => SPECIAL_KEYS.get(column).copied(),
+  }
+}
+
+pub fn height() -> u16 {
+  rows() as u16 + 2
+}
+
+// Draws the keyboard right below the given container, if it fits on the screen.
+pub fn draw<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>, container: Rect) {
+  let size = f.size();
+
+  let width = row_length(0) as u16 * KEY_WIDTH + 3;
+  let height = height();
+  let y = container.y + container.height + 1;
+
+  if width > size.width || y + height > size.height {
+    return;
+  }
+
+  let x = (container.x + container.width / 2).saturating_sub(width / 2).min(size.width - width);
+  let area = Rect::new(x, y, width, height);
+
+  let block = Block::default()
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
+
+  let lines = (0..rows())
+    .map(|row| {
+      let keys = (0..row_length(row)).filter_map(|column| {
+        let label = match key(row, column, greeter.osk_shift)? {
+          Key::Char(c) => format!(" {c} "),
+          Key::Shift => format!(" {} ", fl!("osk_shift")),
+          Key::Space => format!(" {} ", fl!("osk_space")),
+          Key::Backspace => format!(" {} ", fl!("osk_backspace")),
+          Key::Enter => format!(" {} ", fl!("osk_enter")),
+        };
+
+        let style = match greeter.osk_selection == (row, column) {
+          true => greeter.theme.of(&[Themed::Text]).add_modifier(Modifier::REVERSED),
+          false => greeter.theme.of(&[Themed::Text]),
+        };
+
+        Some(Span::styled(label, style))
+      });
+
+      let spans = keys.flat_map(|key| vec![Span::raw(" "), key]).skip(1).collect::<Vec<_>>();
+
+      Spans::from(spans)
+    })
+    .collect::<Vec<_>>();
+
+  let inner = Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2);
+
+  // Keys are every other span, and centered the same way as paragraphs do.
+  for (row, line) in lines.iter().enumerate() {
+    let mut x = inner.x + (inner.width / 2).saturating_sub(line.width() as u16 / 2);
+
+    for (index, span) in line.0.iter().enumerate() {
+      if index % 2 == 0 {
+        greeter.hitboxes.push((Rect::new(x, inner.y + row as u16, span.width() as u16, 1), Target::Osk(row, index / 2)));
+      }
+
+      x += span.width() as u16;
+    }
+  }
+
+  let keyboard = Paragraph::new(Text::from(lines)).alignment(Alignment::Center);
+
+  f.render_widget(Container::new(greeter, block), area);
+  f.render_widget(keyboard, inner);
+}
@@ -1,63 +1,66 @@
-use std::error::Error;
+use std::{error::Error, time::Instant};
 
-use lazy_static::lazy_static;
 use ratatui::{
-  layout::Rect,
-  style::{Modifier, Style},
+  backend::Backend,
+  layout::{Alignment, Constraint, Direction, Layout, Rect},
   text::Span,
-  widgets::{Block, BorderType, Borders, Paragraph},
+  widgets::{Block, Borders, Paragraph},
 };
 
 use crate::{
-  power::PowerOption,
-  ui::{util::*, Frame},
+  ui::{menu::Menu, util::*, Frame, Themed},
   Greeter,
 };
 
-lazy_static! {
-  pub static ref OPTIONS: [(PowerOption, String); 2] = {
-    let shutdown = fl!("shutdown");
-    let reboot = fl!("reboot");
-
-    [(PowerOption::Shutdown, shutdown), (PowerOption::Reboot, reboot)]
-  };
+pub fn menu(greeter: &Greeter) -> Menu<String> {
+  Menu {
+    title: titleize(&fl!("title_power")),
+    items: greeter.power_options.iter().map(|(_, label)| label.clone()).enumerate().collect(),
+    selected: greeter.selected_power_option,
+  }
 }
 
-pub fn draw(greeter: &mut Greeter, f: &mut Frame<'_>) -> Result<(u16, u16), Box<dyn Error>> {
+// Counts down to the selected power action, which can still be canceled.
+pub fn draw_confirm<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
   let size = f.size();
 
   let width = greeter.width();
-  let height: u16 = get_height(greeter) + OPTIONS.len() as u16;
-  let x = (size.width - width) / 2;
-  let y = (size.height - height) / 2;
+  let height: u16 = get_height(greeter) + 2;
+  let (x, y) = get_container_position(greeter, size, width, height);
 
   let container = Rect::new(x, y, width, height);
+  let container_padding = greeter.container_padding();
+  let frame = Rect::new(
+    x + container_padding,
+    y + container_padding,
+    width.saturating_sub(2 * container_padding),
+    height.saturating_sub(2 * container_padding),
+  );
 
-  let title = Span::from(titleize(&fl!("title_power")));
-  let block = Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Plain);
+  let title = Span::styled(titleize(&fl!("title_power")), greeter.theme.of(&[Themed::Title]));
+  let block = Block::default()
+    .title(title)
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
 
-  for (index, (_, label)) in OPTIONS.iter().enumerate() {
-    let name = format!("{:1$}", label, greeter.width() as usize - 4);
+  let constraints = [Constraint::Length(1), Constraint::Length(1)];
 
-    let frame = Rect::new(x + 2, y + 2 + index as u16, width, 1);
-    let option_text = get_option(greeter, name, index);
-    let option = Paragraph::new(option_text);
+  let action = greeter.power_options.get(greeter.selected_power_option).map(|(_, label)| label.clone()).unwrap_or_default();
+  let seconds = match greeter.power_confirm_deadline {
+    Some(deadline) => deadline.saturating_duration_since(Instant::now()).as_secs_f64().ceil() as u64,
+    None => 0,
+  };
 
-    f.render_widget(option, frame);
-  }
+  let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
+  let text = Span::styled(fl!("power_confirm", action = action, seconds = seconds), greeter.theme.of(&[Themed::Text]));
+  let paragraph = Paragraph::new(text).alignment(Alignment::Center);
+  let hint = Span::styled(fl!("power_confirm_hint"), greeter.theme.of(&[Themed::Action]));
+  let hint = Paragraph::new(hint).alignment(Alignment::Center);
 
-  f.render_widget(block, container);
+  f.render_widget(Container::new(greeter, block), container);
+  f.render_widget(paragraph, chunks[0]);
+  f.render_widget(hint, chunks[1]);
 
   Ok((1, 1))
 }
-
-fn get_option<'g, S>(greeter: &Greeter, name: S, index: usize) -> Span<'g>
-where
-  S: Into<String>,
-{
-  if greeter.selected_power_option == index {
-    Span::styled(name.into(), Style::default().add_modifier(Modifier::REVERSED))
-  } else {
-    Span::from(name.into())
-  }
-}
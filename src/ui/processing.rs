@@ -1,38 +1,100 @@
 use std::error::Error;
 
 use ratatui::{
+  backend::Backend,
   layout::{Alignment, Constraint, Direction, Layout, Rect},
-  text::Span,
-  widgets::{Block, BorderType, Borders, Paragraph},
+  text::{Span, Text},
+  widgets::{Block, Borders, Paragraph},
 };
 
 use crate::{
-  ui::{util::*, Frame},
+  ui::{util::*, Frame, Themed},
   Greeter,
 };
 
-pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn Error>> {
+pub fn draw<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
   let size = f.size();
 
   let width = greeter.width();
-  let height: u16 = get_height(greeter) + 1;
-  let x = (size.width - width) / 2;
-  let y = (size.height - height) / 2;
+  let container_padding = greeter.container_padding();
+
+  let device_prompt = greeter.device_prompt.as_deref().map(|prompt| {
+    textwrap::wrap(prompt, width.saturating_sub(2 * container_padding).max(1) as usize)
+      .into_iter()
+      .map(|line| line.to_string())
+      .collect::<Vec<_>>()
+  });
+
+  let height: u16 = get_height(greeter) + device_prompt.as_ref().map(|lines| lines.len() as u16 + 4).unwrap_or(1);
+  let (x, y) = get_container_position(greeter, size, width, height);
 
   let container = Rect::new(x, y, width, height);
-  let container_padding = greeter.container_padding();
-  let frame = Rect::new(x + container_padding, y + container_padding, width - (2 * container_padding), height - (2 * container_padding));
+  let frame = Rect::new(
+    x + container_padding,
+    y + container_padding,
+    width.saturating_sub(2 * container_padding),
+    height.saturating_sub(2 * container_padding),
+  );
+
+  let block = Block::default()
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
+
+  f.render_widget(Container::new(greeter, block), container);
+
+  let elapsed = greeter.processing_since.map(|since| since.elapsed().as_secs()).unwrap_or_default();
 
-  let block = Block::default().borders(Borders::ALL).border_type(BorderType::Plain);
+  // While a PAM module waits on a device, its instructions are displayed
+  // along with a spinner and the time spent waiting, instead of an empty
+  // prompt, and users are told how to give up.
+  if let Some(lines) = device_prompt {
+    let constraints = [
+      Constraint::Length(lines.len() as u16),
+      Constraint::Length(1),
+      Constraint::Length(1),
+      Constraint::Length(1),
+      Constraint::Length(1),
+    ];
+    let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
+
+    let status = match greeter.spinner.frame(greeter.frame) {
+      Some(spinner) => format!("{spinner} {elapsed}s"),
+      None => format!("{elapsed}s"),
+    };
+
+    let text = Text::styled(lines.join("\n"), greeter.theme.of(&[Themed::Prompt, Themed::Text]));
+    let paragraph = Paragraph::new(text).alignment(Alignment::Center);
+    let status = Paragraph::new(Span::styled(status, greeter.theme.of(&[Themed::Action, Themed::Text]))).alignment(Alignment::Center);
+    let hint = Paragraph::new(Span::styled(fl!("device_cancel_hint"), greeter.theme.of(&[Themed::Action]))).alignment(Alignment::Center);
+
+    f.render_widget(paragraph, chunks[0]);
+    f.render_widget(status, chunks[2]);
+    f.render_widget(hint, chunks[4]);
+
+    return Ok((1, 1));
+  }
 
   let constraints = [Constraint::Length(1)];
 
   let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
-  let text = Span::from(fl!("wait"));
+
+  let label = match greeter.cancelling {
+    true => fl!("canceling"),
+    false => fl!("wait"),
+  };
+
+  let text = match (greeter.spinner.frame(greeter.frame), elapsed) {
+    (None, 0) => label,
+    (None, elapsed) => format!("{label} {elapsed}s"),
+    (Some(spinner), 0) => format!("{spinner} {label}"),
+    (Some(spinner), elapsed) => format!("{spinner} {label} {elapsed}s"),
+  };
+
+  let text = Span::styled(text, greeter.theme.of(&[Themed::Text]));
   let paragraph = Paragraph::new(text).alignment(Alignment::Center);
 
   f.render_widget(paragraph, chunks[0]);
-  f.render_widget(block, container);
 
   Ok((1, 1))
 }
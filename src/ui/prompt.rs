@@ -1,126 +1,207 @@
 use std::error::Error;
 
 use ratatui::{
+  backend::Backend,
   layout::{Alignment, Constraint, Direction, Layout, Rect},
-  text::{Span, Text},
-  widgets::{Block, BorderType, Borders, Paragraph},
+  style::{Color, Modifier, Style},
+  text::{Span, Spans, Text},
+  widgets::{Block, Borders, Paragraph},
 };
 
 use crate::{
-  info::get_hostname,
-  ui::{prompt_value, util::*, Frame},
-  Greeter, Mode,
+  ui::{osk, prompt_value, util::*, Frame, Themed},
+  Greeter, MessageLevel, Mode,
 };
 
 const GREETING_INDEX: usize = 0;
 const USERNAME_INDEX: usize = 1;
-const ANSWER_INDEX: usize = 2;
+const STEPS_INDEX: usize = 2;
+const ANSWER_INDEX: usize = 3;
+const MESSAGES_INDEX: usize = 4;
+
+// Room left for typing after the labels of prompts sized to their content.
+const AUTO_INPUT_WIDTH: u16 = 24;
+
+// The width needed to display the title, greeting, messages and prompts of the
+// current mode without wrapping.
+pub fn content_width(greeter: &Greeter) -> u16 {
+  let padding = 2 * greeter.container_padding() + 2;
+
+  let title = match greeter.mode {
+    Mode::Command => fl!("title_command"),
+    Mode::Environment => fl!("title_environment"),
+    _ => fl!("title_authenticate", hostname = greeter.hostname()),
+  };
+
+  let title = text_width(&titleize(&title)) + 4;
+  let greeting = greeter.greeting.iter().flat_map(|greeting| greeting.lines()).map(|line| text_width(split_alignment(line).1) + padding);
+  let messages = greeter.messages.iter().flat_map(|message| message.text.lines()).map(|line| text_width(line) + padding);
+
+  let prompt = greeter.prompt.as_ref().map(|prompt| prompt.message.clone());
+  let labels = [Some(fl!("username")), prompt, Some(fl!("new_command")), Some(fl!("new_environment"))];
+  let labels = labels.iter().flatten().map(|label| text_width(label) + 1 + AUTO_INPUT_WIDTH + padding);
+
+  let steps = greeter
+    .steps
+    .iter()
+    .map(|step| text_width(&step.prompt.message) + text_width(step.answer.as_deref().unwrap_or_default()) + padding);
+
+  greeting
+    .chain(messages)
+    .chain(labels)
+    .chain(steps)
+    .chain([title, text_width(&fl!("select_user")) + padding])
+    .max()
+    .unwrap_or(0)
+}
 
-pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn Error>> {
+pub fn draw<B: Backend>(greeter: &mut Greeter, f: &mut Frame<B>) -> Result<(u16, u16), Box<dyn Error>> {
   let size = f.size();
 
   let width = greeter.width();
   let height = get_height(greeter);
   let container_padding = greeter.container_padding();
   let prompt_padding = greeter.prompt_padding();
-  let x = (size.width - width) / 2;
-  let y = (size.height - height) / 2;
+  let keyboard_height = if greeter.osk { osk::height() + 1 } else { 0 };
+  let (x, y) = get_container_position(greeter, size, width, height + keyboard_height);
 
   let container = Rect::new(x, y, width, height);
-  let frame = Rect::new(x + container_padding, y + container_padding, width - (2 * container_padding), height - (2 * container_padding));
-
-  let hostname = Span::from(titleize(&fl!("title_authenticate", hostname = get_hostname())));
-  let block = Block::default().title(hostname).borders(Borders::ALL).border_type(BorderType::Plain);
-
-  f.render_widget(block, container);
+  let frame = Rect::new(
+    x + container_padding,
+    y + container_padding,
+    width.saturating_sub(2 * container_padding),
+    height.saturating_sub(2 * container_padding),
+  );
+
+  let hostname = Span::styled(
+    titleize(&reorder(greeter, &fl!("title_authenticate", hostname = greeter.hostname()))),
+    greeter.theme.of(&[Themed::Title]),
+  );
+  let block = Block::default()
+    .title(hostname)
+    .title_alignment(mirror_alignment(greeter, Alignment::Left))
+    .borders(Borders::ALL)
+    .border_style(greeter.theme.of(&[Themed::Border]))
+    .style(greeter.theme.of(&[Themed::Container]));
+
+  f.render_widget(Container::new(greeter, block), container);
+
+  if greeter.osk {
+    osk::draw(greeter, f, container);
+  }
 
-  let (message, message_height) = get_message_height(greeter, container_padding, 1);
   let (greeting, greeting_height) = get_greeting_height(greeter, container_padding, 0);
 
   let username_padding = if greeter.mode == Mode::Username && prompt_padding == 0 { 1 } else { prompt_padding };
   let answer_padding = if prompt_padding == 0 { 1 } else { prompt_padding };
 
   let constraints = [
-    Constraint::Length(greeting_height),                                                     // Greeting
-    Constraint::Length(1 + username_padding),                                                // Username
-    Constraint::Length(if greeter.mode == Mode::Username { 0 } else { 1 + answer_padding }), // Answer
+    Constraint::Length(greeting_height),                                                            // Greeting
+    Constraint::Length(if greeter.kiosk_user.is_some() { 0 } else { 1 + username_padding }),        // Username
+    Constraint::Length(if greeter.mode == Mode::Username { 0 } else { get_steps_height(greeter) }), // Steps
+    Constraint::Length(if greeter.mode == Mode::Username { 0 } else { 1 + answer_padding }),        // Answer
+    Constraint::Length(get_message_height(greeter)),                                                // Messages
   ];
 
   let chunks = Layout::default().direction(Direction::Vertical).constraints(constraints.as_ref()).split(frame);
   let cursor = chunks[USERNAME_INDEX];
 
   if let Some(greeting) = &greeting {
-    let greeting_text = greeting.trim_end();
-    let greeting_label = Paragraph::new(greeting_text).alignment(Alignment::Center);
-
-    f.render_widget(greeting_label, chunks[GREETING_INDEX]);
+    crate::ui::greeting::draw(greeter, f, greeting, chunks[GREETING_INDEX]);
   }
 
-  let username_label = if greeter.user_menu && greeter.username.is_empty() {
-    let prompt_text = Span::from(fl!("select_user"));
+  let (username_label, username_label_area) = if greeter.user_menu && greeter.username.is_empty() {
+    let prompt_text = Span::styled(reorder(greeter, &fl!("select_user")), greeter.theme.of(&[Themed::Prompt]));
 
-    Paragraph::new(prompt_text).alignment(Alignment::Center)
+    (Paragraph::new(prompt_text).alignment(Alignment::Center), chunks[USERNAME_INDEX])
   } else {
-    let username_text = prompt_value(Some(fl!("username")));
+    let username_text = prompt_value(&greeter.theme, Some(reorder(greeter, &fl!("username"))));
+    let area = Rect::new(chunks[USERNAME_INDEX].x, chunks[USERNAME_INDEX].y, text_width(&fl!("username")), 1);
+
+    (Paragraph::new(username_text), mirror_area(greeter, container, area))
+  };
 
-    Paragraph::new(username_text)
+  let username_area = mirror_area(
+    greeter,
+    container,
+    Rect::new(
+      1 + chunks[USERNAME_INDEX].x + text_width(&fl!("username")),
+      chunks[USERNAME_INDEX].y,
+      get_input_width(greeter, &Some(fl!("username"))),
+      1,
+    ),
+  );
+
+  let answer_area = mirror_area(
+    greeter,
+    container,
+    Rect::new(
+      chunks[USERNAME_INDEX].x + greeter.prompt_width() as u16,
+      chunks[ANSWER_INDEX].y,
+      get_input_width(greeter, &greeter.prompt.as_ref().map(|prompt| prompt.message.clone())),
+      1,
+    ),
+  );
+
+  let username = reorder(greeter, greeter.username_mask.as_deref().unwrap_or_else(|| greeter.username.as_ref()));
+  let username_value_text = Span::styled(username, greeter.theme.of(&[Themed::Input, Themed::Text]));
+  let username_value = Paragraph::new(username_value_text).alignment(mirror_alignment(greeter, Alignment::Left));
+
+  let secret = greeter.is_secret();
+
+  let answer = match secret {
+    true if greeter.asterisks => greeter.asterisks_char.to_string().repeat(greeter.mask_length()),
+    true => String::new(),
+    false => reorder(greeter, &greeter.answer),
   };
 
-  let username = greeter.username_mask.as_deref().unwrap_or_else(|| greeter.username.as_ref());
-  let username_value_text = Span::from(username);
-  let username_value = Paragraph::new(username_value_text);
+  let otp = greeter.is_otp();
+  let answer = if otp { group_digits(&answer) } else { answer };
 
   match greeter.mode {
     Mode::Username | Mode::Password => {
-      f.render_widget(username_label, chunks[USERNAME_INDEX]);
-
-      if !greeter.user_menu || !greeter.username.is_empty() {
-        f.render_widget(
-          username_value,
-          Rect::new(
-            1 + chunks[USERNAME_INDEX].x + fl!("username").len() as u16,
-            chunks[USERNAME_INDEX].y,
-            get_input_width(greeter, &Some(fl!("username"))),
-            1,
-          ),
-        );
+      if greeter.kiosk_user.is_none() {
+        f.render_widget(username_label, username_label_area);
+
+        if !greeter.user_menu || !greeter.username.is_empty() {
+          f.render_widget(username_value, username_area);
+        }
       }
 
-      let answer_text = if greeter.working { Span::from(fl!("wait")) } else { prompt_value(greeter.prompt.as_ref()) };
+      let answer_text = if greeter.working {
+        Span::styled(reorder(greeter, &fl!("wait")), greeter.theme.of(&[Themed::Text]))
+      } else {
+        prompt_value(&greeter.theme, greeter.prompt.as_ref().map(|prompt| reorder(greeter, &prompt.message)))
+      };
 
-      let answer_label = Paragraph::new(answer_text);
+      let answer_label = Paragraph::new(answer_text).alignment(mirror_alignment(greeter, Alignment::Left));
 
       if greeter.mode == Mode::Password || greeter.previous_mode == Mode::Password {
+        draw_steps(greeter, f, container, chunks[STEPS_INDEX]);
+
         f.render_widget(answer_label, chunks[ANSWER_INDEX]);
 
-        if !greeter.secret || greeter.asterisks {
-          let value = if greeter.secret && greeter.asterisks {
-            greeter.asterisks_char.to_string().repeat(greeter.answer.len())
-          } else {
-            greeter.answer.clone()
+        if !secret || greeter.asterisks {
+          let style = match otp {
+            true => greeter.theme.of(&[Themed::Input, Themed::Text]).add_modifier(Modifier::BOLD),
+            false => greeter.theme.of(&[Themed::Input, Themed::Text]),
           };
 
-          let answer_value_text = Span::from(value);
-          let answer_value = Paragraph::new(answer_value_text);
-
-          f.render_widget(
-            answer_value,
-            Rect::new(
-              chunks[ANSWER_INDEX].x + greeter.prompt_width() as u16,
-              chunks[ANSWER_INDEX].y,
-              get_input_width(greeter, &greeter.prompt),
-              1,
-            ),
-          );
+          let answer_value_text = Span::styled(answer.clone(), style);
+          let answer_value = Paragraph::new(answer_value_text).alignment(mirror_alignment(greeter, Alignment::Left));
+
+          f.render_widget(answer_value, answer_area);
         }
-      }
 
-      if let Some(message) = message {
-        let message_text = Text::from(message);
-        let message = Paragraph::new(message_text).alignment(Alignment::Center);
+        if let Some(hint) = greeter.password_hint() {
+          let hint_text = Span::styled(reorder(greeter, &hint), greeter.theme.of(&[Themed::Text]).add_modifier(Modifier::DIM));
+          let hint = Paragraph::new(hint_text).alignment(mirror_alignment(greeter, Alignment::Right));
 
-        f.render_widget(message, Rect::new(x, y + height, width, message_height));
+          f.render_widget(hint, answer_area);
+        }
       }
+
+      draw_messages(greeter, f, chunks[MESSAGES_INDEX]);
     }
 
     _ => {}
@@ -128,23 +209,99 @@ pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn
 
   match greeter.mode {
     Mode::Username => {
-      let username_length = greeter.username.chars().count();
-      let offset = get_cursor_offset(greeter, username_length);
+      let value = greeter.username.clone();
+      let column = get_cursor_column(greeter, &value, None);
 
-      Ok((2 + cursor.x + fl!("username").len() as u16 + offset as u16, USERNAME_INDEX as u16 + cursor.y))
+      Ok((get_input_cursor(greeter, username_area, &reorder(greeter, &value), column), USERNAME_INDEX as u16 + cursor.y))
     }
 
     Mode::Password => {
-      let answer_length = greeter.answer.chars().count();
-      let offset = get_cursor_offset(greeter, answer_length);
+      let value = greeter.answer.to_string();
+      let mask_width = secret.then(|| text_width(&greeter.asterisks_char.to_string()));
 
-      if greeter.secret && !greeter.asterisks {
-        Ok((1 + cursor.x + greeter.prompt_width() as u16, ANSWER_INDEX as u16 + prompt_padding + cursor.y))
+      let column = if otp || (secret && (!greeter.asterisks || greeter.asterisks_length.is_some())) {
+        get_end_column(greeter, text_width(&answer))
       } else {
-        Ok((1 + cursor.x + greeter.prompt_width() as u16 + offset as u16, ANSWER_INDEX as u16 + prompt_padding + cursor.y))
-      }
+        get_cursor_column(greeter, &value, mask_width)
+      };
+
+      Ok((get_input_cursor(greeter, answer_area, &answer, column), 1 + chunks[ANSWER_INDEX].y))
     }
 
     _ => Ok((1, 1)),
   }
 }
+
+// Answered questions are greyed out, followed by their answer when it was
+// echoed.
+fn draw_steps<B: Backend>(greeter: &Greeter, f: &mut Frame<B>, container: Rect, area: Rect) {
+  let row_height = 1 + greeter.prompt_padding();
+  let skipped = greeter.steps.len().saturating_sub(STEPS_MAX_HEIGHT);
+
+  for (row, step) in greeter.steps.iter().skip(skipped).enumerate() {
+    let y = area.y + row as u16 * row_height;
+
+    if y >= area.y + area.height {
+      break;
+    }
+
+    let label_width = text_width(&step.prompt.message);
+    let mut label = prompt_value(&greeter.theme, Some(reorder(greeter, &step.prompt.message)));
+    label.style = label.style.add_modifier(Modifier::DIM);
+
+    let label = Paragraph::new(label);
+
+    f.render_widget(label, mirror_area(greeter, container, Rect::new(area.x, y, label_width.min(area.width), 1)));
+
+    if let Some(answer) = &step.answer {
+      let value = Span::styled(reorder(greeter, answer), greeter.theme.of(&[Themed::Input, Themed::Text]).add_modifier(Modifier::DIM));
+      let value = Paragraph::new(value).alignment(mirror_alignment(greeter, Alignment::Left));
+
+      f.render_widget(value, mirror_area(greeter, container, Rect::new(area.x + label_width, y, area.width.saturating_sub(label_width), 1)));
+    }
+  }
+}
+
+fn draw_messages<B: Backend>(greeter: &Greeter, f: &mut Frame<B>, area: Rect) {
+  let lines = get_message_lines(greeter);
+
+  if lines.is_empty() {
+    return;
+  }
+
+  let end = lines.len() - greeter.messages_scroll.min(lines.len());
+  let start = end.saturating_sub(MESSAGES_MAX_HEIGHT);
+
+  let text = lines[start..end]
+    .iter()
+    .map(|(level, line)| {
+      let style = match level {
+        MessageLevel::Info => greeter.theme.of(&[Themed::Text]),
+        MessageLevel::Error if greeter.high_contrast => Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        MessageLevel::Error => Style::default().fg(Color::Red).patch(greeter.theme.of(&[Themed::Error])),
+      };
+
+      Spans::from(Span::styled(line.clone(), style))
+    })
+    .collect::<Vec<_>>();
+
+  let messages = Paragraph::new(Text::from(text)).alignment(Alignment::Center);
+
+  f.render_widget(messages, area);
+
+  let (up, down) = match greeter.ascii {
+    true => ("^", "v"),
+    false => ("▲", "▼"),
+  };
+
+  if start > 0 {
+    f.render_widget(Paragraph::new(up).alignment(Alignment::Right), Rect::new(area.x, area.y, area.width, 1));
+  }
+
+  if end < lines.len() && area.height > 0 {
+    f.render_widget(
+      Paragraph::new(down).alignment(Alignment::Right),
+      Rect::new(area.x, (area.y + area.height).saturating_sub(1), area.width, 1),
+    );
+  }
+}
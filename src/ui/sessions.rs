@@ -1,52 +1,212 @@
-use std::error::Error;
-
 use ratatui::{
-  layout::Rect,
-  style::{Modifier, Style},
-  text::Span,
-  widgets::{Block, BorderType, Borders, Paragraph},
+  style::Modifier,
+  text::{Span, Spans},
 };
 
 use crate::{
-  ui::{util::*, Frame},
+  ui::{
+    menu::{self, Matched, Menu, MenuItem},
+    util::*,
+  },
   Greeter,
 };
 
-pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn Error>> {
-  let size = f.size();
+#[derive(SmartDefault, Debug, Copy, Clone, PartialEq)]
+pub enum SessionType {
+  X11,
+  Wayland,
+  #[default]
+  None,
+}
+
+impl SessionType {
+  pub fn as_xdg_session_type(&self) -> Option<&'static str> {
+    match self {
+      SessionType::X11 => Some("x11"),
+      SessionType::Wayland => Some("wayland"),
+      SessionType::None => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+  pub name: String,
+  pub command: String,
+  // The description from the desktop file, telling apart sessions with
+  // similar names.
+  pub comment: Option<String>,
+  pub session_type: SessionType,
+  pub desktop_names: Option<String>,
+  // The command the session is started through, such as the one spawning an
+  // X server for X11 sessions.
+  pub wrapper: Option<String>,
+  // The name of the desktop file the session was read from, without its
+  // extension.
+  pub id: Option<String>,
+}
+
+impl Session {
+  pub fn from_command(command: &str) -> Session {
+    Session {
+      name: command.to_string(),
+      command: command.to_string(),
+      comment: None,
+      session_type: SessionType::None,
+      desktop_names: None,
+      wrapper: None,
+      id: None,
+    }
+  }
+
+  // The command as it is run, through its wrapper if it has one.
+  pub fn full_command(&self) -> String {
+    match &self.wrapper {
+      Some(wrapper) => format!("{wrapper} {}", self.command),
+      None => self.command.clone(),
+    }
+  }
+
+  // Sessions are designated by their name or desktop file, ignoring case.
+  fn is(&self, name: &str) -> bool {
+    self.name.eq_ignore_ascii_case(name) || self.id.iter().any(|id| id.eq_ignore_ascii_case(name))
+  }
+}
+
+// Whether the session can be selected given the allowlist, which is ignored
+// when empty, and the denylist, which applies after it.
+pub fn allowed(session: &Session, allow: &[String], deny: &[String]) -> bool {
+  (allow.is_empty() || allow.iter().any(|name| session.is(name))) && !deny.iter().any(|name| session.is(name))
+}
+
+// Returns the command of the session selected on startup: the one remembered
+// from the last login, then the default session, then the command given with
+// --cmd, and finally the first session available. A default session which is
+// not available is only an error when there is no command to fall back to.
+pub fn resolve(sessions: &[Session], remembered: Option<String>, default: Option<&str>, command: Option<&str>) -> Result<Option<String>, String> {
+  if remembered.is_some() {
+    return Ok(remembered);
+  }
+
+  if let Some(name) = default {
+    match sessions.iter().find(|session| session.name == name) {
+      Some(session) => return Ok(Some(session.command.clone())),
+      None if command.is_none() => return Err(format!("Unknown session '{name}' provided in --session-default")),
+      None => {}
+    }
+  }
+
+  Ok(command.map(str::to_string).or_else(|| sessions.first().map(|session| session.command.clone())))
+}
 
-  let width = greeter.width();
-  let height: u16 = get_height(greeter) + greeter.sessions.len() as u16;
-  let x = (size.width - width) / 2;
-  let y = (size.height - height) / 2;
+// Returns the sessions matching the current filter, along with their name and
+// the position of the matched characters.
+pub fn visible(greeter: &Greeter) -> Vec<(usize, String, Vec<usize>)> {
+  greeter
+    .sessions
+    .iter()
+    .enumerate()
+    .filter_map(|(index, session)| fuzzy_match(&greeter.menu_filter, &session.name).map(|positions| (index, session.name.clone(), positions)))
+    .collect()
+}
 
-  let container = Rect::new(x, y, width, height);
+// An entry of the session menu, followed by the description of the session
+// when there is room for it.
+pub struct Entry {
+  matched: Matched,
+  comment: Option<String>,
+}
 
-  let title = Span::from(titleize(&fl!("title_session")));
-  let block = Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Plain);
+impl MenuItem for Entry {
+  fn render(&self, greeter: &Greeter, width: usize, selected: bool) -> Spans<'static> {
+    let label_width = text_width(&self.matched.label) as usize + 2;
 
-  for (index, (name, _)) in greeter.sessions.iter().enumerate() {
-    let name = format!("{:1$}", name, greeter.width() as usize - 4);
+    match &self.comment {
+      // Entries are rendered without a width to measure them.
+      Some(comment) if width == 0 || label_width + text_width(comment) as usize <= width => {
+        let mut spans = self.matched.render(greeter, label_width.max(width.saturating_sub(text_width(comment) as usize)), selected).0;
+        spans.push(Span::styled(comment.clone(), menu::style(greeter, selected).add_modifier(Modifier::DIM)));
 
-    let frame = Rect::new(x + 2, y + 2 + index as u16, width, 1);
-    let option_text = get_option(greeter, name, index);
-    let option = Paragraph::new(option_text);
+        Spans::from(spans)
+      }
 
-    f.render_widget(option, frame);
+      _ => self.matched.render(greeter, width, selected),
+    }
   }
+}
 
-  f.render_widget(block, container);
+pub fn menu(greeter: &Greeter) -> Menu<Entry> {
+  Menu {
+    title: menu_title(fl!("title_session"), &greeter.menu_filter),
+    items: visible(greeter)
+      .into_iter()
+      .map(|(index, label, positions)| {
+        let comment = greeter.sessions.get(index).and_then(|session| session.comment.clone());
 
-  Ok((1, 1))
+        (
+          index,
+          Entry {
+            matched: Matched { label, positions },
+            comment,
+          },
+        )
+      })
+      .collect(),
+    selected: greeter.selected_session,
+  }
 }
 
-fn get_option<'g, S>(greeter: &Greeter, name: S, index: usize) -> Span<'g>
-where
-  S: Into<String>,
-{
-  if greeter.selected_session == index {
-    Span::styled(name.into(), Style::default().add_modifier(Modifier::REVERSED))
-  } else {
-    Span::from(name.into())
+#[cfg(test)]
+mod tests {
+  use super::{allowed, resolve, Session};
+
+  fn sessions() -> Vec<Session> {
+    vec![Session::from_command("sway"), Session::from_command("startxfce4")]
+  }
+
+  #[test]
+  fn remembered_session_comes_first() {
+    let command = resolve(&sessions(), Some("gnome-session".to_string()), Some("startxfce4"), Some("sway"));
+
+    assert_eq!(command, Ok(Some("gnome-session".to_string())));
+  }
+
+  #[test]
+  fn default_session_overrides_command() {
+    assert_eq!(resolve(&sessions(), None, Some("startxfce4"), Some("sway")), Ok(Some("startxfce4".to_string())));
+  }
+
+  #[test]
+  fn unknown_default_session_falls_back_to_command() {
+    assert_eq!(resolve(&sessions(), None, Some("GNOME"), Some("sway")), Ok(Some("sway".to_string())));
+    assert!(resolve(&sessions(), None, Some("GNOME"), None).is_err());
+  }
+
+  #[test]
+  fn first_session_is_selected_otherwise() {
+    assert_eq!(resolve(&sessions(), None, None, None), Ok(Some("sway".to_string())));
+    assert_eq!(resolve(&[], None, None, None), Ok(None));
+  }
+
+  #[test]
+  fn sessions_are_allowed_by_name_or_desktop_file() {
+    let session = Session {
+      id: Some("plasmawayland".to_string()),
+      ..Session::from_command("Plasma (Wayland)")
+    };
+
+    assert!(allowed(&session, &[], &[]));
+    assert!(allowed(&session, &["sway".to_string(), "PlasmaWayland".to_string()], &[]));
+    assert!(allowed(&session, &["plasma (wayland)".to_string()], &[]));
+    assert!(!allowed(&session, &["sway".to_string()], &[]));
+  }
+
+  #[test]
+  fn denylist_applies_after_allowlist() {
+    let session = Session::from_command("sway");
+
+    assert!(!allowed(&session, &[], &["Sway".to_string()]));
+    assert!(!allowed(&session, &["sway".to_string()], &["sway".to_string()]));
+    assert!(allowed(&session, &[], &["plasma".to_string()]));
   }
 }
@@ -0,0 +1,33 @@
+use ratatui::{
+  backend::Backend,
+  layout::{Alignment, Rect},
+  text::Span,
+  widgets::{Paragraph, Wrap},
+};
+
+use crate::{
+  ui::{util::get_minimum_size, Frame, Themed},
+  Greeter,
+};
+
+// Returns whether the terminal is too small for the interface, which is then
+// replaced with the required dimensions until it is resized.
+pub fn draw<B: Backend>(greeter: &Greeter, f: &mut Frame<B>) -> bool {
+  let size = f.size();
+  let (width, height) = get_minimum_size(greeter);
+
+  if size.width >= width && size.height >= height {
+    return false;
+  }
+
+  let text = fl!("terminal_too_small", width = size.width, height = size.height, min_width = width, min_height = height);
+  let text = Paragraph::new(Span::styled(text, greeter.theme.of(&[Themed::Text])))
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true });
+
+  let lines = 2.min(size.height);
+
+  f.render_widget(text, Rect::new(size.x, size.y + size.height.saturating_sub(lines) / 2, size.width, lines));
+
+  true
+}
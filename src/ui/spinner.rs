@@ -0,0 +1,31 @@
+#[derive(SmartDefault, Debug, Clone, Copy, PartialEq)]
+pub enum Spinner {
+  #[default]
+  Line,
+  Dots,
+  Arc,
+  None,
+}
+
+impl Spinner {
+  pub fn parse(value: &str) -> Option<Spinner> {
+    match value {
+      "line" => Some(Spinner::Line),
+      "dots" => Some(Spinner::Dots),
+      "arc" => Some(Spinner::Arc),
+      "none" => Some(Spinner::None),
+      _ => None,
+    }
+  }
+
+  pub fn frame(&self, counter: usize) -> Option<&'static str> {
+    let frames: &[&str] = match self {
+      Spinner::Line => &["|", "/", "-", "\\"],
+      Spinner::Dots => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+      Spinner::Arc => &["◜", "◠", "◝", "◞", "◡", "◟"],
+      Spinner::None => return None,
+    };
+
+    Some(frames[counter % frames.len()])
+  }
+}
@@ -0,0 +1,256 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::text::{Span, Spans};
+
+use crate::{
+  info::{capslock_status, numlock_status, NetworkStatus},
+  tasks::Probe,
+  ui::{format_time, status_label, status_value},
+  Greeter, Mode,
+};
+
+pub const DEFAULT_STATUS_LEFT: &str = "keys,command";
+pub const DEFAULT_STATUS_RIGHT: &str = "battery,layout,num,caps";
+const DEFAULT_CLOCK_FORMAT: &str = "%H:%M";
+
+// A segment is an item of the status bar, which can hide itself when it has
+// nothing to display.
+pub trait Segment: Send + Sync {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>>;
+
+  // Returns the key pressed when clicking each of the spans, for segments
+  // acting as buttons.
+  fn keys(&self, _greeter: &Greeter) -> Vec<Option<KeyEvent>> {
+    Vec::new()
+  }
+
+  // What needs to be looked up in the background for the segment to be
+  // displayed.
+  fn probe(&self) -> Option<Probe> {
+    None
+  }
+}
+
+pub type Segments = Vec<Box<dyn Segment>>;
+
+struct Keys;
+struct Command;
+struct Clock;
+struct Network;
+struct Battery;
+struct Layout;
+struct NumLock;
+struct CapsLock;
+struct Widget(usize);
+
+impl Segment for Keys {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    let theme = &greeter.theme;
+
+    let mut spans = vec![status_label(theme, "ESC"), status_value(theme, fl!("action_reset"))];
+
+    // Sessions are neither chosen nor ended from a locker.
+    if !greeter.lock {
+      spans.extend([
+        status_label(theme, greeter.kb_command.to_string()),
+        status_value(theme, fl!("action_command")),
+        status_label(theme, greeter.kb_sessions.to_string()),
+        status_value(theme, fl!("action_session")),
+        status_label(theme, greeter.kb_power.to_string()),
+        status_value(theme, fl!("action_power")),
+      ]);
+    }
+
+    if !greeter.layouts.is_empty() {
+      spans.push(status_label(theme, greeter.kb_layouts.to_string()));
+      spans.push(status_value(theme, fl!("action_layout")));
+    }
+
+    if greeter.locales.len() > 1 {
+      spans.push(status_label(theme, greeter.kb_locale.to_string()));
+      spans.push(status_value(theme, fl!("action_locale")));
+    }
+
+    Some(spans)
+  }
+
+  // Both the key and the label of an action can be clicked.
+  fn keys(&self, greeter: &Greeter) -> Vec<Option<KeyEvent>> {
+    let mut keys = vec![KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)];
+
+    if !greeter.lock {
+      keys.extend([greeter.kb_command.key(), greeter.kb_sessions.key(), greeter.kb_power.key()]);
+    }
+
+    if !greeter.layouts.is_empty() {
+      keys.push(greeter.kb_layouts.key());
+    }
+
+    if greeter.locales.len() > 1 {
+      keys.push(greeter.kb_locale.key());
+    }
+
+    keys.into_iter().flat_map(|key| vec![Some(key), Some(key)]).collect()
+  }
+}
+
+impl Segment for Command {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    if greeter.lock {
+      return None;
+    }
+
+    // While choosing a session, the command of the highlighted one is
+    // displayed in full.
+    let command = match greeter.sessions.get(greeter.selected_session).filter(|_| greeter.mode == Mode::Sessions) {
+      Some(session) => session.full_command(),
+      None => greeter.command.clone().unwrap_or_else(|| "-".to_string()),
+    };
+
+    Some(vec![status_label(&greeter.theme, fl!("status_command")), status_value(&greeter.theme, command)])
+  }
+}
+
+// The clock of the status bar is kept compact, with a format of its own
+// rather than the one of the title bar, in the same time zone.
+impl Segment for Clock {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    let format = greeter.option("status-clock-format").unwrap_or_else(|| DEFAULT_CLOCK_FORMAT.to_string());
+
+    Some(vec![status_value(&greeter.theme, format_time(greeter, greeter.time_zone.as_ref(), Some(&format)))])
+  }
+}
+
+impl Segment for Network {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    let network = match greeter.network.clone()? {
+      NetworkStatus::Down => fl!("network_down"),
+      NetworkStatus::Wired => fl!("network_wired"),
+      NetworkStatus::Wireless(None) => fl!("network_wireless"),
+      NetworkStatus::Wireless(Some(ssid)) => fl!("network_wireless_ssid", ssid = ssid),
+    };
+
+    Some(vec![status_label(&greeter.theme, fl!("status_network")), status_value(&greeter.theme, network)])
+  }
+
+  fn probe(&self) -> Option<Probe> {
+    Some(Probe::Network)
+  }
+}
+
+impl Segment for Battery {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    let battery = greeter.battery?;
+
+    let capacity = match battery.charging {
+      true => fl!("battery_charging", capacity = battery.capacity),
+      false => fl!("battery", capacity = battery.capacity),
+    };
+
+    Some(vec![status_label(&greeter.theme, fl!("status_battery")), status_value(&greeter.theme, capacity)])
+  }
+
+  fn probe(&self) -> Option<Probe> {
+    Some(Probe::Battery)
+  }
+}
+
+impl Segment for Layout {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    let layout = greeter.layouts.get(greeter.current_layout)?;
+
+    Some(vec![status_label(&greeter.theme, fl!("status_layout")), status_value(&greeter.theme, layout)])
+  }
+}
+
+impl Segment for NumLock {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    numlock_status().then(|| vec![status_label(&greeter.theme, fl!("status_num"))])
+  }
+}
+
+impl Segment for CapsLock {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    capslock_status().then(|| vec![status_label(&greeter.theme, fl!("status_caps"))])
+  }
+}
+
+impl Segment for Widget {
+  fn spans(&self, greeter: &Greeter) -> Option<Vec<Span<'static>>> {
+    let output = greeter.widgets.get(self.0)?.output.clone()?;
+
+    Some(vec![status_value(&greeter.theme, output)])
+  }
+}
+
+// Parses a comma-separated list of segment names, widgets being numbered from
+// 1 in the order they were given, "widget" standing for the first one.
+pub fn parse(spec: &str) -> Result<Segments, String> {
+  spec
+    .split(',')
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+    .map(|name| {
+      let segment: Box<dyn Segment> = match name {
+        "keys" => Box::new(Keys),
+        "command" => Box::new(Command),
+        "clock" => Box::new(Clock),
+        "network" => Box::new(Network),
+        "battery" => Box::new(Battery),
+        "layout" => Box::new(Layout),
+        "num" => Box::new(NumLock),
+        "caps" => Box::new(CapsLock),
+        "widget" => Box::new(Widget(0)),
+        name if name.starts_with("widget") => match name["widget".len()..].parse::<usize>() {
+          Ok(number) if number > 0 => Box::new(Widget(number - 1)),
+          _ => return Err(format!("unknown status bar segment '{name}'")),
+        },
+        name => return Err(format!("unknown status bar segment '{name}'")),
+      };
+
+      Ok(segment)
+    })
+    .collect()
+}
+
+// A clickable span, at the given offset from the start of the status bar.
+pub struct Button {
+  pub offset: u16,
+  pub width: u16,
+  pub key: KeyEvent,
+}
+
+// Renders the visible segments, separated by a space, along with their
+// clickable spans.
+pub fn render(greeter: &Greeter, segments: &Segments) -> Option<(Spans<'static>, Vec<Button>)> {
+  let mut spans: Vec<Span<'static>> = Vec::new();
+  let mut buttons = Vec::new();
+
+  for segment in segments {
+    if let Some(segment_spans) = segment.spans(greeter) {
+      if !spans.is_empty() {
+        spans.push(Span::from(" "));
+      }
+
+      let keys = segment.keys(greeter);
+
+      for (index, span) in segment_spans.into_iter().enumerate() {
+        if let Some(Some(key)) = keys.get(index) {
+          let offset = spans.iter().map(Span::width).sum::<usize>();
+
+          buttons.push(Button {
+            offset: offset as u16,
+            width: span.width() as u16,
+            key: *key,
+          });
+        }
+
+        spans.push(span);
+      }
+    }
+  }
+
+  match spans.is_empty() {
+    true => None,
+    false => Some((Spans::from(spans), buttons)),
+  }
+}
@@ -0,0 +1,179 @@
+use std::{error::Error, fs, path::Path};
+
+use ratatui::style::{Color, Style};
+
+use crate::ui::ColorMode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Themed {
+  Background,
+  Container,
+  Time,
+  Text,
+  Border,
+  Title,
+  Greet,
+  Prompt,
+  Input,
+  Action,
+  ActionButton,
+  Error,
+  Banner,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Theme {
+  background: Option<Color>,
+  container: Option<Color>,
+  time: Option<Color>,
+  text: Option<Color>,
+  border: Option<Color>,
+  title: Option<Color>,
+  greet: Option<Color>,
+  prompt: Option<Color>,
+  input: Option<Color>,
+  action: Option<Color>,
+  button: Option<Color>,
+  error: Option<Color>,
+  banner: Option<Color>,
+}
+
+impl Theme {
+  // A theme can be given inline or as a path to a file with the same syntax,
+  // where components may also be separated by newlines.
+  pub fn load(spec: &str) -> Result<Theme, Box<dyn Error>> {
+    if Path::new(spec).is_file() {
+      let contents = fs::read_to_string(spec)?;
+
+      return Theme::parse(&contents.lines().collect::<Vec<_>>().join(";"));
+    }
+
+    Theme::parse(spec)
+  }
+
+  pub fn parse(spec: &str) -> Result<Theme, Box<dyn Error>> {
+    let mut theme = Theme::default();
+
+    for directive in spec.split(';').map(str::trim).filter(|directive| !directive.is_empty()) {
+      let (component, color) = directive.split_once('=').ok_or_else(|| format!("invalid theme directive '{directive}'"))?;
+
+      theme.set(component.trim(), color.trim())?;
+    }
+
+    Ok(theme)
+  }
+
+  pub fn set(&mut self, component: &str, color: &str) -> Result<(), Box<dyn Error>> {
+    let color = Some(parse_color(color)?);
+
+    match component {
+      "background" => self.background = color,
+      "container" => self.container = color,
+      "time" => self.time = color,
+      "text" => self.text = color,
+      "border" => self.border = color,
+      "title" => self.title = color,
+      "greet" => self.greet = color,
+      "prompt" => self.prompt = color,
+      "input" => self.input = color,
+      "action" => self.action = color,
+      "button" => self.button = color,
+      "error" => self.error = color,
+      "banner" => self.banner = color,
+      component => return Err(format!("unknown theme component '{component}'").into()),
+    }
+
+    Ok(())
+  }
+
+  // Colors the terminal cannot display are replaced with the closest ones it
+  // can.
+  pub fn degrade(&mut self, mode: ColorMode) {
+    let colors = [
+      &mut self.background,
+      &mut self.container,
+      &mut self.time,
+      &mut self.text,
+      &mut self.border,
+      &mut self.title,
+      &mut self.greet,
+      &mut self.prompt,
+      &mut self.input,
+      &mut self.action,
+      &mut self.button,
+      &mut self.error,
+      &mut self.banner,
+    ];
+
+    for color in colors {
+      *color = color.map(|color| mode.degrade(color));
+    }
+  }
+
+  // Returns the style for the first component, in order, that was assigned a
+  // color, falling back on the default style.
+  pub fn of(&self, components: &[Themed]) -> Style {
+    let color = components.iter().find_map(|component| self.color(*component));
+
+    match (components.first(), color) {
+      (Some(Themed::Background | Themed::Container), Some(color)) => Style::default().bg(color),
+      (_, Some(color)) => Style::default().fg(color),
+      (_, None) => Style::default(),
+    }
+  }
+
+  fn color(&self, component: Themed) -> Option<Color> {
+    match component {
+      Themed::Background => self.background,
+      Themed::Container => self.container,
+      Themed::Time => self.time,
+      Themed::Text => self.text,
+      Themed::Border => self.border,
+      Themed::Title => self.title,
+      Themed::Greet => self.greet,
+      Themed::Prompt => self.prompt,
+      Themed::Input => self.input,
+      Themed::Action => self.action,
+      Themed::ActionButton => self.button,
+      Themed::Error => self.error,
+      Themed::Banner => self.banner,
+    }
+  }
+}
+
+fn parse_color(color: &str) -> Result<Color, Box<dyn Error>> {
+  let color = match color.to_lowercase().replace(['-', '_'], "").as_str() {
+    "black" => Color::Black,
+    "red" => Color::Red,
+    "green" => Color::Green,
+    "yellow" => Color::Yellow,
+    "blue" => Color::Blue,
+    "magenta" => Color::Magenta,
+    "cyan" => Color::Cyan,
+    "gray" | "grey" => Color::Gray,
+    "darkgray" | "darkgrey" => Color::DarkGray,
+    "lightred" => Color::LightRed,
+    "lightgreen" => Color::LightGreen,
+    "lightyellow" => Color::LightYellow,
+    "lightblue" => Color::LightBlue,
+    "lightmagenta" => Color::LightMagenta,
+    "lightcyan" => Color::LightCyan,
+    "white" => Color::White,
+
+    hex if hex.starts_with('#') && hex.len() == 7 && hex.is_ascii() => {
+      let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16);
+
+      match (channel(1), channel(3), channel(5)) {
+        (Ok(r), Ok(g), Ok(b)) => Color::Rgb(r, g, b),
+        _ => return Err(format!("invalid color '{color}'").into()),
+      }
+    }
+
+    index => match index.parse::<u8>() {
+      Ok(index) => Color::Indexed(index),
+      Err(_) => return Err(format!("invalid color '{color}'").into()),
+    },
+  };
+
+  Ok(color)
+}
@@ -1,57 +1,105 @@
-use std::error::Error;
-
 use ratatui::{
-  layout::Rect,
-  style::{Modifier, Style},
-  text::Span,
-  widgets::{Block, BorderType, Borders, Paragraph},
+  style::{Color, Modifier, Style},
+  text::{Span, Spans},
 };
 
 use crate::{
-  ui::{util::*, Frame},
+  ui::{
+    menu::{Matched, Menu, MenuItem},
+    util::*,
+  },
   Greeter,
 };
 
-pub fn draw(greeter: &mut Greeter, f: &mut Frame) -> Result<(u16, u16), Box<dyn Error>> {
-  let size = f.size();
+const AVATAR_COLORS: [Color; 6] = [Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan];
 
-  let width = greeter.width();
-  let height: u16 = get_height(greeter) + greeter.users.len() as u16;
-  let x = (size.width - width) / 2;
-  let y = (size.height - height) / 2;
+pub fn label(username: &str, name: &Option<String>) -> String {
+  match name {
+    Some(name) => format!("{name} ({username})"),
+    None => username.to_string(),
+  }
+}
 
-  let container = Rect::new(x, y, width, height);
+// Returns the users matching the current filter, along with their label and
+// the position of the matched characters.
+pub fn visible(greeter: &Greeter) -> Vec<(usize, String, Vec<usize>)> {
+  greeter
+    .users
+    .iter()
+    .enumerate()
+    .filter_map(|(index, (username, name))| {
+      let label = label(username, name);
 
-  let title = Span::from(titleize(&fl!("title_users")));
-  let block = Block::default().title(title).borders(Borders::ALL).border_type(BorderType::Plain);
+      fuzzy_match(&greeter.menu_filter, &label).map(|positions| (index, label, positions))
+    })
+    .collect()
+}
 
-  for (index, (username, name)) in greeter.users.iter().enumerate() {
-    let name = match name {
-      Some(name) => format!("{name} ({username})"),
-      None => username.clone(),
-    };
+// An entry of the user menu, preceded by a placeholder for the picture of the
+// user when avatars are enabled.
+pub struct User {
+  avatar: Option<Span<'static>>,
+  matched: Matched,
+}
 
-    let name = format!("{:1$}", name, greeter.width() as usize - 4);
+impl MenuItem for User {
+  fn render(&self, greeter: &Greeter, width: usize, selected: bool) -> Spans<'static> {
+    match &self.avatar {
+      Some(avatar) => {
+        let mut spans = vec![avatar.clone(), Span::raw(" ")];
+        spans.extend(self.matched.render(greeter, width.saturating_sub(avatar.width() + 1), selected).0);
 
-    let frame = Rect::new(x + 2, y + 2 + index as u16, width, 1);
-    let option_text = get_option(greeter, name, index);
-    let option = Paragraph::new(option_text);
+        Spans::from(spans)
+      }
 
-    f.render_widget(option, frame);
+      None => self.matched.render(greeter, width, selected),
+    }
   }
+}
 
-  f.render_widget(block, container);
+// Users are represented by their initials, on a color derived from their
+// username so that it stays the same from one login to the next.
+fn avatar(greeter: &Greeter, username: &str, name: &Option<String>) -> Span<'static> {
+  let words = match name {
+    Some(name) => name.split_whitespace().collect::<Vec<_>>(),
+    None => username.split(['.', '-', '_'].as_ref()).filter(|word| !word.is_empty()).collect(),
+  };
 
-  Ok((1, 1))
+  let initials = match words.as_slice() {
+    [word] => word.chars().take(2).collect::<String>(),
+    words => words.iter().filter_map(|word| word.chars().next()).take(2).collect(),
+  };
+
+  let style = match greeter.high_contrast {
+    true => Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+
+    false => {
+      let color = AVATAR_COLORS[username.bytes().map(usize::from).sum::<usize>() % AVATAR_COLORS.len()];
+
+      Style::default().fg(Color::Black).bg(color).add_modifier(Modifier::BOLD)
+    }
+  };
+
+  Span::styled(format!(" {} ", pad_to_width(&initials.to_uppercase(), 2)), style)
 }
 
-fn get_option<'g, S>(greeter: &Greeter, name: S, index: usize) -> Span<'g>
-where
-  S: Into<String>,
-{
-  if greeter.selected_user == index {
-    Span::styled(name.into(), Style::default().add_modifier(Modifier::REVERSED))
-  } else {
-    Span::from(name.into())
+pub fn menu(greeter: &Greeter) -> Menu<User> {
+  Menu {
+    title: menu_title(fl!("title_users"), &greeter.menu_filter),
+    items: visible(greeter)
+      .into_iter()
+      .map(|(index, label, positions)| {
+        let avatar = greeter.users.get(index).filter(|_| greeter.user_avatars).map(|(username, name)| avatar(greeter, username, name));
+
+        (
+          index,
+          User {
+            avatar,
+            matched: Matched { label, positions },
+          },
+        )
+      })
+      .collect(),
+    selected: greeter.selected_user,
   }
 }
@@ -1,18 +1,204 @@
-use crate::{Greeter, Mode};
+use ratatui::{
+  buffer::Buffer,
+  layout::{Alignment, Rect},
+  style::{Modifier, Style},
+  text::{Span, Spans},
+  widgets::{Block, BorderType, Widget},
+};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+  ui::{banner::get_banner_height, bidi},
+  Animation, Greeter, IdentityPosition, MessageLevel, Mode, WindowPosition,
+};
+
+pub const MESSAGES_MAX_HEIGHT: usize = 5;
+pub const STEPS_MAX_HEIGHT: usize = 4;
+const SLIDE_COLUMNS: f32 = 8.0;
 
 pub fn titleize(message: &str) -> String {
   format!(" {message} ")
 }
 
+// Case-insensitive subsequence matching, returning the position of every
+// matched character in the text.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Vec<usize>> {
+  let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+  let mut positions = Vec::new();
+
+  for (index, c) in text.chars().enumerate() {
+    match pattern.peek() {
+      Some(expected) if c.to_lowercase().next() == Some(*expected) => {
+        positions.push(index);
+        pattern.next();
+      }
+
+      Some(_) => {}
+      None => break,
+    }
+  }
+
+  match pattern.peek() {
+    None => Some(positions),
+    Some(_) => None,
+  }
+}
+
+pub fn highlight_matches<'s>(text: &str, positions: &[usize], style: Style) -> Spans<'s> {
+  let spans = text
+    .chars()
+    .enumerate()
+    .map(|(index, c)| match positions.contains(&index) {
+      true => Span::styled(c.to_string(), style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)),
+      false => Span::styled(c.to_string(), style),
+    })
+    .collect::<Vec<_>>();
+
+  Spans::from(spans)
+}
+
+pub fn menu_title(title: String, filter: &str) -> String {
+  match filter.is_empty() {
+    true => titleize(&title),
+    false => titleize(&format!("{title}: {filter}")),
+  }
+}
+
+// Draws the block around a container, with thicker lines for high contrast,
+// or ASCII characters for consoles and braille displays without box-drawing
+// characters.
+pub struct Container<'b> {
+  block: Block<'b>,
+  ascii: bool,
+}
+
+impl<'b> Container<'b> {
+  pub fn new(greeter: &Greeter, block: Block<'b>) -> Container<'b> {
+    let border_type = match greeter.high_contrast && !greeter.ascii {
+      true => BorderType::Thick,
+      false => BorderType::Plain,
+    };
+
+    Container {
+      block: block.border_type(border_type),
+      ascii: greeter.ascii,
+    }
+  }
+}
+
+impl<'b> Widget for Container<'b> {
+  fn render(self, area: Rect, buf: &mut Buffer) {
+    self.block.render(area, buf);
+
+    if !self.ascii {
+      return;
+    }
+
+    for y in area.top()..area.bottom() {
+      for x in area.left()..area.right() {
+        if y != area.top() && y != area.bottom() - 1 && x != area.left() && x != area.right() - 1 {
+          continue;
+        }
+
+        let cell = buf.get_mut(x, y);
+
+        let symbol = match cell.symbol.as_str() {
+          "┌" | "┐" | "└" | "┘" => "+",
+          "─" => "-",
+          "│" => "|",
+          _ => continue,
+        };
+
+        cell.set_symbol(symbol);
+      }
+    }
+  }
+}
+
+// The container of the current mode along with the title and status bars,
+// menus and messages being scrolled when they do not fit.
+pub fn get_minimum_size(greeter: &Greeter) -> (u16, u16) {
+  let padding = 2 * greeter.window_padding();
+
+  // Apart from prompts, containers do not account for their content.
+  let content = match greeter.mode {
+    Mode::Username | Mode::Password | Mode::Command | Mode::Environment => 0,
+    _ => 2,
+  };
+
+  (greeter.width() + padding, get_height(greeter) + content + 2 + get_notifications_height(greeter) + padding)
+}
+
 pub fn should_hide_cursor(greeter: &Greeter) -> bool {
-  greeter.working
+  greeter.idle
+    || greeter.working
     || greeter.done
     || (greeter.user_menu && greeter.mode == Mode::Username && greeter.username.is_empty())
     || (greeter.mode == Mode::Password && greeter.prompt.is_none())
     || greeter.mode == Mode::Users
     || greeter.mode == Mode::Sessions
     || greeter.mode == Mode::Power
+    || greeter.mode == Mode::PowerConfirm
+    || greeter.mode == Mode::Layouts
+    || greeter.mode == Mode::Locale
     || greeter.mode == Mode::Processing
+    || greeter.mode == Mode::Autologin
+    || greeter.mode == Mode::Lockout
+}
+
+// Returns the origin of a container of the given size, which is kept clear of
+// the title bar, banner and status bar when placed along an edge of the screen.
+pub fn get_container_position(greeter: &Greeter, size: Rect, width: u16, height: u16) -> (u16, u16) {
+  let padding = greeter.window_padding() + greeter.window_margin;
+  let max_x = size.width.saturating_sub(width);
+  let max_y = size.height.saturating_sub(height);
+
+  let left = padding.min(max_x);
+  let center = max_x / 2;
+  let right = max_x.saturating_sub(padding).max(left);
+
+  let top = (padding + 1 + get_banner_height(greeter)).min(max_y);
+  let middle = max_y / 2;
+  let bottom = max_y.saturating_sub(padding + 1 + get_notifications_height(greeter)).max(top);
+
+  let (x, y) = match greeter.window_position {
+    WindowPosition::TopLeft => (left, top),
+    WindowPosition::Top => (center, top),
+    WindowPosition::TopRight => (right, top),
+    WindowPosition::Left => (left, middle),
+    WindowPosition::Center => (center, middle),
+    WindowPosition::Right => (right, middle),
+    WindowPosition::BottomLeft => (left, bottom),
+    WindowPosition::Bottom => (center, bottom),
+    WindowPosition::BottomRight => (right, bottom),
+    WindowPosition::At(x, y) => (x.min(max_x), y.min(max_y)),
+  };
+
+  (((x as i32) + get_slide_offset(greeter)).clamp(0, max_x as i32) as u16, y)
+}
+
+// Containers slide in quickly at first, then slow down as they reach their
+// position.
+fn get_slide_offset(greeter: &Greeter) -> i32 {
+  match (greeter.animation, greeter.transition, greeter.transition_progress()) {
+    (Animation::Slide, Some((_, direction)), Some(progress)) => direction * (SLIDE_COLUMNS * (1.0 - progress).powi(2)).ceil() as i32,
+    _ => 0,
+  }
+}
+
+pub fn get_notifications_height(greeter: &Greeter) -> u16 {
+  match greeter.notifications.is_empty() {
+    true => 0,
+    false => 1,
+  }
+}
+
+// Questions answered earlier in the conversation each take a row, spaced as
+// the username is from the current one, only the latest ones being displayed
+// in long conversations.
+pub fn get_steps_height(greeter: &Greeter) -> u16 {
+  greeter.steps.len().min(STEPS_MAX_HEIGHT) as u16 * (1 + greeter.prompt_padding())
 }
 
 pub fn get_height(greeter: &Greeter) -> u16 {
@@ -21,27 +207,108 @@ pub fn get_height(greeter: &Greeter) -> u16 {
   let prompt_padding = greeter.prompt_padding();
 
   let initial = match greeter.mode {
-    Mode::Username | Mode::Command => (2 * container_padding) + 1,
-    Mode::Password => match greeter.prompt {
-      Some(_) => (2 * container_padding) + prompt_padding + 2,
-      None => (2 * container_padding) + 1,
+    Mode::Username | Mode::Command | Mode::Environment => (2 * container_padding) + 1,
+    // Kiosks only display the answer.
+    Mode::Password => match (&greeter.prompt, &greeter.kiosk_user) {
+      (_, None) => (2 * container_padding) + prompt_padding + 2 + get_steps_height(greeter),
+      (_, Some(_)) => (2 * container_padding) + 1 + get_steps_height(greeter),
     },
-    Mode::Users | Mode::Sessions | Mode::Power | Mode::Processing => (2 * container_padding),
+    Mode::Users | Mode::Sessions | Mode::Power | Mode::PowerConfirm | Mode::Layouts | Mode::Locale | Mode::Processing | Mode::Autologin | Mode::Lockout => 2 * container_padding,
   };
 
   match greeter.mode {
-    Mode::Command | Mode::Sessions | Mode::Power | Mode::Processing => initial,
-    _ => initial + greeting_height,
+    Mode::Command | Mode::Environment | Mode::Sessions | Mode::Power | Mode::PowerConfirm | Mode::Layouts | Mode::Locale | Mode::Processing | Mode::Autologin | Mode::Lockout => initial,
+    _ => match get_message_height(greeter) {
+      0 => initial + greeting_height,
+      height => initial + greeting_height + prompt_padding.max(1) + height,
+    },
   }
 }
 
+// Widths are counted in terminal columns, wide characters taking two of them
+// and combining characters none.
+pub fn text_width(text: &str) -> u16 {
+  text.width() as u16
+}
+
+// One-time codes are displayed in groups of three digits, which are easier to
+// compare with the code shown on another device.
+pub fn group_digits(text: &str) -> String {
+  let chars = text.chars().collect::<Vec<_>>();
+
+  chars.chunks(3).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+}
+
+// Pads the given text with spaces up to the given number of columns.
+pub fn pad_to_width(text: &str, width: usize) -> String {
+  let padding = width.saturating_sub(text.width());
+
+  format!("{text}{}", " ".repeat(padding))
+}
+
 pub fn get_input_width(greeter: &Greeter, label: &Option<String>) -> u16 {
   let label_width = match label {
     None => 0,
-    Some(label) => label.chars().count(),
+    Some(label) => text_width(label),
   };
 
-  greeter.width() - label_width as u16 - 4 - 1
+  greeter.width().saturating_sub(label_width + 4 + 1)
+}
+
+// Returns the column of the cursor from the start of the given value as it is
+// displayed, each character being displayed with the given width if it is
+// masked.
+pub fn get_cursor_column(greeter: &mut Greeter, value: &str, mask_width: Option<u16>) -> i32 {
+  let length = value.chars().count();
+  let offset = get_cursor_offset(greeter, length) as usize;
+
+  match mask_width {
+    Some(width) if greeter.rtl => ((length - offset) as u16 * width) as i32 - 1,
+    Some(width) => (offset as u16 * width) as i32,
+    None => bidi::cursor_column(value, offset, greeter.rtl),
+  }
+}
+
+// Returns the column of the cursor placed at the end of a value of the given
+// width.
+pub fn get_end_column(greeter: &Greeter, width: u16) -> i32 {
+  match greeter.rtl {
+    true => -1,
+    false => width as i32,
+  }
+}
+
+// Returns the position of the cursor at the given column of a value drawn in
+// the given area, values being aligned next to their label.
+pub fn get_input_cursor(greeter: &Greeter, area: Rect, value: &str, column: i32) -> u16 {
+  let start = match greeter.rtl {
+    true => area.right().saturating_sub(text_width(value)),
+    false => area.x,
+  };
+
+  (start as i32 + 1 + column).max(0) as u16
+}
+
+// Lays out the given text in the order it is displayed in.
+pub fn reorder(greeter: &Greeter, text: &str) -> String {
+  bidi::reorder(text, greeter.rtl)
+}
+
+// When text flows from right to left, labels are placed on the right of the
+// container and inputs on their left.
+pub fn mirror_area(greeter: &Greeter, container: Rect, area: Rect) -> Rect {
+  match greeter.rtl {
+    true => Rect::new((container.x + container.right()).saturating_sub(area.right()), area.y, area.width, area.height),
+    false => area,
+  }
+}
+
+pub fn mirror_alignment(greeter: &Greeter, alignment: Alignment) -> Alignment {
+  match (greeter.rtl, alignment) {
+    (true, Alignment::Left) => Alignment::Right,
+    (true, Alignment::Right) => Alignment::Left,
+    (_, alignment) => alignment,
+  }
 }
 
 pub fn get_cursor_offset(greeter: &mut Greeter, length: usize) -> i16 {
@@ -60,26 +327,93 @@ pub fn get_cursor_offset(greeter: &mut Greeter, length: usize) -> i16 {
   offset
 }
 
-pub fn get_greeting_height(greeter: &Greeter, padding: u16, fallback: u16) -> (Option<String>, u16) {
-  if let Some(greeting) = &greeter.greeting {
-    let width = greeter.width();
-    let wrapped = textwrap::fill(greeting, (width - (2 * padding)) as usize);
-    let height = wrapped.trim_end().matches('\n').count();
+// Each line of the greeting is wrapped on its own, keeping the alignment
+// given by its marker, if any.
+pub fn get_greeting_height(greeter: &Greeter, padding: u16, fallback: u16) -> (Option<Vec<(Alignment, String)>>, u16) {
+  let identity = match &greeter.identity {
+    Some((IdentityPosition::Prompt, identity)) => Some(identity),
+    _ => None,
+  };
+
+  if identity.is_some() || greeter.greeting.is_some() {
+    let width = greeter.width().saturating_sub(2 * padding).max(1) as usize;
 
-    (Some(wrapped), height as u16 + 2)
+    let lines = identity
+      .into_iter()
+      .chain(&greeter.greeting)
+      .flat_map(|text| text.trim_end().lines())
+      .flat_map(|line| {
+        let (alignment, line) = split_alignment(line);
+
+        textwrap::wrap(line, width).into_iter().map(move |line| (alignment, line.to_string()))
+      })
+      .collect::<Vec<_>>();
+
+    let height = lines.len() as u16 + 1;
+
+    (Some(lines), height)
   } else {
     (None, fallback)
   }
 }
 
-pub fn get_message_height(greeter: &Greeter, padding: u16, fallback: u16) -> (Option<String>, u16) {
-  if let Some(message) = &greeter.message {
-    let width = greeter.width();
-    let wrapped = textwrap::fill(message.trim_end(), width as usize - 4);
-    let height = wrapped.trim_end().matches('\n').count();
+pub fn split_alignment(line: &str) -> (Alignment, &str) {
+  for (marker, alignment) in [("%left%", Alignment::Left), ("%center%", Alignment::Center), ("%right%", Alignment::Right)] {
+    if let Some(line) = line.strip_prefix(marker) {
+      return (alignment, line);
+    }
+  }
 
-    (Some(wrapped), height as u16 + padding)
-  } else {
-    (None, fallback)
+  (Alignment::Center, line)
+}
+
+// Replaces every `%name%` with the value provided for that variable, leaving
+// unknown variables untouched.
+pub fn expand_template<F>(text: &str, variable: F) -> String
+where
+  F: Fn(&str) -> Option<String>,
+{
+  let mut output = String::with_capacity(text.len());
+  let mut rest = text;
+
+  while let Some(start) = rest.find('%') {
+    output.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let value = rest[1..].find('%').and_then(|end| variable(&rest[1..end + 1]).map(|value| (value, end + 2)));
+
+    match value {
+      Some((value, length)) => {
+        output.push_str(&value);
+        rest = &rest[length..];
+      }
+
+      None => {
+        output.push('%');
+        rest = &rest[1..];
+      }
+    }
   }
+
+  output.push_str(rest);
+  output
+}
+
+pub fn get_message_lines(greeter: &Greeter) -> Vec<(MessageLevel, String)> {
+  let width = greeter.width().saturating_sub(2 * greeter.container_padding()).max(1);
+
+  greeter
+    .messages
+    .iter()
+    .flat_map(|message| {
+      textwrap::wrap(message.text.trim_end(), width as usize)
+        .into_iter()
+        .map(move |line| (message.level, reorder(greeter, &line)))
+    })
+    .collect()
+}
+
+// The message pane is limited to a few lines so the prompt stays in view.
+pub fn get_message_height(greeter: &Greeter) -> u16 {
+  get_message_lines(greeter).len().min(MESSAGES_MAX_HEIGHT) as u16
 }
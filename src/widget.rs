@@ -0,0 +1,88 @@
+use std::{process::Stdio, time::Duration};
+
+use tokio::{process::Command, time};
+
+const DEFAULT_INTERVAL: u64 = 60;
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_WIDTH: usize = 60;
+
+// A command whose output is displayed in the status bar and refreshed at the
+// given interval. The last output is kept while the command fails.
+#[derive(Debug, Clone)]
+pub struct Widget {
+  pub command: String,
+  pub interval: Duration,
+  pub output: Option<String>,
+}
+
+impl Widget {
+  // Widgets are given as CMD:SECONDS, the interval being optional so that
+  // commands containing colons can still be given on their own.
+  pub fn parse(spec: &str) -> Result<Widget, String> {
+    let (command, interval) = match spec.rsplit_once(':').map(|(command, interval)| (command, interval.trim().parse::<u64>())) {
+      Some((command, Ok(interval))) => (command, interval),
+      _ => (spec, DEFAULT_INTERVAL),
+    };
+
+    if command.trim().is_empty() {
+      return Err(format!("no command given in widget '{spec}'"));
+    }
+
+    if interval == 0 {
+      return Err(format!("the interval of widget '{spec}' must be a positive number of seconds"));
+    }
+
+    Ok(Widget {
+      command: command.trim().to_string(),
+      interval: Duration::from_secs(interval),
+      output: None,
+    })
+  }
+}
+
+// Runs the command through the shell, returning the first line it printed,
+// without the escape sequences and control characters which would mess up the
+// status bar. Nothing is returned if it fails or takes too long.
+pub async fn run(script: &str) -> Option<String> {
+  let mut command = Command::new("sh");
+  command.arg("-c").arg(script);
+  command.stdin(Stdio::null());
+  command.stderr(Stdio::null());
+  command.kill_on_drop(true);
+
+  let output = time::timeout(TIMEOUT, command.output()).await.ok()?.ok()?;
+
+  if !output.status.success() {
+    return None;
+  }
+
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let line = sanitize(stdout.lines().find(|line| !line.trim().is_empty())?);
+
+  match line.chars().count() > MAX_WIDTH {
+    true => Some(format!("{}…", line.chars().take(MAX_WIDTH - 1).collect::<String>())),
+    false => Some(line),
+  }
+}
+
+fn sanitize(line: &str) -> String {
+  let mut sanitized = String::new();
+  let mut chars = line.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      // Control sequences end with a character between @ and ~.
+      '\x1b' => {
+        if chars.next() == Some('[') {
+          chars.by_ref().find(|c| ('@'..='~').contains(c));
+        }
+      }
+
+      '\t' => sanitized.push(' '),
+      c if c.is_control() => {}
+      c => sanitized.push(c),
+    }
+  }
+
+  sanitized.trim().to_string()
+}